@@ -12,13 +12,27 @@ fn main() {
         .build(&event_loop)
         .expect("Failed to create an instance of Window.");
 
-    let _engine = engine::Engine::new(&window).unwrap();
+    let mut engine = engine::Engine::new(&window, Default::default()).unwrap();
 
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent { window_id, event } if window_id == window.id() => match event {
             WindowEvent::CloseRequested => control_flow.set_exit(),
+            WindowEvent::Resized(size) => engine.resize(size).unwrap(),
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                engine.resize(*new_inner_size).unwrap()
+            }
             _ => (),
         },
+        Event::MainEventsCleared => window.request_redraw(),
+        Event::RedrawRequested(window_id) if window_id == window.id() => {
+            engine.draw().unwrap();
+
+            for event in engine.poll_events() {
+                if event == engine::EngineEvent::DeviceRecovered {
+                    window.set_title("Test Application (recovered from a GPU device loss)");
+                }
+            }
+        }
         _ => (),
     });
 }
@@ -13,8 +13,21 @@ pub enum EngineError {
     DeviceCreationFailed(String),
     #[error("Shader Error: {0}")]
     ShaderError(String),
+    #[error("Vulkan Device was lost.")]
+    DeviceLost,
     #[error("Vulkan API Error: {0}.")]
-    VulkanApiError(#[from] Result),
+    VulkanApiError(Result),
     #[error("{0}.")]
     UnknownError(String),
 }
+
+/// Device loss is kept apart from other API errors, the renderer can recover from it.
+impl From<Result> for EngineError {
+    #[inline(always)]
+    fn from(result: Result) -> Self {
+        match result {
+            Result::ERROR_DEVICE_LOST => Self::DeviceLost,
+            result => Self::VulkanApiError(result),
+        }
+    }
+}
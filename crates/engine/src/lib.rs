@@ -7,19 +7,166 @@ mod renderer;
 
 use std::hash::BuildHasher;
 
-use error::*;
 use logging::*;
 
+pub use ash::vk;
+pub use common::Id;
+pub use error::{EngineError, EngineResult};
+pub use renderer::{
+    BindlessKind, BlendState, BufferDesc, CapabilityReport, CommandRecorder, ComputePipelineDesc,
+    Context, DebugLabel, DepthStencilState, DescriptorBinding, DescriptorWriter, DeviceFeature,
+    DeviceRequirements, DeviceSelection, EnabledFeatures, GpuFrameTimings, GpuZone, GpuZoneTiming,
+    GraphicsPipelineDesc, HeapStatistics, ImageDesc, ImageKind, InvalidatedObjects, MemoryLocation,
+    OutputColorSpace, OutputFormat, Pipeline, QueueKind, QueueLocation, QueueTopology,
+    RasterizationState, RecordedCommandBuffer, RendererConfig, SamplerDesc, Shader, StrictMode,
+    SurfaceFormat, ValidationFeatures, ValidationPolicy, ValidationSeverity, ValidationStats,
+    VsyncMode,
+};
+
+/// #### Description
+/// Something the application may want to react to, e.g. by showing a message.
+/// Collected with `Engine::poll_events`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineEvent {
+    /// The GPU device was lost, e.g. after a driver crash or a GPU reset.
+    DeviceLost,
+    /// The device was recreated after a loss. Buffers and images keep their Ids,
+    /// but their contents must be uploaded again.
+    DeviceRecovered,
+    /// Follows `DeviceRecovered` when objects created before the loss must be requested again,
+    /// e.g. pipelines built from descriptions with the old shader modules.
+    ObjectsInvalidated(InvalidatedObjects),
+}
+
 pub struct Engine<'a> {
     renderer: renderer::Renderer<'a>,
+    events: Vec<EngineEvent>,
     _logging: Logging,
 }
 
-impl Engine<'_> {
+impl<'a> Engine<'a> {
     const ENGINE_LOG_DIRECTORY: &str = "logs";
     const ENGINE_LOG_NAME: &str = "engine.log";
 
-    pub fn new(window: &winit::window::Window) -> EngineResult<Self> {
+    pub fn new(window: &winit::window::Window, config: RendererConfig) -> EngineResult<Self> {
+        Self::init(|| renderer::Renderer::new(window, &config))
+    }
+
+    /// #### Description
+    /// Creates the Engine without a window, frames are rendered into offscreen images.
+    /// Useful for machines without a display, e.g. for automated tests.
+    pub fn new_headless(width: u32, height: u32, config: RendererConfig) -> EngineResult<Self> {
+        Self::init(|| renderer::Renderer::new_headless(width, height, &config))
+    }
+
+    #[inline]
+    pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) -> EngineResult<()> {
+        let result = self.renderer.resize(size.width, size.height);
+
+        self.recover_if_device_lost(result)
+    }
+
+    /// #### Description
+    /// Renders a frame. When the device is lost it's recreated and `EngineEvent::DeviceLost`
+    /// with `EngineEvent::DeviceRecovered` are queued, the error is returned only if recovery fails.
+    #[inline]
+    pub fn draw(&mut self) -> EngineResult<()> {
+        let result = self.renderer.draw_frame();
+
+        self.recover_if_device_lost(result)
+    }
+
+    /// #### Description
+    /// Events which happened since the last call, in order.
+    #[inline]
+    pub fn poll_events(&mut self) -> impl Iterator<Item = EngineEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    /// #### Description
+    /// Usage of every GPU memory heap, useful for debugging overlays and bug reports.
+    #[inline]
+    pub fn memory_statistics(&self) -> Vec<HeapStatistics> {
+        self.renderer.memory_statistics()
+    }
+
+    /// #### Description
+    /// Which queue families the renderer uses, e.g. to check whether async compute is available.
+    #[inline]
+    pub fn queue_topology(&self) -> QueueTopology {
+        self.renderer.queue_topology()
+    }
+
+    /// #### Description
+    /// Capabilities of every GPU on the machine, `CapabilityReport::to_json` gives the text for bug reports.
+    #[inline]
+    pub fn capability_report(&self) -> EngineResult<CapabilityReport> {
+        self.renderer.capability_report()
+    }
+
+    /// #### Description
+    /// Buffers, images, samplers, descriptors, shaders, pipelines and compute work of the renderer.
+    #[inline]
+    pub fn context(&self) -> &Context<'a> {
+        self.renderer.context()
+    }
+
+    /// #### Description
+    /// Creates and uploads resources, runs compute work and reads results back, e.g. headlessly for tests.
+    ///
+    /// #### NOTE
+    /// Frames are driven by `draw`, so use this between frames rather than to begin or end them.
+    #[inline]
+    pub fn context_mut(&mut self) -> &mut Context<'a> {
+        self.renderer.context_mut()
+    }
+
+    /// #### Description
+    /// Extensions and features actually enabled on the device, to branch on optional requirements.
+    #[inline]
+    pub fn enabled_features(&self) -> &EnabledFeatures {
+        self.renderer.enabled_features()
+    }
+
+    /// #### Description
+    /// Format and color space of presented frames, tone mapping has to output values in it.
+    #[inline]
+    pub fn output_format(&self) -> OutputFormat {
+        self.renderer.output_format()
+    }
+
+    /// #### Description
+    /// Vsync mode in use after falling back from unsupported ones, `None` when headless.
+    #[inline]
+    pub fn vsync_mode(&self) -> Option<VsyncMode> {
+        self.renderer.vsync_mode()
+    }
+
+    /// #### Description
+    /// Takes effect from the next frame, the swapchain is recreated with the new present mode.
+    #[inline]
+    pub fn set_vsync_mode(&mut self, vsync_mode: VsyncMode) {
+        self.renderer.set_vsync_mode(vsync_mode)
+    }
+
+    /// #### Description
+    /// Validation messages reported so far, e.g. for tests to assert there were no errors.
+    #[inline]
+    pub fn validation_stats(&self) -> ValidationStats {
+        self.renderer.validation_stats()
+    }
+
+    /// #### Description
+    /// GPU time of every render pass of the last frame the GPU finished.
+    /// With the `profiling` feature the same zones are visible in Tracy.
+    #[inline]
+    pub fn gpu_timings(&self) -> Option<&GpuFrameTimings> {
+        self.renderer.gpu_timings()
+    }
+
+    fn init<'a>(
+        create_renderer: impl FnOnce() -> EngineResult<renderer::Renderer<'a>>,
+    ) -> EngineResult<Engine<'a>> {
         unsafe {
             common::HASHER
                 .set(ahash::random_state::RandomState::with_seeds(1, 2, 3, 4).build_hasher())
@@ -29,14 +176,33 @@ impl Engine<'_> {
         let logging = Self::init_logging();
 
         info!("Initializing renderer.");
-        let renderer = renderer::Renderer::new(window)?;
+        let renderer = create_renderer()?;
 
-        Ok(Self {
+        Ok(Engine {
             renderer,
+            events: Default::default(),
             _logging: logging,
         })
     }
 
+    fn recover_if_device_lost(&mut self, result: EngineResult<()>) -> EngineResult<()> {
+        if !matches!(result, Err(EngineError::DeviceLost)) {
+            return result;
+        }
+
+        error!("GPU device was lost.");
+        self.events.push(EngineEvent::DeviceLost);
+
+        let invalidated_objects = self.renderer.recover_device()?;
+        self.events.push(EngineEvent::DeviceRecovered);
+        if invalidated_objects != InvalidatedObjects::default() {
+            self.events
+                .push(EngineEvent::ObjectsInvalidated(invalidated_objects));
+        }
+
+        Ok(())
+    }
+
     fn init_logging() -> Logging {
         let log_level = match () {
             #[cfg(feature = "dev")]
@@ -1,19 +1,198 @@
 mod context;
+mod render_graph;
 mod utils;
 
 use logging::*;
 
 use crate::{debug, error::EngineResult};
 
+use ash::vk;
+
+pub use context::{
+    BindlessKind, BlendState, BufferDesc, CapabilityReport, CommandRecorder, ComputePipelineDesc,
+    Context, DebugLabel, DepthStencilState, DescriptorBinding, DescriptorWriter, DeviceFeature,
+    DeviceRequirements, DeviceSelection, EnabledFeatures, GpuFrameTimings, GpuZone, GpuZoneTiming,
+    GraphicsPipelineDesc, HeapStatistics, ImageDesc, ImageKind, InvalidatedObjects, MemoryLocation,
+    OutputColorSpace, OutputFormat, Pipeline, QueueKind, QueueLocation, QueueTopology,
+    RasterizationState, RecordedCommandBuffer, SamplerDesc, Shader, StrictMode, SurfaceFormat,
+    ValidationFeatures, ValidationPolicy, ValidationSeverity, ValidationStats, VsyncMode,
+};
+
+#[derive(Debug, Clone)]
+pub struct RendererConfig {
+    /// How many frames the CPU can record ahead of the GPU.
+    pub frames_in_flight: usize,
+    /// Enables descriptor indexing and one global descriptor set indexed by `u32` in shaders.
+    /// Only devices which support descriptor indexing are considered.
+    pub bindless: bool,
+    /// Directory for data the engine keeps between runs, e.g. the pipeline cache.
+    pub data_directory: std::path::PathBuf,
+    /// Which GPU to use on machines with several, can be overridden by the `ENGINE_DEVICE` environment variable.
+    pub device_selection: DeviceSelection,
+    /// Extensions and features the application needs, devices missing required ones aren't considered.
+    pub device_requirements: DeviceRequirements,
+    /// Filtering and strictness of validation messages, only used with the `dev` feature.
+    pub validation: ValidationPolicy,
+    /// Swapchain formats in order of preference, the surface's own format is used if none is supported.
+    pub surface_formats: Vec<SurfaceFormat>,
+    /// Present mode of the swapchain, can be changed at runtime with `Engine::set_vsync_mode`.
+    pub vsync: VsyncMode,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            frames_in_flight: 2,
+            bindless: false,
+            data_directory: std::path::PathBuf::from("data"),
+            device_selection: Default::default(),
+            device_requirements: Default::default(),
+            validation: Default::default(),
+            surface_formats: SurfaceFormat::DEFAULT.to_vec(),
+            vsync: Default::default(),
+        }
+    }
+}
+
 pub struct Renderer<'a> {
     context: context::Context<'a>,
 }
 
-impl Renderer<'_> {
+impl<'a> Renderer<'a> {
+    const CLEAR_COLOR: [f32; 4] = [0.01, 0.01, 0.02, 1.0];
+
+    #[inline]
+    pub fn new(window: &winit::window::Window, config: &RendererConfig) -> EngineResult<Self> {
+        Self::with_target(context::Target::Window(window), config)
+    }
+
+    #[inline]
+    pub fn new_headless(width: u32, height: u32, config: &RendererConfig) -> EngineResult<Self> {
+        Self::with_target(
+            context::Target::Headless(ash::vk::Extent2D { width, height }),
+            config,
+        )
+    }
+
+    #[inline]
+    pub fn resize(&mut self, width: u32, height: u32) -> EngineResult<()> {
+        self.context.resize(width, height)
+    }
+
+    #[inline]
+    pub fn current_frame_index(&self) -> usize {
+        self.context.current_frame_index()
+    }
+
+    #[inline]
+    pub fn wait_for_frame(&self, frame_index: usize) -> EngineResult<()> {
+        self.context.wait_for_frame(frame_index)
+    }
+
+    #[inline]
+    pub fn memory_statistics(&self) -> Vec<HeapStatistics> {
+        self.context.memory_statistics()
+    }
+
+    #[inline]
+    pub fn queue_topology(&self) -> QueueTopology {
+        self.context.queue_topology()
+    }
+
+    #[inline]
+    pub fn capability_report(&self) -> EngineResult<CapabilityReport> {
+        self.context.capability_report()
+    }
+
+    #[inline]
+    pub fn context(&self) -> &Context<'a> {
+        &self.context
+    }
+
+    #[inline]
+    pub fn context_mut(&mut self) -> &mut Context<'a> {
+        &mut self.context
+    }
+
+    #[inline]
+    pub fn enabled_features(&self) -> &EnabledFeatures {
+        self.context.enabled_features()
+    }
+
+    #[inline]
+    pub fn output_format(&self) -> OutputFormat {
+        self.context.output_format()
+    }
+
+    #[inline]
+    pub fn vsync_mode(&self) -> Option<VsyncMode> {
+        self.context.vsync_mode()
+    }
+
+    #[inline]
+    pub fn set_vsync_mode(&mut self, vsync_mode: VsyncMode) {
+        self.context.set_vsync_mode(vsync_mode)
+    }
+
+    #[inline]
+    pub fn validation_stats(&self) -> ValidationStats {
+        self.context.validation_stats()
+    }
+
+    #[inline]
+    pub fn gpu_timings(&self) -> Option<&GpuFrameTimings> {
+        self.context.gpu_timings()
+    }
+
+    #[inline]
+    pub fn recover_device(&mut self) -> EngineResult<InvalidatedObjects> {
+        self.context.recover_device()
+    }
+
+    pub fn draw_frame(&mut self) -> EngineResult<()> {
+        let Some(image_index) = self.context.begin_frame()? else {
+            return Ok(());
+        };
+
+        let mut graph = render_graph::RenderGraph::new();
+        let target = graph.import_image(
+            "target",
+            render_graph::GraphImage {
+                image: self.context.target_images()[image_index as usize],
+                view: self.context.target_image_views()[image_index as usize],
+                extent: self.context.target_extent(),
+                subresource_range: vk::ImageSubresourceRange::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .level_count(1)
+                    .layer_count(1),
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                // The acquire semaphore is waited on at this stage.
+                initial_stages: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                final_layout: Some(self.context.target_final_layout()),
+            },
+        );
+        // Attachments are cleared on load, so the pass doesn't record anything yet.
+        graph.add_pass("clear").color_attachment(
+            target,
+            render_graph::AttachmentLoad::ClearColor(Self::CLEAR_COLOR),
+        );
+        let graph = graph.compile()?;
+
+        let recorder = self.context.record_commands(
+            context::QueueKind::Graphics,
+            vk::CommandBufferLevel::PRIMARY,
+            None,
+        )?;
+        graph.execute(&recorder);
+        let command_buffer = recorder.finish()?;
+
+        self.context.end_frame(image_index, &[command_buffer])
+    }
+
     #[inline]
-    pub fn new(window: &winit::window::Window) -> EngineResult<Self> {
+    fn with_target(target: context::Target, config: &RendererConfig) -> EngineResult<Self> {
         debug!("Initializing Vulkan.");
-        let context = context::Context::new(window)?;
+        let context = context::Context::new(target, config)?;
 
         Ok(Self { context })
     }
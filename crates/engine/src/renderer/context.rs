@@ -1,32 +1,130 @@
+mod allocator;
+mod bindless;
+mod buffer;
+mod capabilities;
+mod command;
 #[cfg(feature = "dev")]
 mod debug_messenger;
+mod descriptor;
 mod device;
+mod features;
+mod image;
 mod instance;
+mod offscreen;
+mod pipeline;
+mod pipeline_cache;
+mod profiler;
+mod resource_manager;
+mod sampler;
 mod shader;
 mod surface;
+mod surface_format;
+mod swapchain;
 mod sync;
-mod resource_manager;
+mod validation;
 
 use logging::*;
 
 use crate::{
-    error::EngineResult,
-    renderer::{context::device::DeviceManager, utils::*},
+    common::Id,
+    error::{EngineError, EngineResult},
+    renderer::{context::device::DeviceManager, utils::*, RendererConfig},
+};
+
+pub use allocator::{HeapStatistics, MemoryLocation};
+pub use bindless::BindlessKind;
+pub use buffer::BufferDesc;
+pub use capabilities::CapabilityReport;
+pub use command::{CommandRecorder, DebugLabel, RecordedCommandBuffer};
+pub use descriptor::{DescriptorBinding, DescriptorWriter};
+pub use device::{DeviceSelection, QueueKind, QueueLocation, QueueTopology};
+pub use features::{DeviceFeature, DeviceRequirements, EnabledFeatures};
+pub use image::{ImageDesc, ImageKind};
+pub use pipeline::{
+    BlendState, ComputePipelineDesc, DepthStencilState, GraphicsPipelineDesc, Pipeline,
+    RasterizationState,
+};
+pub use profiler::{GpuFrameTimings, GpuZone, GpuZoneTiming};
+pub use sampler::SamplerDesc;
+pub use shader::Shader;
+pub use surface_format::{OutputColorSpace, OutputFormat, SurfaceFormat};
+pub use swapchain::VsyncMode;
+pub use validation::{
+    StrictMode, ValidationFeatures, ValidationPolicy, ValidationSeverity, ValidationStats,
 };
 
+use allocator::GpuAllocator;
+use bindless::BindlessManager;
+use buffer::Buffer;
+use command::{CommandManager, SubmitDesc};
+use descriptor::DescriptorManager;
+use image::Image;
 use instance::InstanceHandle;
+use offscreen::OffscreenManager;
+use pipeline::PipelineManager;
+use profiler::ProfilerManager;
+use resource_manager::RESOURCE_MANAGER;
 use surface::SurfaceManager;
+use swapchain::SwapchainManager;
 
 use std::mem::ManuallyDrop;
 
+use ash::vk;
+
+#[derive(Clone, Copy)]
+pub enum Target<'w> {
+    Window(&'w winit::window::Window),
+    Headless(vk::Extent2D),
+}
+
+/// #### Description
+/// Objects created through the Context before a device loss. Their handles are invalid after
+/// the recovery and the application must request them again.
+///
+/// #### NOTE
+/// Shaders keep their Ids and get new modules, pipeline descriptions must be built from them again.
+/// Descriptor sets and bindless indices are invalid as well.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidatedObjects {
+    /// Graphics and compute pipelines with their layouts.
+    pub pipelines: usize,
+    pub descriptor_set_layouts: usize,
+    pub samplers: usize,
+}
+
+/// #### Description
+/// Where the frames go: either presented to the surface of the window or kept in offscreen images.
+enum Presenter {
+    Swapchain {
+        surface_manager: SurfaceManager,
+        swapchain_manager: SwapchainManager,
+    },
+    Offscreen(OffscreenManager),
+}
+
 pub struct Context<'a> {
     entry: ManuallyDrop<ash::Entry>,
     instance_manager: InstanceHandle,
+    #[cfg(feature = "dev")]
     debug_messenger_manager: debug_messenger::DebugMessengerManager,
-    surface_manager: SurfaceManager,
     device_manager: DeviceManager,
+    allocator: GpuAllocator,
+    presenter: Presenter,
+    command_manager: CommandManager,
+    descriptor_manager: DescriptorManager,
+    bindless_manager: Option<BindlessManager>,
+    pipeline_manager: PipelineManager,
+    profiler_manager: ProfilerManager,
     shader_manager: shader::ShaderManager<'a>,
     sync_manager: sync::SynchronizationPrimitivesManager,
+    window_extent: vk::Extent2D,
+    /// Kept to create the Device and its objects again after a device loss.
+    config: RendererConfig,
+    /// Objects of the lost Device are destroyed and not created again yet.
+    is_device_lost: bool,
+    /// Image acquired by `begin_frame` whose frame wasn't submitted, e.g. because recording failed.
+    /// Its acquire semaphore is still signaled, so the next `begin_frame` renders into it again.
+    acquired_image_index: Option<u32>,
 }
 
 impl Context<'_> {
@@ -38,32 +136,1353 @@ impl Context<'_> {
         Self::VALIDATION_LAYER_NAME,
     ];
 
-    pub fn new(window: &winit::window::Window) -> EngineResult<Self> {
+    pub fn new(target: Target, config: &RendererConfig) -> EngineResult<Self> {
         debug!("Loading Vulkan lib.");
         let entry = unsafe { ash::Entry::load()? };
 
-        let instance_manager = InstanceHandle::new(&entry, window)?;
-        let debug_messenger_manager =
-            debug_messenger::DebugMessengerManager::new(&entry, &instance_manager.instance)?;
+        let window = match target {
+            Target::Window(window) => Some(window),
+            Target::Headless(_) => None,
+        };
+
+        let instance_manager = InstanceHandle::new(&entry, window, &config.validation.features)?;
+        #[cfg(feature = "dev")]
+        let debug_messenger_manager = debug_messenger::DebugMessengerManager::new(
+            &entry,
+            &instance_manager.instance,
+            &config.validation,
+        )?;
 
-        let surface_manager =
-            surface::SurfaceManager::new(&entry, &instance_manager.instance, window)?;
+        let surface_manager = match window {
+            Some(window) => Some(surface::SurfaceManager::new(
+                &entry,
+                &instance_manager.instance,
+                window,
+            )?),
+            None => None,
+        };
 
         debug!("Creating Device.");
-        let device_manager = DeviceManager::new(&instance_manager.instance, &surface_manager)?;
-        let sync_manager = sync::SynchronizationPrimitivesManager::new(&device_manager)?;
+        #[cfg_attr(not(feature = "dev"), allow(unused_mut))]
+        let mut device_manager = DeviceManager::new(
+            &instance_manager.instance,
+            surface_manager.as_ref(),
+            config.bindless,
+            &config.device_selection,
+            &config.device_requirements,
+        )?;
+        #[cfg(feature = "dev")]
+        device_manager.enable_debug_names(debug_messenger_manager.debug_utils_loader.clone());
+        let sync_manager =
+            sync::SynchronizationPrimitivesManager::new(&device_manager, config.frames_in_flight)?;
+
+        let mut allocator = GpuAllocator::new(&device_manager);
 
-        let shader_manager = shader::ShaderManager::new();
+        let window_extent = match target {
+            Target::Window(window) => {
+                let window_size = window.inner_size();
+
+                vk::Extent2D {
+                    width: window_size.width,
+                    height: window_size.height,
+                }
+            }
+            Target::Headless(extent) => extent,
+        };
+        let presenter = match surface_manager {
+            Some(surface_manager) => {
+                let swapchain_manager = SwapchainManager::new(
+                    &instance_manager.instance,
+                    &device_manager,
+                    &surface_manager,
+                    window_extent,
+                    &config.surface_formats,
+                    config.vsync,
+                )?;
 
-        Ok(Self {
+                Presenter::Swapchain {
+                    surface_manager,
+                    swapchain_manager,
+                }
+            }
+            None => Presenter::Offscreen(OffscreenManager::new(
+                &device_manager,
+                &mut allocator,
+                window_extent,
+            )?),
+        };
+
+        let command_manager = CommandManager::new(config.frames_in_flight);
+        let descriptor_manager = DescriptorManager::new(config.frames_in_flight);
+        let bindless_manager = if config.bindless {
+            Some(BindlessManager::new(&device_manager)?)
+        } else {
+            None
+        };
+        let pipeline_manager = PipelineManager::new(&device_manager, &config.data_directory)?;
+        let profiler_manager = ProfilerManager::new(&device_manager, config.frames_in_flight)?;
+        let shader_manager = shader::ShaderManager::new(
+            cfg!(feature = "dev") && config.validation.features.debug_printf,
+        );
+
+        #[cfg_attr(not(feature = "profiling"), allow(unused_mut))]
+        let mut context = Self {
             entry: ManuallyDrop::new(entry),
             instance_manager,
+            #[cfg(feature = "dev")]
             debug_messenger_manager,
-            surface_manager,
             device_manager,
+            allocator,
+            presenter,
+            command_manager,
+            descriptor_manager,
+            bindless_manager,
+            pipeline_manager,
+            profiler_manager,
             shader_manager,
             sync_manager,
-        })
+            window_extent,
+            config: config.clone(),
+            is_device_lost: false,
+            acquired_image_index: None,
+        };
+
+        #[cfg(feature = "profiling")]
+        context.connect_tracy();
+
+        Ok(context)
+    }
+
+    #[inline(always)]
+    pub fn resize(&mut self, width: u32, height: u32) -> EngineResult<()> {
+        self.window_extent = vk::Extent2D { width, height };
+        let is_minimized = self.is_minimized();
+
+        match &mut self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => swapchain_manager.mark_outdated(),
+            Presenter::Offscreen(offscreen_manager) => {
+                if !is_minimized {
+                    offscreen_manager.resize(
+                        &self.device_manager,
+                        &mut self.allocator,
+                        self.window_extent,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn is_headless(&self) -> bool {
+        matches!(self.presenter, Presenter::Offscreen(_))
+    }
+
+    /// #### Description
+    /// Images the frames are rendered into, the swapchain images or the offscreen ones.
+    #[inline(always)]
+    pub fn target_images(&self) -> &[vk::Image] {
+        match &self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => &swapchain_manager.images,
+            Presenter::Offscreen(offscreen_manager) => &offscreen_manager.images,
+        }
+    }
+
+    #[inline(always)]
+    pub fn target_image_views(&self) -> &[vk::ImageView] {
+        match &self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => &swapchain_manager.image_views,
+            Presenter::Offscreen(offscreen_manager) => &offscreen_manager.image_views,
+        }
+    }
+
+    #[inline(always)]
+    pub fn target_extent(&self) -> vk::Extent2D {
+        match &self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => swapchain_manager.extent,
+            Presenter::Offscreen(offscreen_manager) => offscreen_manager.extent,
+        }
+    }
+
+    #[inline(always)]
+    pub fn target_format(&self) -> vk::Format {
+        match &self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => swapchain_manager.surface_format.format,
+            Presenter::Offscreen(offscreen_manager) => offscreen_manager.format,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_minimized(&self) -> bool {
+        self.window_extent.width == Default::default()
+            || self.window_extent.height == Default::default()
+    }
+
+    /// #### Description
+    /// Layout the target image must be in at the end of the frame.
+    #[inline(always)]
+    pub fn target_final_layout(&self) -> vk::ImageLayout {
+        match &self.presenter {
+            Presenter::Swapchain { .. } => vk::ImageLayout::PRESENT_SRC_KHR,
+            Presenter::Offscreen(_) => vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        }
+    }
+
+    pub fn create_buffer(&mut self, desc: BufferDesc) -> EngineResult<Id> {
+        let id = unsafe { RESOURCE_MANAGER.next_id() };
+        let buffer = Buffer::new(&self.device_manager, &mut self.allocator, id, desc)?;
+
+        unsafe { RESOURCE_MANAGER.register_buffer(buffer) };
+
+        Ok(id)
+    }
+
+    /// #### Description
+    /// Names a buffer or an image for validation messages and captures, does nothing without the `dev` feature.
+    pub fn set_debug_name(&self, id: Id, name: &str) -> EngineResult<()> {
+        if let Some(buffer) = unsafe { RESOURCE_MANAGER.buffer(id) } {
+            self.device_manager.set_object_name(buffer.buffer, name);
+        } else if let Some(image) = unsafe { RESOURCE_MANAGER.image(id) } {
+            self.device_manager.set_object_name(image.image, name);
+            self.device_manager
+                .set_object_name(image.view, &std::format!("{name} View"));
+        } else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Unable to find Buffer or Image {id:?}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Names a raw Vulkan object owned by the engine, e.g. a pipeline or a descriptor set.
+    #[inline(always)]
+    pub fn set_object_name<H: vk::Handle>(&self, handle: H, name: &str) {
+        self.device_manager.set_object_name(handle, name);
+    }
+
+    /// #### Description
+    /// Writes `data` into a host visible buffer, see `Buffer::write`.
+    pub fn write_buffer<T: bytemuck::Pod>(
+        &mut self,
+        id: Id,
+        offset: vk::DeviceSize,
+        data: &[T],
+    ) -> EngineResult<()> {
+        let Some(buffer) = (unsafe { RESOURCE_MANAGER.buffer_mut(id) }) else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Unable to find Buffer {id:?}"
+            )));
+        };
+
+        buffer.write(offset, data)
+    }
+
+    /// #### Description
+    /// The buffer must not be in use by the GPU anymore.
+    #[inline(always)]
+    pub fn destroy_buffer(&mut self, id: Id) {
+        if let Some(bindless_manager) = &mut self.bindless_manager {
+            bindless_manager.release(id);
+        }
+        unsafe { RESOURCE_MANAGER.delete_buffer(id, &self.device_manager, &mut self.allocator) };
+    }
+
+    /// #### Description
+    /// Copies `data` into a buffer in device local memory through a staging buffer on the transfer queue.
+    /// Blocks until the upload is finished.
+    pub fn upload_buffer<T: bytemuck::Pod>(
+        &mut self,
+        id: Id,
+        offset: vk::DeviceSize,
+        data: &[T],
+    ) -> EngineResult<()> {
+        let Some(buffer) = (unsafe { RESOURCE_MANAGER.buffer(id) }) else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Unable to find Buffer {id:?}"
+            )));
+        };
+        let (buffer, is_concurrent, buffer_size) =
+            (buffer.buffer, buffer.is_concurrent, buffer.size());
+
+        let bytes = bytemuck::cast_slice::<T, u8>(data);
+        let size = bytes.len() as vk::DeviceSize;
+        // Zero-sized copies are invalid usage, out of bounds ones would be executed by the GPU.
+        if size == 0 {
+            return Err(EngineError::UnknownError(std::format!(
+                "Upload into Buffer {id:?} has no data"
+            )));
+        }
+        if !offset
+            .checked_add(size)
+            .is_some_and(|end| end <= buffer_size)
+        {
+            return Err(EngineError::UnknownError(std::format!(
+                "Upload of {size} bytes at offset {offset} is out of Buffer {id:?} of size {buffer_size}"
+            )));
+        }
+
+        self.upload(
+            bytes,
+            is_concurrent,
+            |recorder, staging_buffer| {
+                recorder.copy_buffer(
+                    staging_buffer,
+                    buffer,
+                    &[vk::BufferCopy::default().dst_offset(offset).size(size)],
+                );
+            },
+            |src_queue_family_index, dst_queue_family_index, is_acquire| {
+                let barrier = vk::BufferMemoryBarrier2::default()
+                    .src_queue_family_index(src_queue_family_index)
+                    .dst_queue_family_index(dst_queue_family_index)
+                    .buffer(buffer)
+                    .offset(offset)
+                    .size(size);
+
+                (
+                    vec![Self::upload_barrier_stages(barrier, is_acquire)],
+                    Vec::new(),
+                )
+            },
+        )
+    }
+
+    pub fn create_image(&mut self, desc: ImageDesc) -> EngineResult<Id> {
+        let id = unsafe { RESOURCE_MANAGER.next_id() };
+        let image = Image::new(&self.device_manager, &mut self.allocator, id, desc)?;
+
+        unsafe { RESOURCE_MANAGER.register_image(image) };
+
+        Ok(id)
+    }
+
+    /// #### Description
+    /// Uploads texels of the base mip level (all layers/faces tightly packed one after another)
+    /// through a staging buffer on the transfer queue. The image ends up in `SHADER_READ_ONLY_OPTIMAL`
+    /// layout and owned by the graphics queue. Blocks until the upload is finished.
+    pub fn upload_image(&mut self, id: Id, data: &[u8]) -> EngineResult<()> {
+        let Some(image) = (unsafe { RESOURCE_MANAGER.image(id) }) else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Unable to find Image {id:?}"
+            )));
+        };
+        let (image_handle, desc, is_concurrent) = (image.image, image.desc, image.is_concurrent);
+        let subresource_range = desc.subresource_range();
+
+        // A shorter slice would make the copy read past the end of the staging buffer.
+        let Some(expected_size) = desc.base_level_size() else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Unable to upload Image {id:?}, size of format {:?} is unknown",
+                desc.format
+            )));
+        };
+        if data.len() as vk::DeviceSize != expected_size {
+            return Err(EngineError::UnknownError(std::format!(
+                "Unable to upload Image {id:?}, expected {expected_size} bytes but got {}",
+                data.len()
+            )));
+        }
+
+        self.upload(
+            data,
+            is_concurrent,
+            |recorder, staging_buffer| {
+                recorder.pipeline_barrier(
+                    &[],
+                    &[vk::ImageMemoryBarrier2::default()
+                        .dst_stage_mask(vk::PipelineStageFlags2::COPY)
+                        .dst_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
+                        .old_layout(vk::ImageLayout::UNDEFINED)
+                        .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                        .image(image_handle)
+                        .subresource_range(subresource_range)],
+                );
+                recorder.copy_buffer_to_image(
+                    staging_buffer,
+                    image_handle,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[vk::BufferImageCopy::default()
+                        .image_subresource(
+                            vk::ImageSubresourceLayers::default()
+                                .aspect_mask(desc.aspect_mask())
+                                .layer_count(desc.layer_count()),
+                        )
+                        .image_extent(desc.extent())],
+                );
+            },
+            |src_queue_family_index, dst_queue_family_index, is_acquire| {
+                let barrier = vk::ImageMemoryBarrier2::default()
+                    .src_queue_family_index(src_queue_family_index)
+                    .dst_queue_family_index(dst_queue_family_index)
+                    .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                    .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .image(image_handle)
+                    .subresource_range(subresource_range);
+
+                (
+                    Vec::new(),
+                    vec![Self::upload_barrier_stages(barrier, is_acquire)],
+                )
+            },
+        )?;
+
+        if let Some(image) = unsafe { RESOURCE_MANAGER.image_mut(id) } {
+            image.layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL;
+        }
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// The image must not be in use by the GPU anymore.
+    #[inline(always)]
+    pub fn destroy_image(&mut self, id: Id) {
+        if let Some(bindless_manager) = &mut self.bindless_manager {
+            bindless_manager.release(id);
+        }
+        unsafe { RESOURCE_MANAGER.delete_image(id, &self.device_manager, &mut self.allocator) };
+    }
+
+    /// #### Description
+    /// Sampler matching the description, created on first use and owned by the engine.
+    #[inline(always)]
+    pub fn sampler(&self, desc: SamplerDesc) -> EngineResult<vk::Sampler> {
+        unsafe { RESOURCE_MANAGER.sampler(desc, &self.device_manager) }
+    }
+
+    /// #### Description
+    /// Layout with the given bindings, created on first use and owned by the engine.
+    #[inline(always)]
+    pub fn descriptor_set_layout(
+        &mut self,
+        bindings: &[DescriptorBinding],
+    ) -> EngineResult<vk::DescriptorSetLayout> {
+        self.descriptor_manager
+            .layout(&self.device_manager, bindings)
+    }
+
+    /// #### Description
+    /// Descriptor set which lives until the Context is destroyed.
+    #[inline(always)]
+    pub fn allocate_descriptor_set(
+        &mut self,
+        layout: vk::DescriptorSetLayout,
+    ) -> EngineResult<vk::DescriptorSet> {
+        self.descriptor_manager
+            .allocate(&self.device_manager, layout)
+    }
+
+    /// #### Description
+    /// Descriptor set valid only for the current frame in flight.
+    #[inline(always)]
+    pub fn allocate_transient_descriptor_set(
+        &mut self,
+        layout: vk::DescriptorSetLayout,
+    ) -> EngineResult<vk::DescriptorSet> {
+        self.descriptor_manager.allocate_transient(
+            &self.device_manager,
+            self.sync_manager.current_frame_index(),
+            layout,
+        )
+    }
+
+    /// #### Description
+    /// The set must not be in use by the GPU.
+    #[inline(always)]
+    pub fn write_descriptor_set(
+        &self,
+        set: vk::DescriptorSet,
+        writer: &DescriptorWriter,
+    ) -> EngineResult<()> {
+        writer.update(&self.device_manager, set)
+    }
+
+    /// #### Description
+    /// Compiles the shader, its stage is taken from the file extension (e.g. `./shaders/triangle.vert`).
+    #[inline(always)]
+    pub fn load_shader<T: AsRef<std::path::Path>>(&mut self, path: &T) -> EngineResult<Id> {
+        self.shader_manager.load_shader(&self.device_manager, path)
+    }
+
+    #[inline(always)]
+    pub fn shader(&self, id: Id) -> Option<&Shader> {
+        self.shader_manager.shader(id)
+    }
+
+    /// #### Description
+    /// Pipeline matching the description, created on first use and owned by the engine.
+    #[inline(always)]
+    pub fn graphics_pipeline(&mut self, desc: &GraphicsPipelineDesc) -> EngineResult<Pipeline> {
+        self.pipeline_manager
+            .graphics_pipeline(&self.device_manager, desc)
+    }
+
+    #[inline(always)]
+    pub fn compute_pipeline(&mut self, desc: &ComputePipelineDesc) -> EngineResult<Pipeline> {
+        self.pipeline_manager
+            .compute_pipeline(&self.device_manager, desc)
+    }
+
+    /// #### Description
+    /// Records a one-shot job on the compute queue, submits it and blocks until it's finished.
+    /// Shader writes are made visible to the host, so results can be read with `read_buffer`.
+    pub fn run_compute(&mut self, record: impl FnOnce(&CommandRecorder)) -> EngineResult<()> {
+        let result = Self::run_immediate(
+            &mut self.command_manager,
+            &self.device_manager,
+            QueueKind::Compute,
+            |recorder| {
+                record(recorder);
+                recorder.memory_barrier(&[vk::MemoryBarrier2::default()
+                    .src_stage_mask(vk::PipelineStageFlags2::COMPUTE_SHADER)
+                    .src_access_mask(vk::AccessFlags2::SHADER_WRITE)
+                    .dst_stage_mask(vk::PipelineStageFlags2::HOST)
+                    .dst_access_mask(vk::AccessFlags2::HOST_READ)]);
+            },
+        );
+        self.check_validation();
+
+        result
+    }
+
+    /// #### Description
+    /// Copies the content of a host visible buffer, e.g. a readback buffer written by the GPU.
+    ///
+    /// #### NOTE
+    /// Size of the buffer must be a multiple of the size of `T`, zero-sized types are rejected.
+    pub fn read_buffer<T: bytemuck::Pod>(&self, id: Id) -> EngineResult<Vec<T>> {
+        let element_size = std::mem::size_of::<T>();
+        if element_size == 0 {
+            return Err(EngineError::UnknownError(
+                "Unable to read the Buffer into zero-sized elements".to_owned(),
+            ));
+        }
+
+        let Some(buffer) = (unsafe { RESOURCE_MANAGER.buffer(id) }) else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Unable to find Buffer {id:?}"
+            )));
+        };
+        let Some(bytes) = buffer.mapped_bytes() else {
+            return Err(EngineError::UnknownError(
+                "Unable to read the Buffer which isn't host visible".to_owned(),
+            ));
+        };
+
+        if bytes.len() % element_size != 0 {
+            return Err(EngineError::UnknownError(std::format!(
+                "Buffer {id:?} of size {} isn't a multiple of the element size {element_size}",
+                bytes.len()
+            )));
+        }
+
+        let mut data = vec![<T as bytemuck::Zeroable>::zeroed(); bytes.len() / element_size];
+        bytemuck::cast_slice_mut::<T, u8>(&mut data).copy_from_slice(bytes);
+
+        Ok(data)
+    }
+
+    /// #### Description
+    /// Layout of the global bindless set, pipelines which use bindless resources include it.
+    #[inline(always)]
+    pub fn bindless_layout(&self) -> EngineResult<vk::DescriptorSetLayout> {
+        Ok(self.bindless_manager()?.layout)
+    }
+
+    #[inline(always)]
+    pub fn bindless_set(&self) -> EngineResult<vk::DescriptorSet> {
+        Ok(self.bindless_manager()?.set)
+    }
+
+    /// #### Description
+    /// Stable index of the resource in the bindless array of its kind, valid until the resource is destroyed.
+    #[inline(always)]
+    pub fn bindless_index(&mut self, kind: BindlessKind, id: Id) -> EngineResult<u32> {
+        let Some(bindless_manager) = &mut self.bindless_manager else {
+            return Err(Self::bindless_disabled_error());
+        };
+
+        bindless_manager.index(&self.device_manager, kind, id)
+    }
+
+    #[inline(always)]
+    pub fn bindless_sampler_index(&mut self, desc: SamplerDesc) -> EngineResult<u32> {
+        let sampler = self.sampler(desc)?;
+        let Some(bindless_manager) = &mut self.bindless_manager else {
+            return Err(Self::bindless_disabled_error());
+        };
+
+        bindless_manager.sampler_index(&self.device_manager, sampler)
+    }
+
+    /// #### Description
+    /// GPU timings of the most recent frame the GPU finished, `None` until one is resolved
+    /// or when the device doesn't support timestamps.
+    #[inline(always)]
+    pub fn gpu_timings(&self) -> Option<&GpuFrameTimings> {
+        self.profiler_manager.last_timings()
+    }
+
+    #[inline(always)]
+    pub fn memory_statistics(&self) -> Vec<HeapStatistics> {
+        self.allocator.statistics()
+    }
+
+    /// #### Description
+    /// Queue families and queues used for graphics, compute and transfer work.
+    #[inline(always)]
+    pub fn queue_topology(&self) -> QueueTopology {
+        self.device_manager.queue_topology
+    }
+
+    /// #### Description
+    /// Queries capabilities of every GPU, the one in use is marked as selected.
+    pub fn capability_report(&self) -> EngineResult<CapabilityReport> {
+        let surface_manager = match &self.presenter {
+            Presenter::Swapchain {
+                surface_manager, ..
+            } => Some(surface_manager),
+            Presenter::Offscreen(_) => None,
+        };
+
+        CapabilityReport::new(
+            &self.instance_manager.instance,
+            surface_manager,
+            self.device_manager.physical_device,
+        )
+    }
+
+    /// #### Description
+    /// Extensions and features enabled on the Device, optional requirements may be missing.
+    #[inline(always)]
+    pub fn enabled_features(&self) -> &EnabledFeatures {
+        &self.device_manager.enabled_features
+    }
+
+    /// #### Description
+    /// Format and color space frames are presented in, offscreen images are 8-bit sRGB.
+    #[inline(always)]
+    pub fn output_format(&self) -> OutputFormat {
+        match &self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => swapchain_manager.output_format,
+            Presenter::Offscreen(offscreen_manager) => OutputFormat::new(
+                vk::SurfaceFormatKHR {
+                    format: offscreen_manager.format,
+                    color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+                },
+                Some(SurfaceFormat::Srgb),
+            ),
+        }
+    }
+
+    /// #### Description
+    /// Vsync mode the swapchain is created with, `None` when headless.
+    #[inline(always)]
+    pub fn vsync_mode(&self) -> Option<VsyncMode> {
+        match &self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => Some(swapchain_manager.vsync_mode),
+            Presenter::Offscreen(_) => None,
+        }
+    }
+
+    /// #### Description
+    /// Changes the vsync mode at runtime, the swapchain is recreated before the next frame.
+    /// The mode is kept for a swapchain created after a device loss.
+    pub fn set_vsync_mode(&mut self, vsync_mode: VsyncMode) {
+        self.config.vsync = vsync_mode;
+
+        if let Presenter::Swapchain {
+            swapchain_manager, ..
+        } = &mut self.presenter
+        {
+            swapchain_manager.set_vsync_mode(&self.device_manager, vsync_mode);
+        }
+    }
+
+    /// #### Description
+    /// Counters of validation messages, always empty without the `dev` feature.
+    #[inline(always)]
+    pub fn validation_stats(&self) -> ValidationStats {
+        match () {
+            #[cfg(feature = "dev")]
+            _ => self.debug_messenger_manager.validation_stats(),
+
+            #[cfg(not(feature = "dev"))]
+            _ => Default::default(),
+        }
+    }
+
+    /// #### Description
+    /// Panics with the first validation error in `StrictMode::Panic`. The engine checks after every frame,
+    /// blocking job and upload and when the Context is dropped, tests can check after anything else.
+    ///
+    /// #### NOTE
+    /// The messenger callback only records the error, a panic can't unwind through the Vulkan loader.
+    #[inline(always)]
+    pub fn check_validation(&self) {
+        #[cfg(feature = "dev")]
+        if let Some(error) = self.debug_messenger_manager.strict_failure() {
+            panic!("Validation error in strict mode: {error}");
+        }
+    }
+
+    #[inline(always)]
+    pub fn frames_in_flight(&self) -> usize {
+        self.sync_manager.frames_in_flight()
+    }
+
+    #[inline(always)]
+    pub fn current_frame_index(&self) -> usize {
+        self.sync_manager.current_frame_index()
+    }
+
+    /// #### Description
+    /// Blocks until the GPU finishes the work submitted for the frame with the given index.
+    #[inline(always)]
+    pub fn wait_for_frame(&self, frame_index: usize) -> EngineResult<()> {
+        self.sync_manager
+            .wait_for_frame(&self.device_manager, frame_index)
+    }
+
+    /// #### Description
+    /// Waits until the current frame in flight is free and acquires the next image to render into.
+    /// Returns `None` if the frame must be skipped (minimized window or outdated swapchain).
+    /// The image of a frame which wasn't submitted is returned again.
+    pub fn begin_frame(&mut self) -> EngineResult<Option<u32>> {
+        if self.is_device_lost {
+            return Err(EngineError::DeviceLost);
+        }
+
+        if self.is_minimized() {
+            return Ok(None);
+        }
+
+        if let Presenter::Swapchain {
+            surface_manager,
+            swapchain_manager,
+        } = &mut self.presenter
+        {
+            if swapchain_manager.is_outdated() {
+                // The image of an abandoned frame goes away with the old swapchain, but its acquire
+                // semaphore is still signaled and must be waited on before it's used again.
+                if self.acquired_image_index.is_some() {
+                    let available_image_semaphore =
+                        self.sync_manager.current_frame().available_image_semaphore;
+                    Self::submit_frame(
+                        &self.device_manager,
+                        &self.command_manager,
+                        &mut self.sync_manager,
+                        &[],
+                        &[(
+                            available_image_semaphore,
+                            vk::PipelineStageFlags::ALL_COMMANDS,
+                        )],
+                        &[],
+                    )?;
+                    self.acquired_image_index = None;
+                }
+
+                swapchain_manager.recreate(
+                    &self.device_manager,
+                    surface_manager,
+                    self.window_extent,
+                )?;
+            }
+        }
+
+        let frame = self.sync_manager.current_frame();
+        self.device_manager
+            .wait_for_fences(&[frame.render_fence], u64::MAX)?;
+
+        let image_index = match (self.acquired_image_index, &mut self.presenter) {
+            (Some(image_index), _) => image_index,
+            (
+                None,
+                Presenter::Swapchain {
+                    swapchain_manager, ..
+                },
+            ) => {
+                let Some(image_index) =
+                    swapchain_manager.acquire_next_image(frame.available_image_semaphore)?
+                else {
+                    return Ok(None);
+                };
+
+                image_index
+            }
+            (None, Presenter::Offscreen(offscreen_manager)) => {
+                offscreen_manager.acquire_next_image()
+            }
+        };
+        // The fence stays signaled until the frame is submitted, so a frame abandoned
+        // before `end_frame` can't make the next wait on it hang.
+        self.acquired_image_index = Some(image_index);
+
+        self.command_manager.reset_frame(
+            &self.device_manager,
+            self.sync_manager.current_frame_index(),
+        )?;
+        self.descriptor_manager.reset_frame(
+            &self.device_manager,
+            self.sync_manager.current_frame_index(),
+        )?;
+        self.profiler_manager.begin_frame(
+            &self.device_manager,
+            self.sync_manager.current_frame_index(),
+        )?;
+
+        Ok(Some(image_index))
+    }
+
+    /// #### Description
+    /// Begins recording of a command buffer for the current frame in flight.
+    #[inline(always)]
+    pub fn record_commands(
+        &mut self,
+        queue_kind: QueueKind,
+        level: vk::CommandBufferLevel,
+        inheritance_info: Option<&vk::CommandBufferInheritanceInfo>,
+    ) -> EngineResult<CommandRecorder<'_>> {
+        self.command_manager.record(
+            &self.device_manager,
+            self.sync_manager.current_frame_index(),
+            queue_kind,
+            level,
+            inheritance_info,
+            &self.profiler_manager,
+        )
+    }
+
+    /// #### Description
+    /// Submits the work of the current frame, presents it and moves to the next frame in flight.
+    pub fn end_frame(
+        &mut self,
+        image_index: u32,
+        command_buffers: &[RecordedCommandBuffer],
+    ) -> EngineResult<()> {
+        let available_image_semaphore = self.sync_manager.current_frame().available_image_semaphore;
+
+        let result = match &mut self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => {
+                // Per image, a present of another image may still wait on the semaphore of this frame in flight.
+                let render_semaphore = swapchain_manager.render_semaphore(image_index);
+                // On failure the image stays acquired and is rendered into by the next frame.
+                Self::submit_frame(
+                    &self.device_manager,
+                    &self.command_manager,
+                    &mut self.sync_manager,
+                    command_buffers,
+                    &[(
+                        available_image_semaphore,
+                        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                    )],
+                    &[render_semaphore],
+                )?;
+                self.acquired_image_index = None;
+
+                swapchain_manager.present(
+                    self.device_manager.graphics_queue,
+                    render_semaphore,
+                    image_index,
+                )
+            }
+            // Offscreen images aren't acquired, so there is nothing to wait for.
+            Presenter::Offscreen(_) => {
+                Self::submit_frame(
+                    &self.device_manager,
+                    &self.command_manager,
+                    &mut self.sync_manager,
+                    command_buffers,
+                    &[],
+                    &[],
+                )?;
+                self.acquired_image_index = None;
+
+                Ok(())
+            }
+        };
+
+        self.sync_manager.advance_frame();
+        self.check_validation();
+
+        result
+    }
+
+    /// #### Description
+    /// Creates the Device again after `EngineError::DeviceLost`, together with sync primitives,
+    /// the swapchain or offscreen images, shader modules and every registered buffer and image
+    /// (from its description, under the same Id).
+    ///
+    /// #### NOTE
+    /// Contents of buffers and images are lost and must be uploaded again.
+    /// Pipelines, samplers, descriptor sets and bindless indices obtained before are invalid,
+    /// the returned `InvalidatedObjects` tells what the application must request again.
+    pub fn recover_device(&mut self) -> EngineResult<InvalidatedObjects> {
+        // Objects of the lost Device are already destroyed, a failed recovery isn't retried.
+        if self.is_device_lost {
+            return Err(EngineError::DeviceLost);
+        }
+
+        warning!("Vulkan Device was lost, recreating it with all GPU resources.");
+
+        // The lost Device doesn't execute anything anymore, waiting may report the loss again.
+        if let Err(e) = self.device_manager.wait_for_idle() {
+            debug!(std::format!("Waiting for the lost Device failed: {e}"));
+        }
+
+        let (buffer_descs, image_descs) = unsafe {
+            (
+                RESOURCE_MANAGER.buffer_descs(),
+                RESOURCE_MANAGER.image_descs(),
+            )
+        };
+        let invalidated_objects = InvalidatedObjects {
+            pipelines: self.pipeline_manager.pipeline_count(),
+            descriptor_set_layouts: self.descriptor_manager.layout_count(),
+            samplers: unsafe { RESOURCE_MANAGER.sampler_count() },
+        };
+        self.is_device_lost = true;
+        self.shader_manager.destroy_modules(&self.device_manager);
+        self.destroy_device_objects();
+
+        self.recreate_device_objects(&buffer_descs, &image_descs)?;
+        self.is_device_lost = false;
+        self.acquired_image_index = None;
+        self.check_validation();
+
+        info!("Vulkan Device was recreated.");
+
+        Ok(invalidated_objects)
+    }
+
+    /// #### Description
+    /// Submits work of the current frame in flight. Its fence is reset right before the submission
+    /// and replaced by a signaled one when the submission fails, so waiting on it never hangs.
+    fn submit_frame(
+        device_manager: &DeviceManager,
+        command_manager: &CommandManager,
+        sync_manager: &mut sync::SynchronizationPrimitivesManager,
+        command_buffers: &[RecordedCommandBuffer],
+        wait_semaphores: &[(vk::Semaphore, vk::PipelineStageFlags)],
+        signal_semaphores: &[vk::Semaphore],
+    ) -> EngineResult<()> {
+        let render_fence = sync_manager.current_frame().render_fence;
+        device_manager.reset_fences(&[render_fence])?;
+
+        let result = command_manager.submit(
+            device_manager,
+            command_buffers,
+            &SubmitDesc {
+                wait_semaphores,
+                signal_semaphores,
+                fence: render_fence,
+            },
+        );
+        if result.is_err() {
+            sync_manager.replace_current_fence(device_manager)?;
+        }
+
+        result
+    }
+
+    /// #### Description
+    /// Records a one-shot command buffer, submits it and blocks until it's finished.
+    fn run_immediate(
+        command_manager: &mut CommandManager,
+        device_manager: &DeviceManager,
+        queue_kind: QueueKind,
+        record: impl FnOnce(&CommandRecorder),
+    ) -> EngineResult<()> {
+        let fence = device_manager.create_fence(&vk::FenceCreateInfo::default())?;
+
+        let result = command_manager
+            .record_immediate(device_manager, queue_kind)
+            .and_then(|recorder| {
+                record(&recorder);
+
+                recorder.finish()
+            })
+            .and_then(|command_buffer| {
+                command_manager.submit(
+                    device_manager,
+                    &[command_buffer],
+                    &SubmitDesc {
+                        fence,
+                        ..Default::default()
+                    },
+                )
+            })
+            .and_then(|_| device_manager.wait_for_fences(&[fence], u64::MAX));
+
+        device_manager.destroy_fence(fence);
+        command_manager.reset_immediate(device_manager)?;
+
+        result
+    }
+
+    /// #### Description
+    /// Destroys every object created from the Device and the Device itself.
+    /// Shaders are left to the caller, recovery keeps them loaded.
+    ///
+    /// #### NOTE
+    /// Managers are left empty, so objects which aren't created again yet are never destroyed twice.
+    /// The pipeline cache isn't saved once the Device is lost.
+    fn destroy_device_objects(&mut self) {
+        self.pipeline_manager
+            .destroy_resources(&self.device_manager, !self.is_device_lost);
+        self.command_manager.destroy_resources(&self.device_manager);
+        self.profiler_manager
+            .destroy_resources(&self.device_manager);
+        self.descriptor_manager
+            .destroy_resources(&self.device_manager);
+        if let Some(bindless_manager) = self.bindless_manager.take() {
+            bindless_manager.destroy_resources(&self.device_manager);
+        }
+        self.sync_manager.destroy_resources(&self.device_manager);
+        match &mut self.presenter {
+            Presenter::Swapchain {
+                swapchain_manager, ..
+            } => swapchain_manager.destroy_resources(&self.device_manager),
+            Presenter::Offscreen(offscreen_manager) => {
+                offscreen_manager.destroy_resources(&self.device_manager, &mut self.allocator)
+            }
+        }
+        unsafe {
+            RESOURCE_MANAGER.delete_buffers(&self.device_manager, &mut self.allocator);
+            RESOURCE_MANAGER.delete_images(&self.device_manager, &mut self.allocator);
+            RESOURCE_MANAGER.delete_samplers(&self.device_manager);
+        }
+        self.allocator.destroy_resources(&self.device_manager);
+        self.device_manager.destroy_device();
+    }
+
+    /// #### Description
+    /// Counterpart of `destroy_device_objects`, creates a new Device and its objects with the same configuration.
+    fn recreate_device_objects(
+        &mut self,
+        buffer_descs: &[(Id, BufferDesc)],
+        image_descs: &[(Id, ImageDesc)],
+    ) -> EngineResult<()> {
+        let surface_manager = match &self.presenter {
+            Presenter::Swapchain {
+                surface_manager, ..
+            } => Some(surface_manager),
+            Presenter::Offscreen(_) => None,
+        };
+        #[cfg_attr(not(feature = "dev"), allow(unused_mut))]
+        let mut device_manager = DeviceManager::new(
+            &self.instance_manager.instance,
+            surface_manager,
+            self.config.bindless,
+            &self.config.device_selection,
+            &self.config.device_requirements,
+        )?;
+        #[cfg(feature = "dev")]
+        device_manager.enable_debug_names(self.debug_messenger_manager.debug_utils_loader.clone());
+        self.device_manager = device_manager;
+
+        // Nothing of the new Device is kept after a failure, so the Context stays in the lost state
+        // with no live objects and `Drop` has nothing left to release.
+        if let Err(e) = self.create_device_objects(buffer_descs, image_descs) {
+            self.shader_manager.destroy_modules(&self.device_manager);
+            self.destroy_device_objects();
+
+            return Err(e);
+        }
+
+        #[cfg(feature = "profiling")]
+        self.connect_tracy();
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Creates objects of a new Device. On failure the ones created so far stay in the managers.
+    fn create_device_objects(
+        &mut self,
+        buffer_descs: &[(Id, BufferDesc)],
+        image_descs: &[(Id, ImageDesc)],
+    ) -> EngineResult<()> {
+        self.sync_manager = sync::SynchronizationPrimitivesManager::new(
+            &self.device_manager,
+            self.config.frames_in_flight,
+        )?;
+        self.allocator = GpuAllocator::new(&self.device_manager);
+        match &mut self.presenter {
+            Presenter::Swapchain {
+                surface_manager,
+                swapchain_manager,
+            } => {
+                *swapchain_manager = SwapchainManager::new(
+                    &self.instance_manager.instance,
+                    &self.device_manager,
+                    surface_manager,
+                    self.window_extent,
+                    &self.config.surface_formats,
+                    self.config.vsync,
+                )?
+            }
+            Presenter::Offscreen(offscreen_manager) => {
+                *offscreen_manager = OffscreenManager::new(
+                    &self.device_manager,
+                    &mut self.allocator,
+                    offscreen_manager.extent,
+                )?
+            }
+        }
+
+        self.command_manager = CommandManager::new(self.config.frames_in_flight);
+        self.descriptor_manager = DescriptorManager::new(self.config.frames_in_flight);
+        self.bindless_manager = if self.config.bindless {
+            Some(BindlessManager::new(&self.device_manager)?)
+        } else {
+            None
+        };
+        self.pipeline_manager =
+            PipelineManager::new(&self.device_manager, &self.config.data_directory)?;
+        self.profiler_manager =
+            ProfilerManager::new(&self.device_manager, self.config.frames_in_flight)?;
+        self.shader_manager.recreate_modules(&self.device_manager)?;
+
+        for &(id, desc) in buffer_descs {
+            let buffer = Buffer::new(&self.device_manager, &mut self.allocator, id, desc)?;
+            unsafe { RESOURCE_MANAGER.register_buffer(buffer) };
+        }
+        for &(id, desc) in image_descs {
+            let image = Image::new(&self.device_manager, &mut self.allocator, id, desc)?;
+            unsafe { RESOURCE_MANAGER.register_image(image) };
+        }
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Creates the Tracy GPU context calibrated with a timestamp written right now.
+    /// Failure only disables GPU zones in Tracy.
+    #[cfg(feature = "profiling")]
+    fn connect_tracy(&mut self) {
+        if !self.profiler_manager.is_enabled() {
+            return;
+        }
+
+        let profiler_manager = &self.profiler_manager;
+        let mut query = None;
+        let result = Self::run_immediate(
+            &mut self.command_manager,
+            &self.device_manager,
+            QueueKind::Graphics,
+            |recorder| query = profiler_manager.write_calibration_timestamp(recorder),
+        )
+        .and_then(|_| match query {
+            Some(query) => profiler_manager
+                .read_calibration_timestamp(&self.device_manager, query)
+                .map(Some),
+            None => Ok(None),
+        });
+
+        match result {
+            Ok(Some(gpu_timestamp)) => self.profiler_manager.connect_tracy(gpu_timestamp),
+            Ok(None) => {}
+            Err(e) => warning!(std::format!(
+                "Failed to calibrate GPU timestamps for Tracy: {e}"
+            )),
+        }
+    }
+
+    #[inline(always)]
+    fn bindless_manager(&self) -> EngineResult<&BindlessManager> {
+        self.bindless_manager
+            .as_ref()
+            .ok_or_else(Self::bindless_disabled_error)
+    }
+
+    #[inline(always)]
+    fn bindless_disabled_error() -> EngineError {
+        EngineError::UnknownError(
+            "Bindless mode isn't enabled, see `RendererConfig::bindless`".to_owned(),
+        )
+    }
+
+    /// #### Description
+    /// Records `record_copy` from a staging buffer with `data` on the transfer queue.
+    /// `ownership_barriers(src_family, dst_family, is_acquire)` returns barriers which make the result
+    /// visible to the graphics queue. Exclusive resources use them as a release/acquire pair when
+    /// queue families differ, concurrent ones only get the final barrier recorded on the graphics queue.
+    fn upload(
+        &mut self,
+        data: &[u8],
+        is_concurrent: bool,
+        record_copy: impl FnOnce(&CommandRecorder, vk::Buffer),
+        ownership_barriers: impl Fn(
+            u32,
+            u32,
+            bool,
+        ) -> (
+            Vec<vk::BufferMemoryBarrier2<'static>>,
+            Vec<vk::ImageMemoryBarrier2<'static>>,
+        ),
+    ) -> EngineResult<()> {
+        // Released on every path, also when creating, recording or submitting fails halfway.
+        let mut upload_objects = UploadObjects::default();
+        let result = self.submit_upload(
+            &mut upload_objects,
+            data,
+            is_concurrent,
+            record_copy,
+            ownership_barriers,
+        );
+
+        upload_objects.destroy(&self.device_manager, &mut self.allocator);
+        let reset_result = self.command_manager.reset_immediate(&self.device_manager);
+        self.check_validation();
+
+        result.and(reset_result)
+    }
+
+    /// #### Description
+    /// Body of `upload`, objects it creates are stored in `upload_objects` for the caller to destroy.
+    fn submit_upload(
+        &mut self,
+        upload_objects: &mut UploadObjects,
+        data: &[u8],
+        is_concurrent: bool,
+        record_copy: impl FnOnce(&CommandRecorder, vk::Buffer),
+        ownership_barriers: impl Fn(
+            u32,
+            u32,
+            bool,
+        ) -> (
+            Vec<vk::BufferMemoryBarrier2<'static>>,
+            Vec<vk::ImageMemoryBarrier2<'static>>,
+        ),
+    ) -> EngineResult<()> {
+        let transfer_family_index = self.device_manager.queue_family_index(QueueKind::Transfer);
+        let graphics_family_index = self.device_manager.queue_family_index(QueueKind::Graphics);
+        let is_cross_family = transfer_family_index != graphics_family_index;
+        let (src_family_index, dst_family_index) = if is_cross_family && !is_concurrent {
+            (transfer_family_index, graphics_family_index)
+        } else {
+            (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+        };
+
+        let staging_id = unsafe { RESOURCE_MANAGER.next_id() };
+        let staging_buffer = upload_objects.staging_buffer.insert(Buffer::new(
+            &self.device_manager,
+            &mut self.allocator,
+            staging_id,
+            BufferDesc::staging(data.len() as vk::DeviceSize),
+        )?);
+        staging_buffer.write(Default::default(), data)?;
+
+        upload_objects.fence = self
+            .device_manager
+            .create_fence(&vk::FenceCreateInfo::default())?;
+        upload_objects.semaphore = self
+            .device_manager
+            .create_semaphore(&vk::SemaphoreCreateInfo::default())?;
+
+        let transfer_recorder = self
+            .command_manager
+            .record_immediate(&self.device_manager, QueueKind::Transfer)?;
+        record_copy(&transfer_recorder, staging_buffer.buffer);
+        // The transfer queue may not support stages of the graphics queue, which concurrent resources
+        // on another family would need in a single barrier, the semaphore orders the copy instead.
+        if !(is_cross_family && is_concurrent) {
+            let (buffer_barriers, image_barriers) =
+                ownership_barriers(src_family_index, dst_family_index, false);
+            transfer_recorder.pipeline_barrier(&buffer_barriers, &image_barriers);
+        }
+        let transfer_command_buffer = transfer_recorder.finish()?;
+
+        if is_cross_family {
+            let graphics_recorder = self
+                .command_manager
+                .record_immediate(&self.device_manager, QueueKind::Graphics)?;
+            let (buffer_barriers, image_barriers) =
+                ownership_barriers(src_family_index, dst_family_index, true);
+            graphics_recorder.pipeline_barrier(&buffer_barriers, &image_barriers);
+            let graphics_command_buffer = graphics_recorder.finish()?;
+
+            self.command_manager.submit(
+                &self.device_manager,
+                &[transfer_command_buffer],
+                &SubmitDesc {
+                    signal_semaphores: &[upload_objects.semaphore],
+                    ..Default::default()
+                },
+            )?;
+            self.command_manager.submit(
+                &self.device_manager,
+                &[graphics_command_buffer],
+                &SubmitDesc {
+                    wait_semaphores: &[(
+                        upload_objects.semaphore,
+                        vk::PipelineStageFlags::ALL_COMMANDS,
+                    )],
+                    fence: upload_objects.fence,
+                    ..Default::default()
+                },
+            )?;
+        } else {
+            self.command_manager.submit(
+                &self.device_manager,
+                &[transfer_command_buffer],
+                &SubmitDesc {
+                    fence: upload_objects.fence,
+                    ..Default::default()
+                },
+            )?;
+        }
+
+        self.device_manager
+            .wait_for_fences(&[upload_objects.fence], u64::MAX)
+    }
+
+    /// #### Description
+    /// Release half of an ownership transfer only makes writes available, acquire half makes them
+    /// visible for reads on the graphics queue. Without a transfer both halves are in one barrier.
+    #[inline(always)]
+    fn upload_barrier_stages<B: UploadBarrier>(barrier: B, is_acquire: bool) -> B {
+        let is_release = barrier.is_ownership_transfer() && !is_acquire;
+        let is_acquire = barrier.is_ownership_transfer() && is_acquire;
+
+        let (src_stage, src_access) = if is_acquire {
+            (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE)
+        } else {
+            (
+                vk::PipelineStageFlags2::COPY,
+                vk::AccessFlags2::TRANSFER_WRITE,
+            )
+        };
+        let (dst_stage, dst_access) = if is_release {
+            (vk::PipelineStageFlags2::NONE, vk::AccessFlags2::NONE)
+        } else {
+            (
+                vk::PipelineStageFlags2::ALL_COMMANDS,
+                vk::AccessFlags2::MEMORY_READ,
+            )
+        };
+
+        barrier.with_stages(src_stage, src_access, dst_stage, dst_access)
     }
 
     #[cfg(feature = "dev")]
@@ -153,25 +1572,116 @@ impl Context<'_> {
     }
 }
 
+/// #### Description
+/// Objects of a single upload, null handles until they are created.
+#[derive(Default)]
+struct UploadObjects {
+    staging_buffer: Option<Buffer>,
+    fence: vk::Fence,
+    semaphore: vk::Semaphore,
+}
+
+impl UploadObjects {
+    /// #### Description
+    /// Destroying null handles is a no-op, so it's fine after a partial upload.
+    fn destroy(self, device: &DeviceManager, allocator: &mut GpuAllocator) {
+        device.destroy_fence(self.fence);
+        device.destroy_semaphore(self.semaphore);
+        if let Some(staging_buffer) = self.staging_buffer {
+            staging_buffer.destroy(device, allocator);
+        }
+    }
+}
+
+trait UploadBarrier {
+    fn is_ownership_transfer(&self) -> bool;
+
+    fn with_stages(
+        self,
+        src_stage: vk::PipelineStageFlags2,
+        src_access: vk::AccessFlags2,
+        dst_stage: vk::PipelineStageFlags2,
+        dst_access: vk::AccessFlags2,
+    ) -> Self;
+}
+
+impl UploadBarrier for vk::BufferMemoryBarrier2<'_> {
+    #[inline(always)]
+    fn is_ownership_transfer(&self) -> bool {
+        self.src_queue_family_index != self.dst_queue_family_index
+    }
+
+    #[inline(always)]
+    fn with_stages(
+        self,
+        src_stage: vk::PipelineStageFlags2,
+        src_access: vk::AccessFlags2,
+        dst_stage: vk::PipelineStageFlags2,
+        dst_access: vk::AccessFlags2,
+    ) -> Self {
+        self.src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(dst_stage)
+            .dst_access_mask(dst_access)
+    }
+}
+
+impl UploadBarrier for vk::ImageMemoryBarrier2<'_> {
+    #[inline(always)]
+    fn is_ownership_transfer(&self) -> bool {
+        self.src_queue_family_index != self.dst_queue_family_index
+    }
+
+    #[inline(always)]
+    fn with_stages(
+        self,
+        src_stage: vk::PipelineStageFlags2,
+        src_access: vk::AccessFlags2,
+        dst_stage: vk::PipelineStageFlags2,
+        dst_access: vk::AccessFlags2,
+    ) -> Self {
+        self.src_stage_mask(src_stage)
+            .src_access_mask(src_access)
+            .dst_stage_mask(dst_stage)
+            .dst_access_mask(dst_access)
+    }
+}
+
 impl Drop for Context<'_> {
     fn drop(&mut self) {
-        unsafe {
-            self.device_manager.wait_for_idle().unwrap();
+        // After a failed recovery objects of the lost Device are already destroyed.
+        if !self.is_device_lost {
+            if let Err(e) = self.device_manager.wait_for_idle() {
+                error!(std::format!(
+                    "Failed to wait for the Device to be idle: {e}"
+                ));
+            }
             self.shader_manager.unload_shaders(&self.device_manager);
-            self.sync_manager.destroy_resources(&self.device_manager);
-            self.device_manager.destroy_device();
+            self.destroy_device_objects();
+        }
 
+        unsafe {
             #[cfg(feature = "dev")]
             self.debug_messenger_manager
                 .debug_utils_loader
                 .destroy_debug_utils_messenger(self.debug_messenger_manager.debug_utils, None);
 
+            if let Presenter::Swapchain {
+                surface_manager, ..
+            } = &self.presenter
+            {
+                surface_manager
+                    .surface_loader
+                    .destroy_surface(surface_manager.surface, None);
+            }
             self.instance_manager.instance.destroy_instance(None);
-            self.surface_manager
-                .surface_loader
-                .destroy_surface(self.surface_manager.surface, None);
 
             ManuallyDrop::drop(&mut self.entry);
         }
+
+        // Teardown is validated too, e.g. objects which are still in use. A second panic would abort.
+        if !std::thread::panicking() {
+            self.check_validation();
+        }
     }
 }
@@ -0,0 +1,620 @@
+use logging::*;
+
+use crate::error::{EngineError, EngineResult};
+
+use std::ptr::NonNull;
+
+use ahash::AHashMap;
+use ash::vk;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryLocation {
+    /// Device local memory, not accessible from the CPU.
+    GpuOnly,
+    /// Host visible memory for uploads, device local if the GPU has such (e.g. ReBAR or UMA).
+    CpuToGpu,
+    /// Host visible memory for readbacks, cached if possible.
+    GpuToCpu,
+}
+
+/// #### Description
+/// Linear resources (buffers, linear images) and optimal images are never placed in the same block,
+/// so they can't violate `bufferImageGranularity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AllocationKind {
+    Linear,
+    Optimal,
+}
+
+type PoolKey = (u32, AllocationKind);
+
+#[derive(Debug)]
+pub struct Allocation {
+    pub memory: vk::DeviceMemory,
+    pub offset: vk::DeviceSize,
+    pub size: vk::DeviceSize,
+    pub memory_type_index: u32,
+    mapped_ptr: Option<NonNull<u8>>,
+    pool_key: PoolKey,
+    block_index: usize,
+}
+
+impl Allocation {
+    /// #### Description
+    /// Pointer to the start of the allocation, `None` if the memory isn't host visible.
+    #[inline(always)]
+    pub fn mapped_ptr(&self) -> Option<NonNull<u8>> {
+        self.mapped_ptr
+    }
+
+    /// #### Description
+    /// Mapped memory of the allocation, `None` if the memory isn't host visible.
+    #[inline(always)]
+    pub fn mapped_slice_mut(&mut self) -> Option<&mut [u8]> {
+        self.mapped_ptr.map(|mapped_ptr| unsafe {
+            std::slice::from_raw_parts_mut(mapped_ptr.as_ptr(), self.size as usize)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapStatistics {
+    pub heap_index: u32,
+    pub heap_size: vk::DeviceSize,
+    pub heap_flags: vk::MemoryHeapFlags,
+    pub block_count: usize,
+    pub allocation_count: usize,
+    /// Bytes allocated from Vulkan as `vk::DeviceMemory`.
+    pub reserved_bytes: vk::DeviceSize,
+    /// Bytes handed out to resources.
+    pub used_bytes: vk::DeviceSize,
+}
+
+struct MemoryBlock {
+    memory: vk::DeviceMemory,
+    size: vk::DeviceSize,
+    mapped_ptr: Option<NonNull<u8>>,
+    /// Sorted by offset, neighbours are always merged.
+    free_ranges: Vec<(vk::DeviceSize, vk::DeviceSize)>,
+    allocation_count: usize,
+    is_dedicated: bool,
+}
+
+impl MemoryBlock {
+    fn new(
+        device: &super::DeviceManager,
+        size: vk::DeviceSize,
+        memory_type_index: u32,
+        is_host_visible: bool,
+        is_dedicated: bool,
+    ) -> EngineResult<Self> {
+        let memory_info = vk::MemoryAllocateInfo::default()
+            .allocation_size(size)
+            .memory_type_index(memory_type_index);
+        let memory = device.allocate_memory(&memory_info)?;
+
+        // Host visible blocks are mapped once for the whole lifetime of the block.
+        let mapped_ptr = if is_host_visible {
+            match device.map_memory(memory, Default::default(), vk::WHOLE_SIZE) {
+                Ok(mapped_ptr) => NonNull::new(mapped_ptr.cast::<u8>()),
+                Err(e) => {
+                    device.free_memory(memory);
+
+                    return Err(e);
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(Self {
+            memory,
+            size,
+            mapped_ptr,
+            free_ranges: vec![(Default::default(), size)],
+            allocation_count: Default::default(),
+            is_dedicated,
+        })
+    }
+
+    /// #### Description
+    /// First-fit search, returns the offset of the allocated range.
+    fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Option<vk::DeviceSize> {
+        let (range_index, aligned_offset) =
+            self.free_ranges
+                .iter()
+                .enumerate()
+                .find_map(|(i, &(offset, range_size))| {
+                    let aligned_offset = align_up(offset, alignment);
+                    let padding = aligned_offset - offset;
+
+                    (range_size >= size + padding).then_some((i, aligned_offset))
+                })?;
+
+        let (offset, range_size) = self.free_ranges.remove(range_index);
+        let end = offset + range_size;
+        let allocation_end = aligned_offset + size;
+
+        if allocation_end < end {
+            self.free_ranges
+                .insert(range_index, (allocation_end, end - allocation_end));
+        }
+        if aligned_offset > offset {
+            self.free_ranges
+                .insert(range_index, (offset, aligned_offset - offset));
+        }
+
+        self.allocation_count += 1;
+
+        Some(aligned_offset)
+    }
+
+    fn free(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let index = self
+            .free_ranges
+            .partition_point(|&(free_offset, _)| free_offset < offset);
+        self.free_ranges.insert(index, (offset, size));
+
+        // Merge with the next range.
+        if let Some(&(next_offset, next_size)) = self.free_ranges.get(index + 1) {
+            if offset + size == next_offset {
+                self.free_ranges[index].1 += next_size;
+                self.free_ranges.remove(index + 1);
+            }
+        }
+
+        // Merge with the previous range.
+        if index > Default::default() {
+            let (previous_offset, previous_size) = self.free_ranges[index - 1];
+            if previous_offset + previous_size == offset {
+                self.free_ranges[index - 1].1 += self.free_ranges[index].1;
+                self.free_ranges.remove(index);
+            }
+        }
+
+        self.allocation_count -= 1;
+    }
+
+    #[inline(always)]
+    fn used_bytes(&self) -> vk::DeviceSize {
+        self.size
+            - self
+                .free_ranges
+                .iter()
+                .map(|&(_, range_size)| range_size)
+                .sum::<vk::DeviceSize>()
+    }
+
+    #[inline(always)]
+    fn destroy(&self, device: &super::DeviceManager) {
+        if self.mapped_ptr.is_some() {
+            device.unmap_memory(self.memory);
+        }
+        device.free_memory(self.memory);
+    }
+}
+
+#[derive(Default)]
+struct MemoryPool {
+    blocks: Vec<Option<MemoryBlock>>,
+}
+
+/// #### Description
+/// Sub-allocates big `vk::DeviceMemory` blocks per memory type,
+/// allocations bigger than a half of a block get their own dedicated block.
+pub struct GpuAllocator {
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+    buffer_image_granularity: vk::DeviceSize,
+    pools: AHashMap<PoolKey, MemoryPool>,
+}
+
+impl GpuAllocator {
+    pub const DEFAULT_BLOCK_SIZE: vk::DeviceSize = 64 * 1024 * 1024;
+    const SMALL_HEAP_SIZE: vk::DeviceSize = 1024 * 1024 * 1024;
+
+    pub fn new(device: &super::DeviceManager) -> Self {
+        debug!("Initializing GPU memory allocator.");
+
+        let memory_properties = device.memory_properties;
+        let buffer_image_granularity = device.device_properties.limits.buffer_image_granularity;
+
+        let mut memory_info = String::from("Memory Properties:\n");
+        memory_properties.memory_heaps[..memory_properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .for_each(|(i, memory_heap)| {
+                memory_info.push_str(&std::format!(
+                    "{TAB_IN_SPACES}- Heap {i}: {} MiB, {:?}\n",
+                    memory_heap.size / (1024 * 1024),
+                    memory_heap.flags
+                ));
+            });
+        memory_properties.memory_types[..memory_properties.memory_type_count as usize]
+            .iter()
+            .enumerate()
+            .for_each(|(i, memory_type)| {
+                memory_info.push_str(&std::format!(
+                    "{TAB_IN_SPACES}- Type {i}: Heap {}, {:?}\n",
+                    memory_type.heap_index,
+                    memory_type.property_flags
+                ));
+            });
+        debug!(memory_info);
+
+        Self {
+            memory_properties,
+            buffer_image_granularity,
+            pools: Default::default(),
+        }
+    }
+
+    pub fn allocate(
+        &mut self,
+        device: &super::DeviceManager,
+        requirements: vk::MemoryRequirements,
+        location: MemoryLocation,
+        kind: AllocationKind,
+    ) -> EngineResult<Allocation> {
+        let memory_type_index =
+            self.find_memory_type_index(requirements.memory_type_bits, location)?;
+        let property_flags =
+            self.memory_properties.memory_types[memory_type_index as usize].property_flags;
+        let is_host_visible = property_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+
+        let pool_key = (memory_type_index, self.pool_kind(kind));
+        let block_size = self.block_size(memory_type_index);
+        let pool = self.pools.entry(pool_key).or_default();
+
+        let (block_index, offset) = if Self::needs_dedicated_block(requirements.size, block_size) {
+            let block = MemoryBlock::new(
+                device,
+                requirements.size,
+                memory_type_index,
+                is_host_visible,
+                true,
+            )?;
+
+            (Self::insert_block(pool, block), Default::default())
+        } else {
+            let found = pool
+                .blocks
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(i, block)| block.as_mut().map(|block| (i, block)))
+                .filter(|(_, block)| !block.is_dedicated)
+                .find_map(|(i, block)| {
+                    block
+                        .allocate(requirements.size, requirements.alignment)
+                        .map(|offset| (i, offset))
+                });
+
+            match found {
+                Some(found) => found,
+                None => {
+                    let mut block = MemoryBlock::new(
+                        device,
+                        block_size,
+                        memory_type_index,
+                        is_host_visible,
+                        false,
+                    )?;
+                    let offset = block
+                        .allocate(requirements.size, requirements.alignment)
+                        .ok_or_else(|| {
+                            EngineError::UnknownError(
+                                "Allocation doesn't fit into a new memory block".to_owned(),
+                            )
+                        })?;
+
+                    (Self::insert_block(pool, block), offset)
+                }
+            }
+        };
+
+        let block = pool.blocks[block_index].as_mut().unwrap();
+        if block.is_dedicated {
+            block.allocation_count += 1;
+            block.free_ranges.clear();
+        }
+
+        Ok(Allocation {
+            memory: block.memory,
+            offset,
+            size: requirements.size,
+            memory_type_index,
+            mapped_ptr: block
+                .mapped_ptr
+                .map(|mapped_ptr| unsafe { mapped_ptr.add(offset as usize) }),
+            pool_key,
+            block_index,
+        })
+    }
+
+    pub fn free(&mut self, device: &super::DeviceManager, allocation: Allocation) {
+        let Some(pool) = self.pools.get_mut(&allocation.pool_key) else {
+            return;
+        };
+        let Some(block) = pool.blocks[allocation.block_index].as_mut() else {
+            return;
+        };
+
+        if block.is_dedicated {
+            block.destroy(device);
+            pool.blocks[allocation.block_index] = None;
+
+            return;
+        }
+
+        block.free(allocation.offset, allocation.size);
+
+        // Keep at least one empty block around to avoid allocation churn every frame.
+        if block.allocation_count == Default::default() {
+            let empty_block_count = pool
+                .blocks
+                .iter()
+                .flatten()
+                .filter(|block| !block.is_dedicated && block.allocation_count == 0)
+                .count();
+            if empty_block_count > 1 {
+                pool.blocks[allocation.block_index]
+                    .take()
+                    .unwrap()
+                    .destroy(device);
+            }
+        }
+    }
+
+    /// #### Description
+    /// Usage of every memory heap of the physical device.
+    pub fn statistics(&self) -> Vec<HeapStatistics> {
+        let mut statistics = self.memory_properties.memory_heaps
+            [..self.memory_properties.memory_heap_count as usize]
+            .iter()
+            .enumerate()
+            .map(|(i, memory_heap)| HeapStatistics {
+                heap_index: i as u32,
+                heap_size: memory_heap.size,
+                heap_flags: memory_heap.flags,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        for (&(memory_type_index, _), pool) in &self.pools {
+            let heap_index =
+                self.memory_properties.memory_types[memory_type_index as usize].heap_index;
+            let heap_statistics = &mut statistics[heap_index as usize];
+
+            for block in pool.blocks.iter().flatten() {
+                heap_statistics.block_count += 1;
+                heap_statistics.allocation_count += block.allocation_count;
+                heap_statistics.reserved_bytes += block.size;
+                heap_statistics.used_bytes += block.used_bytes();
+            }
+        }
+
+        statistics
+    }
+
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        for (_, pool) in self.pools.drain() {
+            for block in pool.blocks.iter().flatten() {
+                if block.allocation_count > Default::default() {
+                    warning!(std::format!(
+                        "Destroying memory block with {} alive allocations.",
+                        block.allocation_count
+                    ));
+                }
+
+                block.destroy(device);
+            }
+        }
+    }
+
+    fn find_memory_type_index(
+        &self,
+        memory_type_bits: u32,
+        location: MemoryLocation,
+    ) -> EngineResult<u32> {
+        let (required_flags, preferred_flags) = match location {
+            MemoryLocation::GpuOnly => (
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+                vk::MemoryPropertyFlags::empty(),
+            ),
+            MemoryLocation::CpuToGpu => (
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            ),
+            MemoryLocation::GpuToCpu => (
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                vk::MemoryPropertyFlags::HOST_CACHED,
+            ),
+        };
+
+        let memory_types = &self.memory_properties.memory_types
+            [..self.memory_properties.memory_type_count as usize];
+        let find = |flags: vk::MemoryPropertyFlags| {
+            memory_types
+                .iter()
+                .enumerate()
+                .find(|(i, memory_type)| {
+                    (memory_type_bits & (1 << i)) != Default::default()
+                        && memory_type.property_flags.contains(flags)
+                })
+                .map(|(i, _)| i as u32)
+        };
+
+        find(required_flags | preferred_flags)
+            .or_else(|| find(required_flags))
+            .ok_or_else(|| {
+                EngineError::UnknownError(std::format!(
+                    "Unable to find memory type for {location:?} (type bits {memory_type_bits:#b})"
+                ))
+            })
+    }
+
+    /// #### Description
+    /// When the granularity is 1 linear and optimal resources can share blocks safely.
+    #[inline(always)]
+    fn pool_kind(&self, kind: AllocationKind) -> AllocationKind {
+        if self.buffer_image_granularity > 1 {
+            kind
+        } else {
+            AllocationKind::Linear
+        }
+    }
+
+    #[inline(always)]
+    fn needs_dedicated_block(size: vk::DeviceSize, block_size: vk::DeviceSize) -> bool {
+        size > block_size / 2
+    }
+
+    /// #### Description
+    /// Small heaps (e.g. 256 MiB BAR heap) get smaller blocks, so a single block can't exhaust them.
+    #[inline(always)]
+    fn block_size(&self, memory_type_index: u32) -> vk::DeviceSize {
+        let heap_index = self.memory_properties.memory_types[memory_type_index as usize].heap_index;
+        let heap_size = self.memory_properties.memory_heaps[heap_index as usize].size;
+
+        if heap_size <= Self::SMALL_HEAP_SIZE {
+            (heap_size / 8).min(Self::DEFAULT_BLOCK_SIZE)
+        } else {
+            Self::DEFAULT_BLOCK_SIZE
+        }
+    }
+
+    #[inline(always)]
+    fn insert_block(pool: &mut MemoryPool, block: MemoryBlock) -> usize {
+        match pool.blocks.iter().position(Option::is_none) {
+            Some(index) => {
+                pool.blocks[index] = Some(block);
+
+                index
+            }
+            None => {
+                pool.blocks.push(Some(block));
+
+                pool.blocks.len() - 1
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: vk::DeviceSize = 1024 * 1024;
+
+    fn memory_block(size: vk::DeviceSize) -> MemoryBlock {
+        MemoryBlock {
+            memory: vk::DeviceMemory::null(),
+            size,
+            mapped_ptr: None,
+            free_ranges: vec![(0, size)],
+            allocation_count: 0,
+            is_dedicated: false,
+        }
+    }
+
+    fn allocator(heap_size: vk::DeviceSize) -> GpuAllocator {
+        let mut memory_properties = vk::PhysicalDeviceMemoryProperties {
+            memory_type_count: 1,
+            memory_heap_count: 1,
+            ..Default::default()
+        };
+        memory_properties.memory_types[0] = vk::MemoryType {
+            property_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            heap_index: 0,
+        };
+        memory_properties.memory_heaps[0] = vk::MemoryHeap {
+            size: heap_size,
+            flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+        };
+
+        GpuAllocator {
+            memory_properties,
+            buffer_image_granularity: 1,
+            pools: Default::default(),
+        }
+    }
+
+    #[test]
+    fn allocation_splits_free_range_around_alignment_padding() {
+        let mut block = memory_block(1024);
+
+        assert_eq!(block.allocate(100, 1), Some(0));
+        assert_eq!(block.allocate(100, 256), Some(256));
+
+        assert_eq!(block.free_ranges, vec![(100, 156), (356, 668)]);
+        assert_eq!(block.allocation_count, 2);
+        assert_eq!(block.used_bytes(), 200);
+    }
+
+    #[test]
+    fn allocation_uses_first_range_which_fits() {
+        let mut block = memory_block(1024);
+        for _ in 0..3 {
+            block.allocate(100, 1).unwrap();
+        }
+        block.free(100, 100);
+
+        assert_eq!(block.allocate(50, 1), Some(100));
+        assert_eq!(block.allocate(100, 1), Some(300));
+        assert_eq!(block.allocate(1024, 1), None);
+    }
+
+    #[test]
+    fn free_merges_neighbouring_ranges() {
+        let mut block = memory_block(1024);
+        let offsets = (0..3).map(|_| block.allocate(100, 1)).collect::<Vec<_>>();
+        assert_eq!(offsets, vec![Some(0), Some(100), Some(200)]);
+
+        block.free(100, 100);
+        assert_eq!(block.free_ranges, vec![(100, 100), (300, 724)]);
+
+        block.free(0, 100);
+        assert_eq!(block.free_ranges, vec![(0, 200), (300, 724)]);
+
+        block.free(200, 100);
+        assert_eq!(block.free_ranges, vec![(0, 1024)]);
+        assert_eq!(block.allocation_count, 0);
+        assert_eq!(block.used_bytes(), 0);
+    }
+
+    #[test]
+    fn allocations_bigger_than_half_of_a_block_are_dedicated() {
+        let allocator = allocator(8 * 1024 * MIB);
+        let block_size = allocator.block_size(0);
+
+        assert_eq!(block_size, GpuAllocator::DEFAULT_BLOCK_SIZE);
+        assert!(!GpuAllocator::needs_dedicated_block(
+            block_size / 2,
+            block_size
+        ));
+        assert!(GpuAllocator::needs_dedicated_block(
+            block_size / 2 + 1,
+            block_size
+        ));
+    }
+
+    #[test]
+    fn small_heaps_get_smaller_blocks() {
+        let allocator = allocator(256 * MIB);
+        let block_size = allocator.block_size(0);
+
+        assert_eq!(block_size, 32 * MIB);
+        assert!(GpuAllocator::needs_dedicated_block(
+            16 * MIB + 1,
+            block_size
+        ));
+    }
+}
@@ -0,0 +1,341 @@
+use logging::*;
+
+use crate::{
+    common::Id,
+    error::{EngineError, EngineResult},
+};
+
+use super::resource_manager::RESOURCE_MANAGER;
+
+use ahash::AHashMap;
+use ash::vk;
+
+/// #### Description
+/// Kind of resource in the global bindless set, each kind is a separate array binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BindlessKind {
+    /// Sampled image in `SHADER_READ_ONLY_OPTIMAL` layout, `binding = 0`.
+    Texture,
+    /// Storage image in `GENERAL` layout, `binding = 1`.
+    StorageImage,
+    /// Storage buffer, `binding = 2`.
+    StorageBuffer,
+}
+
+impl BindlessKind {
+    #[inline(always)]
+    fn binding(self) -> u32 {
+        match self {
+            Self::Texture => 0,
+            Self::StorageImage => 1,
+            Self::StorageBuffer => 2,
+        }
+    }
+
+    #[inline(always)]
+    fn descriptor_type(self) -> vk::DescriptorType {
+        match self {
+            Self::Texture => vk::DescriptorType::SAMPLED_IMAGE,
+            Self::StorageImage => vk::DescriptorType::STORAGE_IMAGE,
+            Self::StorageBuffer => vk::DescriptorType::STORAGE_BUFFER,
+        }
+    }
+}
+
+/// #### Description
+/// Hands out indices into one array binding, freed indices are reused before new ones.
+struct IndexAllocator {
+    capacity: u32,
+    next_index: u32,
+    free_indices: Vec<u32>,
+}
+
+impl IndexAllocator {
+    #[inline(always)]
+    fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            next_index: Default::default(),
+            free_indices: Default::default(),
+        }
+    }
+
+    #[inline(always)]
+    fn allocate(&mut self) -> Option<u32> {
+        self.free_indices.pop().or_else(|| {
+            (self.next_index < self.capacity).then(|| {
+                self.next_index += 1;
+
+                self.next_index - 1
+            })
+        })
+    }
+
+    #[inline(always)]
+    fn free(&mut self, index: u32) {
+        self.free_indices.push(index);
+    }
+}
+
+/// #### Description
+/// One global descriptor set with arrays of textures, storage images, storage buffers and samplers.
+/// Resources get stable `u32` indices which shaders use to index the arrays directly.
+///
+/// #### NOTE
+/// Samplers are at `binding = 3`. Indices are recycled once resources are destroyed,
+/// which is safe because resources may be destroyed only when the GPU doesn't use them anymore.
+pub struct BindlessManager {
+    pub layout: vk::DescriptorSetLayout,
+    pool: vk::DescriptorPool,
+    pub set: vk::DescriptorSet,
+    index_allocators: [IndexAllocator; 3],
+    sampler_index_allocator: IndexAllocator,
+    indices: AHashMap<(BindlessKind, Id), u32>,
+    sampler_indices: AHashMap<vk::Sampler, u32>,
+}
+
+impl BindlessManager {
+    const SAMPLER_BINDING: u32 = 3;
+    const MAX_TEXTURES: u32 = 16384;
+    const MAX_STORAGE_IMAGES: u32 = 4096;
+    const MAX_STORAGE_BUFFERS: u32 = 4096;
+    const MAX_SAMPLERS: u32 = 256;
+    /// Update-after-bind resources per stage left for other sets and color attachments.
+    const RESERVED_RESOURCES: u32 = 64;
+
+    pub fn new(device: &super::DeviceManager) -> EngineResult<Self> {
+        debug!("Creating bindless descriptor set.");
+
+        let capacities = Self::capacities(&device.descriptor_indexing_properties);
+
+        let layout_bindings = capacities
+            .iter()
+            .enumerate()
+            .map(|(binding, &(descriptor_type, capacity))| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding as u32)
+                    .descriptor_type(descriptor_type)
+                    .descriptor_count(capacity)
+                    .stage_flags(vk::ShaderStageFlags::ALL)
+            })
+            .collect::<Vec<_>>();
+        let binding_flags = [vk::DescriptorBindingFlags::PARTIALLY_BOUND
+            | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING;
+            4];
+        let mut binding_flags_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::default().binding_flags(&binding_flags);
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::default()
+            .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+            .bindings(&layout_bindings)
+            .push_next(&mut binding_flags_info);
+        let layout = device.create_descriptor_set_layout(&layout_info)?;
+
+        let pool_sizes = capacities
+            .iter()
+            .map(|&(descriptor_type, capacity)| {
+                vk::DescriptorPoolSize::default()
+                    .ty(descriptor_type)
+                    .descriptor_count(capacity)
+            })
+            .collect::<Vec<_>>();
+        let pool_info = vk::DescriptorPoolCreateInfo::default()
+            .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+            .max_sets(1)
+            .pool_sizes(&pool_sizes);
+        let pool = match device.create_descriptor_pool(&pool_info) {
+            Ok(pool) => pool,
+            Err(e) => {
+                device.destroy_descriptor_set_layout(layout);
+
+                return Err(e);
+            }
+        };
+
+        let layouts = [layout];
+        let set_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+        let set = match device.allocate_descriptor_sets(&set_info) {
+            Ok(sets) => sets[0],
+            Err(e) => {
+                device.destroy_descriptor_pool(pool);
+                device.destroy_descriptor_set_layout(layout);
+
+                return Err(e);
+            }
+        };
+
+        device.set_object_name(layout, "Bindless Descriptor Set Layout");
+        device.set_object_name(pool, "Bindless Descriptor Pool");
+        device.set_object_name(set, "Bindless Descriptor Set");
+
+        Ok(Self {
+            layout,
+            pool,
+            set,
+            index_allocators: [
+                IndexAllocator::new(capacities[0].1),
+                IndexAllocator::new(capacities[1].1),
+                IndexAllocator::new(capacities[2].1),
+            ],
+            sampler_index_allocator: IndexAllocator::new(capacities[3].1),
+            indices: Default::default(),
+            sampler_indices: Default::default(),
+        })
+    }
+
+    /// #### Description
+    /// Index of the resource in the array of its kind, the resource is written into the set on first use.
+    pub fn index(
+        &mut self,
+        device: &super::DeviceManager,
+        kind: BindlessKind,
+        id: Id,
+    ) -> EngineResult<u32> {
+        if let Some(&index) = self.indices.get(&(kind, id)) {
+            return Ok(index);
+        }
+
+        let mut buffer_info = vk::DescriptorBufferInfo::default();
+        let mut image_info = vk::DescriptorImageInfo::default();
+        match kind {
+            BindlessKind::StorageBuffer => {
+                let Some(buffer) = (unsafe { RESOURCE_MANAGER.buffer(id) }) else {
+                    return Err(EngineError::UnknownError(std::format!(
+                        "Unable to find Buffer {id:?}"
+                    )));
+                };
+
+                buffer_info = buffer_info.buffer(buffer.buffer).range(vk::WHOLE_SIZE);
+            }
+            BindlessKind::Texture | BindlessKind::StorageImage => {
+                let Some(image) = (unsafe { RESOURCE_MANAGER.image(id) }) else {
+                    return Err(EngineError::UnknownError(std::format!(
+                        "Unable to find Image {id:?}"
+                    )));
+                };
+                let image_layout = match kind {
+                    BindlessKind::StorageImage => vk::ImageLayout::GENERAL,
+                    _ => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                };
+
+                image_info = image_info.image_view(image.view).image_layout(image_layout);
+            }
+        }
+
+        let Some(index) = self.index_allocators[kind.binding() as usize].allocate() else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Bindless array of {kind:?} is full"
+            )));
+        };
+
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(self.set)
+            .dst_binding(kind.binding())
+            .dst_array_element(index)
+            .descriptor_type(kind.descriptor_type());
+        let write = match kind {
+            BindlessKind::StorageBuffer => write.buffer_info(std::slice::from_ref(&buffer_info)),
+            _ => write.image_info(std::slice::from_ref(&image_info)),
+        };
+        device.update_descriptor_sets(&[write]);
+
+        self.indices.insert((kind, id), index);
+
+        Ok(index)
+    }
+
+    /// #### Description
+    /// Samplers are owned by the sampler cache and never destroyed before the Context, so their indices aren't recycled.
+    pub fn sampler_index(
+        &mut self,
+        device: &super::DeviceManager,
+        sampler: vk::Sampler,
+    ) -> EngineResult<u32> {
+        if let Some(&index) = self.sampler_indices.get(&sampler) {
+            return Ok(index);
+        }
+
+        let Some(index) = self.sampler_index_allocator.allocate() else {
+            return Err(EngineError::UnknownError(
+                "Bindless array of samplers is full".to_owned(),
+            ));
+        };
+
+        let image_info = vk::DescriptorImageInfo::default().sampler(sampler);
+        let write = vk::WriteDescriptorSet::default()
+            .dst_set(self.set)
+            .dst_binding(Self::SAMPLER_BINDING)
+            .dst_array_element(index)
+            .descriptor_type(vk::DescriptorType::SAMPLER)
+            .image_info(std::slice::from_ref(&image_info));
+        device.update_descriptor_sets(&[write]);
+
+        self.sampler_indices.insert(sampler, index);
+
+        Ok(index)
+    }
+
+    /// #### Description
+    /// Frees every index of the resource, called when the resource is destroyed.
+    pub fn release(&mut self, id: Id) {
+        for kind in [
+            BindlessKind::Texture,
+            BindlessKind::StorageImage,
+            BindlessKind::StorageBuffer,
+        ] {
+            if let Some(index) = self.indices.remove(&(kind, id)) {
+                self.index_allocators[kind.binding() as usize].free(index);
+            }
+        }
+    }
+
+    pub fn destroy_resources(&self, device: &super::DeviceManager) {
+        device.destroy_descriptor_pool(self.pool);
+        device.destroy_descriptor_set_layout(self.layout);
+    }
+
+    /// #### Description
+    /// Array sizes clamped to the update-after-bind limits, per binding and per set. Images and buffers
+    /// are scaled down together when their sum exceeds the per stage resource limit.
+    fn capacities(
+        properties: &vk::PhysicalDeviceDescriptorIndexingProperties,
+    ) -> [(vk::DescriptorType, u32); 4] {
+        let mut resource_capacities = [
+            Self::MAX_TEXTURES
+                .min(properties.max_per_stage_descriptor_update_after_bind_sampled_images)
+                .min(properties.max_descriptor_set_update_after_bind_sampled_images),
+            Self::MAX_STORAGE_IMAGES
+                .min(properties.max_per_stage_descriptor_update_after_bind_storage_images)
+                .min(properties.max_descriptor_set_update_after_bind_storage_images),
+            Self::MAX_STORAGE_BUFFERS
+                .min(properties.max_per_stage_descriptor_update_after_bind_storage_buffers)
+                .min(properties.max_descriptor_set_update_after_bind_storage_buffers),
+        ];
+        let sampler_capacity = Self::MAX_SAMPLERS
+            .min(properties.max_per_stage_descriptor_update_after_bind_samplers)
+            .min(properties.max_descriptor_set_update_after_bind_samplers);
+
+        // Samplers don't count against the per stage resource limit.
+        let resource_budget = properties
+            .max_per_stage_update_after_bind_resources
+            .saturating_sub(Self::RESERVED_RESOURCES) as u64;
+        let resource_sum = resource_capacities
+            .iter()
+            .map(|&capacity| capacity as u64)
+            .sum::<u64>();
+        if resource_sum > resource_budget {
+            for capacity in &mut resource_capacities {
+                *capacity = (*capacity as u64 * resource_budget / resource_sum) as u32;
+            }
+        }
+
+        [
+            (vk::DescriptorType::SAMPLED_IMAGE, resource_capacities[0]),
+            (vk::DescriptorType::STORAGE_IMAGE, resource_capacities[1]),
+            (vk::DescriptorType::STORAGE_BUFFER, resource_capacities[2]),
+            (vk::DescriptorType::SAMPLER, sampler_capacity),
+        ]
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    common::Id,
+    error::{EngineError, EngineResult},
+};
+
+use super::allocator::{Allocation, AllocationKind, GpuAllocator, MemoryLocation};
+
+use ash::vk;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferDesc {
+    pub size: vk::DeviceSize,
+    pub usage: vk::BufferUsageFlags,
+    pub location: MemoryLocation,
+}
+
+impl BufferDesc {
+    #[inline(always)]
+    pub fn new(
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        location: MemoryLocation,
+    ) -> Self {
+        Self {
+            size,
+            usage,
+            location,
+        }
+    }
+
+    #[inline(always)]
+    pub fn vertex(size: vk::DeviceSize) -> Self {
+        Self::new(
+            size,
+            vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+            MemoryLocation::GpuOnly,
+        )
+    }
+
+    #[inline(always)]
+    pub fn index(size: vk::DeviceSize) -> Self {
+        Self::new(
+            size,
+            vk::BufferUsageFlags::INDEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+            MemoryLocation::GpuOnly,
+        )
+    }
+
+    /// #### Description
+    /// Uniform buffers are written from the CPU every frame, so they live in host visible memory.
+    #[inline(always)]
+    pub fn uniform(size: vk::DeviceSize) -> Self {
+        Self::new(
+            size,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            MemoryLocation::CpuToGpu,
+        )
+    }
+
+    #[inline(always)]
+    pub fn storage(size: vk::DeviceSize) -> Self {
+        Self::new(
+            size,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            MemoryLocation::GpuOnly,
+        )
+    }
+
+    #[inline(always)]
+    pub fn staging(size: vk::DeviceSize) -> Self {
+        Self::new(
+            size,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            MemoryLocation::CpuToGpu,
+        )
+    }
+
+    #[inline(always)]
+    pub fn readback(size: vk::DeviceSize) -> Self {
+        Self::new(
+            size,
+            vk::BufferUsageFlags::TRANSFER_DST,
+            MemoryLocation::GpuToCpu,
+        )
+    }
+}
+
+pub struct Buffer {
+    pub id: Id,
+    pub buffer: vk::Buffer,
+    pub desc: BufferDesc,
+    /// Shared between queue families without ownership transfers.
+    pub is_concurrent: bool,
+    allocation: Allocation,
+}
+
+impl Buffer {
+    pub fn new(
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+        id: Id,
+        desc: BufferDesc,
+    ) -> EngineResult<Self> {
+        // Buffers don't lose anything with concurrent sharing, so they are usable from every queue as is.
+        let family_indices = device.concurrent_family_indices();
+        let is_concurrent = family_indices.len() > 1;
+        let buffer_info = vk::BufferCreateInfo::default()
+            .size(desc.size)
+            .usage(desc.usage);
+        let buffer_info = if is_concurrent {
+            buffer_info
+                .sharing_mode(vk::SharingMode::CONCURRENT)
+                .queue_family_indices(&family_indices)
+        } else {
+            buffer_info.sharing_mode(vk::SharingMode::EXCLUSIVE)
+        };
+        let buffer = device.create_buffer(&buffer_info)?;
+
+        let memory_requirements = device.get_buffer_memory_requirements(buffer);
+        let allocation = match allocator.allocate(
+            device,
+            memory_requirements,
+            desc.location,
+            AllocationKind::Linear,
+        ) {
+            Ok(allocation) => allocation,
+            Err(e) => {
+                device.destroy_buffer(buffer);
+
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = device.bind_buffer_memory(buffer, allocation.memory, allocation.offset) {
+            allocator.free(device, allocation);
+            device.destroy_buffer(buffer);
+
+            return Err(e);
+        }
+
+        Ok(Self {
+            id,
+            buffer,
+            desc,
+            is_concurrent,
+            allocation,
+        })
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> vk::DeviceSize {
+        self.desc.size
+    }
+
+    #[inline(always)]
+    pub fn is_mapped(&self) -> bool {
+        self.allocation.mapped_ptr().is_some()
+    }
+
+    /// #### Description
+    /// Copies `data` into the mapped memory of the buffer starting at `offset` bytes.
+    ///
+    /// #### NOTE
+    /// The range must not be in use by the GPU, e.g. the buffer has to be owned by a finished frame.
+    pub fn write<T: bytemuck::Pod>(
+        &mut self,
+        offset: vk::DeviceSize,
+        data: &[T],
+    ) -> EngineResult<()> {
+        let bytes = bytemuck::cast_slice::<T, u8>(data);
+        let size = self.desc.size;
+
+        let Some(mapped_memory) = self.allocation.mapped_slice_mut() else {
+            return Err(EngineError::UnknownError(
+                "Unable to write into the Buffer which isn't host visible".to_owned(),
+            ));
+        };
+
+        let Some(end) = offset
+            .checked_add(bytes.len() as vk::DeviceSize)
+            .filter(|&end| end <= size)
+        else {
+            return Err(EngineError::UnknownError(std::format!(
+                "Write of {} bytes at offset {offset} is out of the Buffer of size {size}",
+                bytes.len()
+            )));
+        };
+
+        mapped_memory[offset as usize..end as usize].copy_from_slice(bytes);
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Mapped memory of the buffer, `None` if the buffer isn't host visible.
+    #[inline(always)]
+    pub fn mapped_bytes(&self) -> Option<&[u8]> {
+        self.allocation.mapped_ptr().map(|mapped_ptr| unsafe {
+            std::slice::from_raw_parts(mapped_ptr.as_ptr(), self.desc.size as usize)
+        })
+    }
+
+    #[inline(always)]
+    pub fn destroy(self, device: &super::DeviceManager, allocator: &mut GpuAllocator) {
+        device.destroy_buffer(self.buffer);
+        allocator.free(device, self.allocation);
+    }
+}
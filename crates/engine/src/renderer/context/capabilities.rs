@@ -0,0 +1,325 @@
+use crate::{error::EngineResult, renderer::utils::to_cstr};
+
+use super::features::{DeviceFeature, FeatureSet};
+
+use ash::vk;
+use serde::Serialize;
+
+/// #### Description
+/// Capabilities of every enumerated GPU, meant to be attached to bug reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityReport {
+    pub devices: Vec<DeviceReport>,
+}
+
+impl CapabilityReport {
+    /// #### Description
+    /// Queries every physical device, surface formats, present modes and present support
+    /// are reported only when there is a surface.
+    pub fn new(
+        instance: &ash::Instance,
+        surface_handle: Option<&super::SurfaceManager>,
+        selected_device: vk::PhysicalDevice,
+    ) -> EngineResult<Self> {
+        let devices = unsafe { instance.enumerate_physical_devices()? }
+            .into_iter()
+            .enumerate()
+            .map(|(index, physical_device)| {
+                DeviceReport::new(
+                    instance,
+                    surface_handle,
+                    index,
+                    physical_device,
+                    physical_device == selected_device,
+                )
+            })
+            .collect::<EngineResult<Vec<_>>>()?;
+
+        Ok(Self { devices })
+    }
+
+    #[inline(always)]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceReport {
+    pub index: usize,
+    pub name: String,
+    /// The device the engine renders with.
+    pub is_selected: bool,
+    pub device_type: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub api_version: String,
+    pub driver_version: u32,
+    pub limits: DeviceLimits,
+    pub memory_heaps: Vec<MemoryHeapReport>,
+    pub memory_types: Vec<MemoryTypeReport>,
+    pub queue_families: Vec<QueueFamilyReport>,
+    pub surface_formats: Vec<SurfaceFormatReport>,
+    pub present_modes: Vec<String>,
+    /// Surface queries which failed for this device, the data they would report is left out.
+    pub surface_errors: Vec<String>,
+    pub extensions: Vec<ExtensionReport>,
+    pub features: Vec<DeviceFeature>,
+}
+
+impl DeviceReport {
+    fn new(
+        instance: &ash::Instance,
+        surface_handle: Option<&super::SurfaceManager>,
+        index: usize,
+        physical_device: vk::PhysicalDevice,
+        is_selected: bool,
+    ) -> EngineResult<Self> {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let queue_family_properties =
+            unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+        let available_extensions =
+            unsafe { instance.enumerate_device_extension_properties(physical_device)? };
+        let supported_features =
+            FeatureSet::supported(instance, physical_device, properties.api_version);
+
+        let memory_heaps = memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .iter()
+            .map(|heap| MemoryHeapReport {
+                size: heap.size,
+                flags: std::format!("{:?}", heap.flags),
+            })
+            .collect();
+        let memory_types = memory_properties.memory_types
+            [..memory_properties.memory_type_count as usize]
+            .iter()
+            .map(|memory_type| MemoryTypeReport {
+                heap_index: memory_type.heap_index,
+                property_flags: std::format!("{:?}", memory_type.property_flags),
+            })
+            .collect();
+
+        let mut surface_errors = Vec::new();
+
+        let queue_families = queue_family_properties
+            .iter()
+            .enumerate()
+            .map(|(family_index, family)| {
+                let supports_present = surface_handle.and_then(|surface_handle| {
+                    let result = unsafe {
+                        surface_handle
+                            .surface_loader
+                            .get_physical_device_surface_support(
+                                physical_device,
+                                family_index as u32,
+                                surface_handle.surface,
+                            )
+                    };
+
+                    result
+                        .inspect_err(|e| {
+                            surface_errors.push(std::format!(
+                                "Present support of queue family {family_index}: {e}"
+                            ))
+                        })
+                        .ok()
+                });
+                let granularity = family.min_image_transfer_granularity;
+
+                QueueFamilyReport {
+                    index: family_index as u32,
+                    flags: std::format!("{:?}", family.queue_flags),
+                    queue_count: family.queue_count,
+                    timestamp_valid_bits: family.timestamp_valid_bits,
+                    min_image_transfer_granularity: [
+                        granularity.width,
+                        granularity.height,
+                        granularity.depth,
+                    ],
+                    supports_present,
+                }
+            })
+            .collect();
+
+        let (surface_formats, present_modes) = match surface_handle {
+            Some(surface_handle) => {
+                let surface = surface_handle.surface;
+                let surface_loader = &surface_handle.surface_loader;
+
+                let surface_formats = unsafe {
+                    surface_loader.get_physical_device_surface_formats(physical_device, surface)
+                }
+                .unwrap_or_else(|e| {
+                    surface_errors.push(std::format!("Surface formats: {e}"));
+
+                    Default::default()
+                });
+                let present_modes = unsafe {
+                    surface_loader
+                        .get_physical_device_surface_present_modes(physical_device, surface)
+                }
+                .unwrap_or_else(|e| {
+                    surface_errors.push(std::format!("Present modes: {e}"));
+
+                    Default::default()
+                });
+
+                (
+                    surface_formats
+                        .into_iter()
+                        .map(|surface_format| SurfaceFormatReport {
+                            format: std::format!("{:?}", surface_format.format),
+                            color_space: std::format!("{:?}", surface_format.color_space),
+                        })
+                        .collect(),
+                    present_modes
+                        .into_iter()
+                        .map(|present_mode| std::format!("{present_mode:?}"))
+                        .collect(),
+                )
+            }
+            None => Default::default(),
+        };
+
+        let extensions = available_extensions
+            .iter()
+            .map(|extension| ExtensionReport {
+                name: to_cstr(extension.extension_name.as_ptr())
+                    .to_string_lossy()
+                    .into_owned(),
+                spec_version: extension.spec_version,
+            })
+            .collect();
+        let features = DeviceFeature::ALL
+            .iter()
+            .copied()
+            .filter(|&feature| supported_features.contains(feature))
+            .collect();
+
+        Ok(Self {
+            index,
+            name: to_cstr(properties.device_name.as_ptr())
+                .to_string_lossy()
+                .into_owned(),
+            is_selected,
+            device_type: std::format!("{:?}", properties.device_type),
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            api_version: std::format!(
+                "{}.{}.{}",
+                vk::api_version_major(properties.api_version),
+                vk::api_version_minor(properties.api_version),
+                vk::api_version_patch(properties.api_version)
+            ),
+            driver_version: properties.driver_version,
+            limits: DeviceLimits::from(&properties.limits),
+            memory_heaps,
+            memory_types,
+            queue_families,
+            surface_formats,
+            present_modes,
+            surface_errors,
+            extensions,
+            features,
+        })
+    }
+}
+
+/// Declares `DeviceLimits` with the listed fields copied from `vk::PhysicalDeviceLimits`.
+macro_rules! device_limits {
+    ($($field:ident: $ty:ty,)*) => {
+        #[derive(Debug, Clone, Serialize)]
+        pub struct DeviceLimits {
+            $(pub $field: $ty,)*
+        }
+
+        impl From<&vk::PhysicalDeviceLimits> for DeviceLimits {
+            fn from(limits: &vk::PhysicalDeviceLimits) -> Self {
+                Self {
+                    $($field: limits.$field,)*
+                }
+            }
+        }
+    };
+}
+
+device_limits! {
+    max_image_dimension2_d: u32,
+    max_image_dimension3_d: u32,
+    max_image_dimension_cube: u32,
+    max_image_array_layers: u32,
+    max_uniform_buffer_range: u32,
+    max_storage_buffer_range: u32,
+    max_push_constants_size: u32,
+    max_memory_allocation_count: u32,
+    max_sampler_allocation_count: u32,
+    buffer_image_granularity: u64,
+    max_bound_descriptor_sets: u32,
+    max_per_stage_descriptor_samplers: u32,
+    max_per_stage_descriptor_uniform_buffers: u32,
+    max_per_stage_descriptor_storage_buffers: u32,
+    max_per_stage_descriptor_sampled_images: u32,
+    max_per_stage_descriptor_storage_images: u32,
+    max_per_stage_resources: u32,
+    max_descriptor_set_samplers: u32,
+    max_descriptor_set_uniform_buffers: u32,
+    max_descriptor_set_storage_buffers: u32,
+    max_descriptor_set_sampled_images: u32,
+    max_descriptor_set_storage_images: u32,
+    max_vertex_input_attributes: u32,
+    max_vertex_input_bindings: u32,
+    max_color_attachments: u32,
+    max_compute_shared_memory_size: u32,
+    max_compute_work_group_count: [u32; 3],
+    max_compute_work_group_invocations: u32,
+    max_compute_work_group_size: [u32; 3],
+    max_draw_indirect_count: u32,
+    max_sampler_anisotropy: f32,
+    max_viewports: u32,
+    max_viewport_dimensions: [u32; 2],
+    max_framebuffer_width: u32,
+    max_framebuffer_height: u32,
+    min_uniform_buffer_offset_alignment: u64,
+    min_storage_buffer_offset_alignment: u64,
+    optimal_buffer_copy_offset_alignment: u64,
+    non_coherent_atom_size: u64,
+    timestamp_period: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryHeapReport {
+    pub size: u64,
+    pub flags: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryTypeReport {
+    pub heap_index: u32,
+    pub property_flags: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueFamilyReport {
+    pub index: u32,
+    pub flags: String,
+    pub queue_count: u32,
+    pub timestamp_valid_bits: u32,
+    pub min_image_transfer_granularity: [u32; 3],
+    /// `None` without a surface (headless) or when the query failed, see `DeviceReport::surface_errors`.
+    pub supports_present: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SurfaceFormatReport {
+    pub format: String,
+    pub color_space: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionReport {
+    pub name: String,
+    pub spec_version: u32,
+}
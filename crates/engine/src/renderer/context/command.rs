@@ -0,0 +1,650 @@
+use logging::*;
+
+use crate::error::{EngineError, EngineResult};
+
+use super::{device::QueueKind, profiler::GpuZone};
+
+use std::cell::Cell;
+
+use ahash::AHashMap;
+use ash::vk;
+
+struct CommandPool {
+    pool: vk::CommandPool,
+    primary_command_buffers: Vec<vk::CommandBuffer>,
+    secondary_command_buffers: Vec<vk::CommandBuffer>,
+    used_primary_count: usize,
+    used_secondary_count: usize,
+}
+
+impl CommandPool {
+    #[inline(always)]
+    fn new(device: &super::DeviceManager, queue_family_index: u32) -> EngineResult<Self> {
+        let command_pool_info = vk::CommandPoolCreateInfo::default()
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+            .queue_family_index(queue_family_index);
+        let pool = device.create_command_pool(&command_pool_info)?;
+
+        Ok(Self {
+            pool,
+            primary_command_buffers: Default::default(),
+            secondary_command_buffers: Default::default(),
+            used_primary_count: Default::default(),
+            used_secondary_count: Default::default(),
+        })
+    }
+
+    /// #### Description
+    /// Hands out a command buffer which wasn't used since the last reset, allocates a new one if there is none.
+    fn acquire(
+        &mut self,
+        device: &super::DeviceManager,
+        level: vk::CommandBufferLevel,
+    ) -> EngineResult<vk::CommandBuffer> {
+        let (command_buffers, used_count) = match level {
+            vk::CommandBufferLevel::SECONDARY => (
+                &mut self.secondary_command_buffers,
+                &mut self.used_secondary_count,
+            ),
+            _ => (
+                &mut self.primary_command_buffers,
+                &mut self.used_primary_count,
+            ),
+        };
+
+        if *used_count == command_buffers.len() {
+            let command_buffer_info = vk::CommandBufferAllocateInfo::default()
+                .command_pool(self.pool)
+                .level(level)
+                .command_buffer_count(1);
+            command_buffers.extend(device.allocate_command_buffers(&command_buffer_info)?);
+        }
+
+        let command_buffer = command_buffers[*used_count];
+        *used_count += 1;
+
+        Ok(command_buffer)
+    }
+
+    #[inline(always)]
+    fn reset(&mut self, device: &super::DeviceManager) -> EngineResult<()> {
+        device.reset_command_pool(self.pool)?;
+
+        self.used_primary_count = Default::default();
+        self.used_secondary_count = Default::default();
+
+        Ok(())
+    }
+}
+
+/// #### Description
+/// Command buffer in the recording state, commands are recorded through its methods.
+pub struct CommandRecorder<'a> {
+    device: &'a super::DeviceManager,
+    command_buffer: vk::CommandBuffer,
+    queue_kind: QueueKind,
+    /// Profiler of the frame, `None` for one-shot command buffers.
+    profiler: Option<&'a super::ProfilerManager>,
+    zone_depth: Cell<u32>,
+}
+
+impl<'a> CommandRecorder<'a> {
+    fn begin(
+        device: &'a super::DeviceManager,
+        command_buffer: vk::CommandBuffer,
+        queue_kind: QueueKind,
+        inheritance_info: Option<&vk::CommandBufferInheritanceInfo>,
+        profiler: Option<&'a super::ProfilerManager>,
+    ) -> EngineResult<Self> {
+        let mut begin_info = vk::CommandBufferBeginInfo::default()
+            .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        if let Some(inheritance_info) = inheritance_info {
+            // Secondaries executed inside dynamic rendering must continue it, otherwise
+            // the inherited attachments are ignored.
+            if Self::is_inheriting_rendering(inheritance_info) {
+                begin_info.flags |= vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE;
+            }
+            begin_info = begin_info.inheritance_info(inheritance_info);
+        }
+
+        unsafe {
+            device
+                .raw()
+                .begin_command_buffer(command_buffer, &begin_info)?
+        };
+
+        Ok(Self {
+            device,
+            command_buffer,
+            queue_kind,
+            profiler,
+            zone_depth: Default::default(),
+        })
+    }
+
+    #[inline(always)]
+    pub fn handle(&self) -> vk::CommandBuffer {
+        self.command_buffer
+    }
+
+    #[inline(always)]
+    pub fn pipeline_barrier(
+        &self,
+        buffer_barriers: &[vk::BufferMemoryBarrier2],
+        image_barriers: &[vk::ImageMemoryBarrier2],
+    ) {
+        let dependency_info = vk::DependencyInfo::default()
+            .buffer_memory_barriers(buffer_barriers)
+            .image_memory_barriers(image_barriers);
+
+        unsafe {
+            self.device
+                .raw()
+                .cmd_pipeline_barrier2(self.command_buffer, &dependency_info)
+        }
+    }
+
+    /// #### Description
+    /// Global barrier, e.g. to make results of a compute dispatch visible to the host.
+    #[inline(always)]
+    pub fn memory_barrier(&self, memory_barriers: &[vk::MemoryBarrier2]) {
+        let dependency_info = vk::DependencyInfo::default().memory_barriers(memory_barriers);
+
+        unsafe {
+            self.device
+                .raw()
+                .cmd_pipeline_barrier2(self.command_buffer, &dependency_info)
+        }
+    }
+
+    #[inline(always)]
+    pub fn clear_color_image(
+        &self,
+        image: vk::Image,
+        layout: vk::ImageLayout,
+        color: vk::ClearColorValue,
+        subresource_range: vk::ImageSubresourceRange,
+    ) {
+        unsafe {
+            self.device.raw().cmd_clear_color_image(
+                self.command_buffer,
+                image,
+                layout,
+                &color,
+                &[subresource_range],
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn copy_buffer(&self, src: vk::Buffer, dst: vk::Buffer, regions: &[vk::BufferCopy]) {
+        unsafe {
+            self.device
+                .raw()
+                .cmd_copy_buffer(self.command_buffer, src, dst, regions)
+        }
+    }
+
+    #[inline(always)]
+    pub fn copy_buffer_to_image(
+        &self,
+        src: vk::Buffer,
+        dst: vk::Image,
+        dst_layout: vk::ImageLayout,
+        regions: &[vk::BufferImageCopy],
+    ) {
+        unsafe {
+            self.device.raw().cmd_copy_buffer_to_image(
+                self.command_buffer,
+                src,
+                dst,
+                dst_layout,
+                regions,
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn bind_pipeline(&self, pipeline: &super::Pipeline) {
+        unsafe {
+            self.device.raw().cmd_bind_pipeline(
+                self.command_buffer,
+                pipeline.bind_point,
+                pipeline.pipeline,
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn bind_descriptor_sets(
+        &self,
+        pipeline: &super::Pipeline,
+        first_set: u32,
+        descriptor_sets: &[vk::DescriptorSet],
+    ) {
+        unsafe {
+            self.device.raw().cmd_bind_descriptor_sets(
+                self.command_buffer,
+                pipeline.bind_point,
+                pipeline.layout,
+                first_set,
+                descriptor_sets,
+                &[],
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn push_constants<T: bytemuck::Pod>(
+        &self,
+        pipeline: &super::Pipeline,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        data: &T,
+    ) {
+        unsafe {
+            self.device.raw().cmd_push_constants(
+                self.command_buffer,
+                pipeline.layout,
+                stages,
+                offset,
+                bytemuck::bytes_of(data),
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn dispatch(&self, group_count_x: u32, group_count_y: u32, group_count_z: u32) {
+        unsafe {
+            self.device.raw().cmd_dispatch(
+                self.command_buffer,
+                group_count_x,
+                group_count_y,
+                group_count_z,
+            )
+        }
+    }
+
+    /// #### Description
+    /// Group counts are read from `vk::DispatchIndirectCommand` in `buffer` at `offset`.
+    #[inline(always)]
+    pub fn dispatch_indirect(&self, buffer: vk::Buffer, offset: vk::DeviceSize) {
+        unsafe {
+            self.device
+                .raw()
+                .cmd_dispatch_indirect(self.command_buffer, buffer, offset)
+        }
+    }
+
+    /// #### Description
+    /// Begins dynamic rendering into the given attachments.
+    #[inline(always)]
+    pub fn begin_rendering(&self, rendering_info: &vk::RenderingInfo) {
+        unsafe {
+            self.device
+                .raw()
+                .cmd_begin_rendering(self.command_buffer, rendering_info)
+        }
+    }
+
+    #[inline(always)]
+    pub fn end_rendering(&self) {
+        unsafe { self.device.raw().cmd_end_rendering(self.command_buffer) }
+    }
+
+    /// #### Description
+    /// Sets viewport and scissor covering the whole extent.
+    #[inline(always)]
+    pub fn set_viewport_and_scissor(&self, extent: vk::Extent2D) {
+        let viewport = vk::Viewport::default()
+            .width(extent.width as f32)
+            .height(extent.height as f32)
+            .max_depth(1.0);
+        let scissor = vk::Rect2D::default().extent(extent);
+
+        unsafe {
+            self.device
+                .raw()
+                .cmd_set_viewport(self.command_buffer, 0, &[viewport]);
+            self.device
+                .raw()
+                .cmd_set_scissor(self.command_buffer, 0, &[scissor]);
+        }
+    }
+
+    #[inline(always)]
+    pub fn bind_vertex_buffers(
+        &self,
+        first_binding: u32,
+        buffers: &[vk::Buffer],
+        offsets: &[vk::DeviceSize],
+    ) {
+        unsafe {
+            self.device.raw().cmd_bind_vertex_buffers(
+                self.command_buffer,
+                first_binding,
+                buffers,
+                offsets,
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn bind_index_buffer(
+        &self,
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        index_type: vk::IndexType,
+    ) {
+        unsafe {
+            self.device
+                .raw()
+                .cmd_bind_index_buffer(self.command_buffer, buffer, offset, index_type)
+        }
+    }
+
+    #[inline(always)]
+    pub fn draw(
+        &self,
+        vertex_count: u32,
+        instance_count: u32,
+        first_vertex: u32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.device.raw().cmd_draw(
+                self.command_buffer,
+                vertex_count,
+                instance_count,
+                first_vertex,
+                first_instance,
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn draw_indexed(
+        &self,
+        index_count: u32,
+        instance_count: u32,
+        first_index: u32,
+        vertex_offset: i32,
+        first_instance: u32,
+    ) {
+        unsafe {
+            self.device.raw().cmd_draw_indexed(
+                self.command_buffer,
+                index_count,
+                instance_count,
+                first_index,
+                vertex_offset,
+                first_instance,
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn execute_commands(&self, secondary_command_buffers: &[vk::CommandBuffer]) {
+        unsafe {
+            self.device
+                .raw()
+                .cmd_execute_commands(self.command_buffer, secondary_command_buffers)
+        }
+    }
+
+    #[inline(always)]
+    pub fn write_timestamp(
+        &self,
+        stage: vk::PipelineStageFlags2,
+        query_pool: vk::QueryPool,
+        query: u32,
+    ) {
+        unsafe {
+            self.device
+                .raw()
+                .cmd_write_timestamp2(self.command_buffer, stage, query_pool, query)
+        }
+    }
+
+    /// #### Description
+    /// Labels the commands recorded until the returned scope is dropped, labels can be nested.
+    /// Shown in captures and validation messages, compiled out without the `dev` feature.
+    #[inline(always)]
+    pub fn debug_label<'r>(&'r self, name: &str) -> DebugLabel<'r, 'a> {
+        self.device.cmd_begin_label(self.command_buffer, name);
+
+        DebugLabel { recorder: self }
+    }
+
+    /// #### Description
+    /// Measures GPU time of the commands recorded until the returned zone is dropped.
+    /// Zones can be nested, timings are available through `Context::gpu_timings` a few frames later.
+    #[track_caller]
+    pub fn gpu_zone<'r>(&'r self, name: &str) -> GpuZone<'r, 'a> {
+        let zone_index = self.profiler.and_then(|profiler| {
+            profiler.begin_zone(
+                self,
+                name,
+                self.zone_depth.get(),
+                std::panic::Location::caller(),
+            )
+        });
+        if zone_index.is_some() {
+            self.zone_depth.set(self.zone_depth.get() + 1);
+        }
+
+        GpuZone::new(self, zone_index)
+    }
+
+    #[inline(always)]
+    pub(super) fn end_gpu_zone(&self, zone_index: usize) {
+        if let Some(profiler) = self.profiler {
+            self.zone_depth.set(self.zone_depth.get() - 1);
+            profiler.end_zone(self, zone_index);
+        }
+    }
+
+    #[inline(always)]
+    pub fn finish(self) -> EngineResult<RecordedCommandBuffer> {
+        unsafe { self.device.raw().end_command_buffer(self.command_buffer)? };
+
+        Ok(RecordedCommandBuffer {
+            command_buffer: self.command_buffer,
+            queue_kind: self.queue_kind,
+        })
+    }
+
+    fn is_inheriting_rendering(inheritance_info: &vk::CommandBufferInheritanceInfo) -> bool {
+        let mut next = inheritance_info.p_next.cast::<vk::BaseInStructure>();
+        while !next.is_null() {
+            let structure = unsafe { &*next };
+            if structure.s_type == vk::StructureType::COMMAND_BUFFER_INHERITANCE_RENDERING_INFO {
+                return true;
+            }
+
+            next = structure.p_next;
+        }
+
+        false
+    }
+}
+
+/// #### Description
+/// Scope of a command buffer label, created by `CommandRecorder::debug_label`.
+pub struct DebugLabel<'r, 'a> {
+    recorder: &'r CommandRecorder<'a>,
+}
+
+impl Drop for DebugLabel<'_, '_> {
+    fn drop(&mut self) {
+        self.recorder
+            .device
+            .cmd_end_label(self.recorder.command_buffer);
+    }
+}
+
+/// #### Description
+/// Command buffer in the executable state, ready to be submitted to the queue it was recorded for.
+#[derive(Debug, Clone, Copy)]
+pub struct RecordedCommandBuffer {
+    pub command_buffer: vk::CommandBuffer,
+    pub queue_kind: QueueKind,
+}
+
+#[derive(Default)]
+pub struct SubmitDesc<'s> {
+    pub wait_semaphores: &'s [(vk::Semaphore, vk::PipelineStageFlags)],
+    pub signal_semaphores: &'s [vk::Semaphore],
+    pub fence: vk::Fence,
+}
+
+/// #### Description
+/// Owns command pools per queue family and per frame in flight.
+/// Pools of a frame are reset as a whole once the GPU finished the work of that frame.
+///
+/// #### NOTE
+/// Recording borrows the manager mutably, so command buffers are recorded from one thread at a time.
+pub struct CommandManager {
+    frame_pools: Vec<AHashMap<QueueKind, CommandPool>>,
+    immediate_pools: AHashMap<QueueKind, CommandPool>,
+}
+
+impl CommandManager {
+    pub fn new(frames_in_flight: usize) -> Self {
+        debug!("Initializing command pools");
+
+        Self {
+            frame_pools: (0..frames_in_flight).map(|_| Default::default()).collect(),
+            immediate_pools: Default::default(),
+        }
+    }
+
+    /// #### Description
+    /// Begins recording of a command buffer taken from the pool of the queue family.
+    pub fn record<'a>(
+        &mut self,
+        device: &'a super::DeviceManager,
+        frame_index: usize,
+        queue_kind: QueueKind,
+        level: vk::CommandBufferLevel,
+        inheritance_info: Option<&vk::CommandBufferInheritanceInfo>,
+        profiler: &'a super::ProfilerManager,
+    ) -> EngineResult<CommandRecorder<'a>> {
+        let pool = match self.frame_pools[frame_index].entry(queue_kind) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(CommandPool::new(
+                device,
+                device.queue_family_index(queue_kind),
+            )?),
+        };
+
+        let command_buffer = pool.acquire(device, level)?;
+
+        // Timestamp support is checked only for the graphics family, zones on other families are ignored.
+        let is_profiled = !device.queue_topology.is_dedicated(queue_kind);
+
+        CommandRecorder::begin(
+            device,
+            command_buffer,
+            queue_kind,
+            inheritance_info,
+            is_profiled.then_some(profiler),
+        )
+    }
+
+    /// #### Description
+    /// Begins recording of a command buffer for one-shot work outside of frames (e.g. uploads).
+    /// The caller waits for its completion and then calls `reset_immediate`.
+    pub fn record_immediate<'a>(
+        &mut self,
+        device: &'a super::DeviceManager,
+        queue_kind: QueueKind,
+    ) -> EngineResult<CommandRecorder<'a>> {
+        let pool = match self.immediate_pools.entry(queue_kind) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+            std::collections::hash_map::Entry::Vacant(entry) => entry.insert(CommandPool::new(
+                device,
+                device.queue_family_index(queue_kind),
+            )?),
+        };
+
+        let command_buffer = pool.acquire(device, vk::CommandBufferLevel::PRIMARY)?;
+
+        CommandRecorder::begin(device, command_buffer, queue_kind, None, None)
+    }
+
+    pub fn reset_immediate(&mut self, device: &super::DeviceManager) -> EngineResult<()> {
+        for pool in self.immediate_pools.values_mut() {
+            pool.reset(device)?;
+        }
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Must be called only after the GPU finished the work of the frame.
+    pub fn reset_frame(
+        &mut self,
+        device: &super::DeviceManager,
+        frame_index: usize,
+    ) -> EngineResult<()> {
+        for pool in self.frame_pools[frame_index].values_mut() {
+            pool.reset(device)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn submit(
+        &self,
+        device: &super::DeviceManager,
+        command_buffers: &[RecordedCommandBuffer],
+        submit_desc: &SubmitDesc,
+    ) -> EngineResult<()> {
+        // Empty submission still has to signal semaphores and the fence, e.g. for a skipped frame.
+        let queue_kind = command_buffers
+            .first()
+            .map_or(QueueKind::Graphics, |command_buffer| {
+                command_buffer.queue_kind
+            });
+
+        if command_buffers
+            .iter()
+            .any(|command_buffer| command_buffer.queue_kind != queue_kind)
+        {
+            return Err(EngineError::UnknownError(
+                "Command buffers recorded for different queues can't be submitted together"
+                    .to_owned(),
+            ));
+        }
+
+        let command_buffers = command_buffers
+            .iter()
+            .map(|command_buffer| command_buffer.command_buffer)
+            .collect::<smallvec::SmallVec<[_; 4]>>();
+        let (wait_semaphores, wait_stages): (
+            smallvec::SmallVec<[_; 4]>,
+            smallvec::SmallVec<[_; 4]>,
+        ) = submit_desc.wait_semaphores.iter().copied().unzip();
+
+        let submit_info = vk::SubmitInfo::default()
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stages)
+            .command_buffers(&command_buffers)
+            .signal_semaphores(submit_desc.signal_semaphores);
+
+        device.queue_submit(device.queue(queue_kind), &[submit_info], submit_desc.fence)
+    }
+
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        for frame_pools in &mut self.frame_pools {
+            for (_, pool) in frame_pools.drain() {
+                device.destroy_command_pool(pool.pool);
+            }
+        }
+
+        for (_, pool) in self.immediate_pools.drain() {
+            device.destroy_command_pool(pool.pool);
+        }
+    }
+}
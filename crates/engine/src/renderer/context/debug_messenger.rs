@@ -1,57 +1,130 @@
+use ahash::AHashMap;
 use ash::extensions::ext::DebugUtils;
 use ash::vk;
 
 use crate::error::{EngineError, EngineResult};
 use logging::*;
 
+use super::{StrictMode, ValidationPolicy, ValidationSeverity, ValidationStats};
+
+use std::sync::Mutex;
+
+/// #### Description
+/// Applies the `ValidationPolicy` of the state behind `p_user_data` and logs what passes it.
 unsafe extern "system" fn debug_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
     message_type: vk::DebugUtilsMessageTypeFlagsEXT,
     p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _p_user_data: *mut std::ffi::c_void,
+    p_user_data: *mut std::ffi::c_void,
 ) -> vk::Bool32 {
-    let message_type = std::format!("{message_type:?}");
-    let message = std::format!("\n{TAB_IN_SPACES}[{message_type}] {}", unsafe {
-        std::ffi::CStr::from_ptr((*p_callback_data).p_message).to_string_lossy()
+    let validation_state = unsafe { &*(p_user_data as *const ValidationState) };
+    let callback_data = unsafe { &*p_callback_data };
+    let message_name = (!callback_data.p_message_id_name.is_null()).then(|| unsafe {
+        std::ffi::CStr::from_ptr(callback_data.p_message_id_name).to_string_lossy()
     });
+    let message_text =
+        unsafe { std::ffi::CStr::from_ptr(callback_data.p_message).to_string_lossy() };
 
-    match message_severity {
-        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => {
-            info!(message);
-        }
-        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => {
-            warning!(message);
+    let (severity, action) = validation_state.process(
+        message_severity,
+        message_type,
+        callback_data.message_id_number,
+        message_name.as_deref(),
+        &message_text,
+    );
+
+    if let MessageAction::DebugPrintf = action {
+        // Shader modules and pipelines are named by the engine, so their names locate the printing shader.
+        let objects = unsafe { object_names(callback_data) };
+        info!(std::format!(
+            "\n{TAB_IN_SPACES}[Shader Printf] {objects}: {}",
+            debug_printf_output(&message_text)
+        ));
+    }
+
+    if let MessageAction::Log { is_last_repeat } = action {
+        let message_type = std::format!("{message_type:?}");
+        let mut message = std::format!("\n{TAB_IN_SPACES}[{message_type}] {message_text}");
+        if is_last_repeat {
+            message.push_str(&std::format!(
+                "\n{TAB_IN_SPACES}The message repeats too often, further repetitions are only counted."
+            ));
         }
-        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => {
-            error!(message);
+
+        match severity {
+            ValidationSeverity::Verbose => debug!(message),
+            ValidationSeverity::Info => info!(message),
+            ValidationSeverity::Warning => warning!(message),
+            ValidationSeverity::Error => error!(message),
         }
-        _ => (),
     }
 
     vk::FALSE
 }
 
+/// #### Description
+/// Names of the objects the message is about, or their types when they aren't named.
+unsafe fn object_names(callback_data: &vk::DebugUtilsMessengerCallbackDataEXT) -> String {
+    if callback_data.p_objects.is_null() {
+        return "Unknown".to_owned();
+    }
+
+    let objects = unsafe {
+        std::slice::from_raw_parts(callback_data.p_objects, callback_data.object_count as usize)
+    };
+    objects
+        .iter()
+        .map(|object| {
+            if object.p_object_name.is_null() {
+                std::format!("{:?}", object.object_type)
+            } else {
+                let name = unsafe { std::ffi::CStr::from_ptr(object.p_object_name) };
+
+                std::format!("{:?} `{}`", object.object_type, name.to_string_lossy())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// #### Description
+/// Layers prefix the printed text with objects and the message ID, separated by ` | `.
+/// Everything after the separator which follows the message ID is the printed text,
+/// so ` | ` inside of it is kept.
+#[inline(always)]
+fn debug_printf_output(message: &str) -> &str {
+    message
+        .split_once("MessageID = ")
+        .and_then(|(_, message_id_and_output)| message_id_and_output.split_once(" | "))
+        .map_or(message, |(_, output)| output)
+        .trim()
+}
+
 pub struct DebugMessengerManager {
     pub debug_utils_loader: DebugUtils,
     pub debug_utils: vk::DebugUtilsMessengerEXT,
+    /// Boxed, so the pointer given to the callback stays valid when the manager moves.
+    validation_state: Box<ValidationState>,
 }
 
 impl DebugMessengerManager {
-    pub fn new(entry: &ash::Entry, instance: &ash::Instance) -> EngineResult<Self> {
+    pub fn new(
+        entry: &ash::Entry,
+        instance: &ash::Instance,
+        policy: &ValidationPolicy,
+    ) -> EngineResult<Self> {
         debug!("Initializing Validation Layer of Vulkan Instance");
 
+        let validation_state = Box::new(ValidationState::new(policy.clone()));
         let debug_utils_info = vk::DebugUtilsMessengerCreateInfoEXT::default()
-            .message_severity(
-                vk::DebugUtilsMessageSeverityFlagsEXT::INFO
-                    | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
-                    | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
-            )
+            .message_severity(policy.severity_flags())
             .message_type(
                 vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
                     | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
                     | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
             )
-            .pfn_user_callback(Some(debug_callback));
+            .pfn_user_callback(Some(debug_callback))
+            .user_data(&*validation_state as *const ValidationState as *mut std::ffi::c_void);
 
         let debug_utils_loader = DebugUtils::new(entry, instance);
         let debug_utils = match unsafe {
@@ -64,6 +137,266 @@ impl DebugMessengerManager {
         Ok(Self {
             debug_utils_loader,
             debug_utils,
+            validation_state,
         })
     }
+
+    #[inline(always)]
+    pub fn validation_stats(&self) -> ValidationStats {
+        self.validation_state.stats()
+    }
+
+    /// #### Description
+    /// First validation error when the policy is `StrictMode::Panic`.
+    #[inline(always)]
+    pub fn strict_failure(&self) -> Option<String> {
+        if self.validation_state.policy.strict != StrictMode::Panic {
+            return None;
+        }
+
+        self.validation_state
+            .stats
+            .lock()
+            .unwrap()
+            .first_error
+            .clone()
+    }
+}
+
+/// #### Description
+/// What a message should do after the policy was applied.
+#[derive(Debug, PartialEq, Eq)]
+enum MessageAction {
+    Ignore,
+    /// Output of `debugPrintfEXT`, always logged.
+    DebugPrintf,
+    /// Counted, but below the threshold or over the repeat limit.
+    Count,
+    Log {
+        /// The repeat limit was just reached, later repetitions aren't logged.
+        is_last_repeat: bool,
+    },
+}
+
+/// #### Description
+/// Policy with its counters, shared with the messenger callback through the user data pointer.
+struct ValidationState {
+    policy: ValidationPolicy,
+    stats: Mutex<ValidationStats>,
+    repeats: Mutex<AHashMap<String, u32>>,
+}
+
+impl ValidationState {
+    fn new(policy: ValidationPolicy) -> Self {
+        Self {
+            policy,
+            stats: Default::default(),
+            repeats: Default::default(),
+        }
+    }
+
+    #[inline(always)]
+    fn stats(&self) -> ValidationStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// #### Description
+    /// Printf messages are named `WARNING-DEBUG-PRINTF` by recent layers, `UNASSIGNED-DEBUG-PRINTF` by older ones.
+    #[inline(always)]
+    fn is_debug_printf(message_name: &str) -> bool {
+        message_name.ends_with("DEBUG-PRINTF")
+    }
+
+    /// #### Description
+    /// Applies the policy to a message and counts it.
+    fn process(
+        &self,
+        severity_flags: vk::DebugUtilsMessageSeverityFlagsEXT,
+        message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+        message_id: i32,
+        message_name: Option<&str>,
+        message: &str,
+    ) -> (ValidationSeverity, MessageAction) {
+        let severity = ValidationSeverity::from_flags(severity_flags);
+        let mut stats = self.stats.lock().unwrap();
+
+        if self.policy.is_ignored(message_id, message_name) {
+            stats.ignored += 1;
+
+            return (severity, MessageAction::Ignore);
+        }
+
+        if message_name.is_some_and(Self::is_debug_printf) {
+            stats.debug_printf += 1;
+
+            return (severity, MessageAction::DebugPrintf);
+        }
+
+        match severity {
+            ValidationSeverity::Verbose => stats.verbose += 1,
+            ValidationSeverity::Info => stats.info += 1,
+            ValidationSeverity::Warning => stats.warning += 1,
+            ValidationSeverity::Error => stats.error += 1,
+        }
+        // The callback can't unwind, so `StrictMode::Panic` only records the error here as well.
+        if severity == ValidationSeverity::Error
+            && self.policy.strict != StrictMode::Off
+            && stats.first_error.is_none()
+        {
+            stats.first_error = Some(message.to_owned());
+        }
+
+        if severity < self.policy.threshold(message_type) {
+            return (severity, MessageAction::Count);
+        }
+
+        let Some(repeat_limit) = self.policy.repeat_limit else {
+            return (
+                severity,
+                MessageAction::Log {
+                    is_last_repeat: false,
+                },
+            );
+        };
+
+        // Messages without a name (e.g. from the loader) are told apart by their text.
+        let key = message_name.unwrap_or(message);
+        let mut repeats = self.repeats.lock().unwrap();
+        let count = repeats.entry(key.to_owned()).or_default();
+        *count += 1;
+
+        if *count > repeat_limit {
+            stats.suppressed += 1;
+
+            return (severity, MessageAction::Count);
+        }
+
+        (
+            severity,
+            MessageAction::Log {
+                is_last_repeat: *count == repeat_limit,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ERROR: vk::DebugUtilsMessageSeverityFlagsEXT =
+        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR;
+    const WARNING: vk::DebugUtilsMessageSeverityFlagsEXT =
+        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING;
+    const INFO: vk::DebugUtilsMessageSeverityFlagsEXT = vk::DebugUtilsMessageSeverityFlagsEXT::INFO;
+    const VALIDATION: vk::DebugUtilsMessageTypeFlagsEXT =
+        vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION;
+
+    const LOG: MessageAction = MessageAction::Log {
+        is_last_repeat: false,
+    };
+
+    #[test]
+    fn ignored_messages_are_only_counted_as_ignored() {
+        let state = ValidationState::new(
+            ValidationPolicy::default()
+                .with_ignored_message_id(42)
+                .with_ignored_message_name("VUID-vkCmdDraw-None-02699"),
+        );
+
+        let (_, by_id) = state.process(ERROR, VALIDATION, 42, Some("VUID-other"), "");
+        let (_, by_name) =
+            state.process(ERROR, VALIDATION, 7, Some("VUID-vkCmdDraw-None-02699"), "");
+        let (_, other) = state.process(ERROR, VALIDATION, 7, Some("VUID-other"), "");
+
+        assert_eq!(by_id, MessageAction::Ignore);
+        assert_eq!(by_name, MessageAction::Ignore);
+        assert_eq!(other, LOG);
+
+        let stats = state.stats();
+        assert_eq!(stats.ignored, 2);
+        assert_eq!(stats.error, 1);
+    }
+
+    #[test]
+    fn repetitions_over_the_limit_are_suppressed() {
+        let state = ValidationState::new(ValidationPolicy::default().with_repeat_limit(Some(2)));
+
+        let actions = (0..3)
+            .map(|_| {
+                state
+                    .process(WARNING, VALIDATION, 1, Some("VUID-repeated"), "")
+                    .1
+            })
+            .collect::<Vec<_>>();
+        let (_, other) = state.process(WARNING, VALIDATION, 2, Some("VUID-other"), "");
+
+        assert_eq!(
+            actions,
+            vec![
+                LOG,
+                MessageAction::Log {
+                    is_last_repeat: true,
+                },
+                MessageAction::Count,
+            ]
+        );
+        assert_eq!(other, LOG);
+
+        let stats = state.stats();
+        assert_eq!(stats.warning, 4);
+        assert_eq!(stats.suppressed, 1);
+    }
+
+    #[test]
+    fn messages_without_name_are_told_apart_by_text() {
+        let state = ValidationState::new(ValidationPolicy::default().with_repeat_limit(Some(1)));
+
+        let (_, first) = state.process(WARNING, VALIDATION, 0, None, "Loader message");
+        let (_, repeated) = state.process(WARNING, VALIDATION, 0, None, "Loader message");
+        let (_, other) = state.process(WARNING, VALIDATION, 0, None, "Another message");
+
+        assert_eq!(
+            first,
+            MessageAction::Log {
+                is_last_repeat: true,
+            }
+        );
+        assert_eq!(repeated, MessageAction::Count);
+        assert_ne!(other, MessageAction::Count);
+    }
+
+    #[test]
+    fn messages_below_threshold_are_counted_without_logging() {
+        let state = ValidationState::new(
+            ValidationPolicy::default()
+                .with_threshold(ValidationSeverity::Warning)
+                .with_repeat_limit(None),
+        );
+
+        let (severity, action) = state.process(INFO, VALIDATION, 0, Some("VUID-info"), "");
+
+        assert_eq!(severity, ValidationSeverity::Info);
+        assert_eq!(action, MessageAction::Count);
+        assert_eq!(state.stats().info, 1);
+    }
+
+    #[test]
+    fn debug_printf_is_always_reported_with_its_text() {
+        let state = ValidationState::new(
+            ValidationPolicy::default().with_threshold(ValidationSeverity::Error),
+        );
+
+        let (_, action) = state.process(INFO, VALIDATION, 0, Some("WARNING-DEBUG-PRINTF"), "");
+
+        assert_eq!(action, MessageAction::DebugPrintf);
+        assert_eq!(state.stats().debug_printf, 1);
+        assert_eq!(
+            debug_printf_output(
+                "Object 0: handle = 0x1, type = VK_OBJECT_TYPE_QUEUE; | MessageID = 0x76589099 | a | b = 1"
+            ),
+            "a | b = 1"
+        );
+        assert_eq!(debug_printf_output(" value = 2 "), "value = 2");
+    }
 }
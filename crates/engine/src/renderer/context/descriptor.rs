@@ -0,0 +1,483 @@
+use logging::*;
+
+use crate::{
+    common::Id,
+    error::{EngineError, EngineResult},
+};
+
+use super::resource_manager::RESOURCE_MANAGER;
+
+use ahash::AHashMap;
+use ash::vk;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DescriptorBinding {
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub count: u32,
+    pub stages: vk::ShaderStageFlags,
+}
+
+impl DescriptorBinding {
+    #[inline(always)]
+    pub fn new(
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        stages: vk::ShaderStageFlags,
+    ) -> Self {
+        Self {
+            binding,
+            descriptor_type,
+            count: 1,
+            stages,
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_count(self, count: u32) -> Self {
+        Self { count, ..self }
+    }
+}
+
+/// #### Description
+/// Creates every distinct set layout once, equal binding lists share one `vk::DescriptorSetLayout`.
+#[derive(Default)]
+struct DescriptorLayoutCache {
+    layouts: AHashMap<Vec<DescriptorBinding>, vk::DescriptorSetLayout>,
+}
+
+impl DescriptorLayoutCache {
+    fn layout(
+        &mut self,
+        device: &super::DeviceManager,
+        bindings: &[DescriptorBinding],
+    ) -> EngineResult<vk::DescriptorSetLayout> {
+        // Order of bindings doesn't matter to Vulkan, so it mustn't matter to the cache either.
+        let mut key = bindings.to_vec();
+        key.sort_unstable_by_key(|binding| binding.binding);
+
+        if let Some(&layout) = self.layouts.get(&key) {
+            return Ok(layout);
+        }
+
+        let layout_bindings = key
+            .iter()
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::default()
+                    .binding(binding.binding)
+                    .descriptor_type(binding.descriptor_type)
+                    .descriptor_count(binding.count)
+                    .stage_flags(binding.stages)
+            })
+            .collect::<smallvec::SmallVec<[_; 8]>>();
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::default().bindings(&layout_bindings);
+        let layout = device.create_descriptor_set_layout(&layout_info)?;
+
+        self.layouts.insert(key, layout);
+
+        Ok(layout)
+    }
+
+    fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        for (_, layout) in self.layouts.drain() {
+            device.destroy_descriptor_set_layout(layout);
+        }
+    }
+}
+
+/// #### Description
+/// Allocates descriptor sets from a list of pools, a new (bigger) pool is created once the current one runs out.
+struct DescriptorAllocator {
+    sets_per_pool: u32,
+    ready_pools: Vec<vk::DescriptorPool>,
+    full_pools: Vec<vk::DescriptorPool>,
+}
+
+impl DescriptorAllocator {
+    const INITIAL_SETS_PER_POOL: u32 = 64;
+    const MAX_SETS_PER_POOL: u32 = 4096;
+    /// Descriptors of each type per set in a pool.
+    const POOL_RATIOS: &[(vk::DescriptorType, u32)] = &[
+        (vk::DescriptorType::SAMPLER, 1),
+        (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4),
+        (vk::DescriptorType::SAMPLED_IMAGE, 4),
+        (vk::DescriptorType::STORAGE_IMAGE, 1),
+        (vk::DescriptorType::UNIFORM_BUFFER, 2),
+        (vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC, 1),
+        (vk::DescriptorType::STORAGE_BUFFER, 2),
+        (vk::DescriptorType::STORAGE_BUFFER_DYNAMIC, 1),
+    ];
+
+    #[inline(always)]
+    fn new() -> Self {
+        Self {
+            sets_per_pool: Self::INITIAL_SETS_PER_POOL,
+            ready_pools: Default::default(),
+            full_pools: Default::default(),
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        device: &super::DeviceManager,
+        layout: vk::DescriptorSetLayout,
+    ) -> EngineResult<vk::DescriptorSet> {
+        let mut pool = self.take_pool(device)?;
+
+        let set = match Self::allocate_from(device, pool, layout) {
+            Err(EngineError::VulkanApiError(
+                vk::Result::ERROR_OUT_OF_POOL_MEMORY | vk::Result::ERROR_FRAGMENTED_POOL,
+            )) => {
+                self.full_pools.push(pool);
+                pool = self.take_pool(device)?;
+
+                Self::allocate_from(device, pool, layout)
+            }
+            result => result,
+        };
+
+        self.ready_pools.push(pool);
+
+        set
+    }
+
+    /// #### Description
+    /// Returns every set to its pool, the sets must not be in use by the GPU anymore.
+    fn reset(&mut self, device: &super::DeviceManager) -> EngineResult<()> {
+        for &pool in self.ready_pools.iter().chain(&self.full_pools) {
+            device.reset_descriptor_pool(pool)?;
+        }
+        self.ready_pools.append(&mut self.full_pools);
+
+        Ok(())
+    }
+
+    fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        for pool in self.ready_pools.drain(..).chain(self.full_pools.drain(..)) {
+            device.destroy_descriptor_pool(pool);
+        }
+    }
+
+    #[inline(always)]
+    fn allocate_from(
+        device: &super::DeviceManager,
+        pool: vk::DescriptorPool,
+        layout: vk::DescriptorSetLayout,
+    ) -> EngineResult<vk::DescriptorSet> {
+        let layouts = [layout];
+        let set_info = vk::DescriptorSetAllocateInfo::default()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+
+        Ok(device.allocate_descriptor_sets(&set_info)?[0])
+    }
+
+    fn take_pool(&mut self, device: &super::DeviceManager) -> EngineResult<vk::DescriptorPool> {
+        if let Some(pool) = self.ready_pools.pop() {
+            return Ok(pool);
+        }
+
+        debug!(std::format!(
+            "Creating descriptor pool for {} sets",
+            self.sets_per_pool
+        ));
+
+        let pool_sizes = Self::POOL_RATIOS
+            .iter()
+            .map(|&(descriptor_type, ratio)| {
+                vk::DescriptorPoolSize::default()
+                    .ty(descriptor_type)
+                    .descriptor_count(ratio * self.sets_per_pool)
+            })
+            .collect::<smallvec::SmallVec<[_; 8]>>();
+        let pool_info = vk::DescriptorPoolCreateInfo::default()
+            .max_sets(self.sets_per_pool)
+            .pool_sizes(&pool_sizes);
+        let pool = device.create_descriptor_pool(&pool_info)?;
+
+        self.sets_per_pool = (self.sets_per_pool * 2).min(Self::MAX_SETS_PER_POOL);
+
+        Ok(pool)
+    }
+}
+
+/// #### Description
+/// Owns descriptor set layouts and pools. Persistent sets live until the Context is destroyed,
+/// transient sets live for one frame in flight and are freed all at once when the frame is reused.
+pub struct DescriptorManager {
+    layout_cache: DescriptorLayoutCache,
+    persistent_allocator: DescriptorAllocator,
+    frame_allocators: Vec<DescriptorAllocator>,
+}
+
+impl DescriptorManager {
+    pub fn new(frames_in_flight: usize) -> Self {
+        debug!("Initializing descriptor pools");
+
+        Self {
+            layout_cache: Default::default(),
+            persistent_allocator: DescriptorAllocator::new(),
+            frame_allocators: (0..frames_in_flight)
+                .map(|_| DescriptorAllocator::new())
+                .collect(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn layout(
+        &mut self,
+        device: &super::DeviceManager,
+        bindings: &[DescriptorBinding],
+    ) -> EngineResult<vk::DescriptorSetLayout> {
+        self.layout_cache.layout(device, bindings)
+    }
+
+    #[inline(always)]
+    pub fn layout_count(&self) -> usize {
+        self.layout_cache.layouts.len()
+    }
+
+    #[inline(always)]
+    pub fn allocate(
+        &mut self,
+        device: &super::DeviceManager,
+        layout: vk::DescriptorSetLayout,
+    ) -> EngineResult<vk::DescriptorSet> {
+        self.persistent_allocator.allocate(device, layout)
+    }
+
+    #[inline(always)]
+    pub fn allocate_transient(
+        &mut self,
+        device: &super::DeviceManager,
+        frame_index: usize,
+        layout: vk::DescriptorSetLayout,
+    ) -> EngineResult<vk::DescriptorSet> {
+        self.frame_allocators[frame_index].allocate(device, layout)
+    }
+
+    /// #### Description
+    /// Must be called only after the GPU finished the work of the frame.
+    #[inline(always)]
+    pub fn reset_frame(
+        &mut self,
+        device: &super::DeviceManager,
+        frame_index: usize,
+    ) -> EngineResult<()> {
+        self.frame_allocators[frame_index].reset(device)
+    }
+
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        self.persistent_allocator.destroy_resources(device);
+        for frame_allocator in &mut self.frame_allocators {
+            frame_allocator.destroy_resources(device);
+        }
+        self.layout_cache.destroy_resources(device);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DescriptorResource {
+    Buffer {
+        id: Id,
+        offset: vk::DeviceSize,
+        range: vk::DeviceSize,
+    },
+    Image {
+        id: Id,
+        sampler: vk::Sampler,
+    },
+    Sampler(vk::Sampler),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DescriptorWrite {
+    binding: u32,
+    array_element: u32,
+    descriptor_type: vk::DescriptorType,
+    resource: DescriptorResource,
+}
+
+/// #### Description
+/// Collects buffers, images and samplers to write into a descriptor set.
+/// Resources are referenced by Id and resolved when the writes are applied.
+#[derive(Debug, Default, Clone)]
+pub struct DescriptorWriter {
+    writes: Vec<DescriptorWrite>,
+}
+
+impl DescriptorWriter {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// #### Description
+    /// Binds the whole buffer.
+    #[inline(always)]
+    pub fn buffer(self, binding: u32, descriptor_type: vk::DescriptorType, id: Id) -> Self {
+        self.buffer_range(
+            binding,
+            descriptor_type,
+            id,
+            Default::default(),
+            vk::WHOLE_SIZE,
+        )
+    }
+
+    pub fn buffer_range(
+        mut self,
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+        id: Id,
+        offset: vk::DeviceSize,
+        range: vk::DeviceSize,
+    ) -> Self {
+        self.writes.push(DescriptorWrite {
+            binding,
+            array_element: Default::default(),
+            descriptor_type,
+            resource: DescriptorResource::Buffer { id, offset, range },
+        });
+
+        self
+    }
+
+    /// #### Description
+    /// Sampled or storage image, storage images are expected in `GENERAL` layout,
+    /// sampled ones in `SHADER_READ_ONLY_OPTIMAL`.
+    #[inline(always)]
+    pub fn image(self, binding: u32, descriptor_type: vk::DescriptorType, id: Id) -> Self {
+        self.image_at(
+            binding,
+            Default::default(),
+            descriptor_type,
+            id,
+            vk::Sampler::null(),
+        )
+    }
+
+    #[inline(always)]
+    pub fn combined_image_sampler(self, binding: u32, id: Id, sampler: vk::Sampler) -> Self {
+        self.image_at(
+            binding,
+            Default::default(),
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+            id,
+            sampler,
+        )
+    }
+
+    /// #### Description
+    /// Writes into element `array_element` of an arrayed binding.
+    pub fn image_at(
+        mut self,
+        binding: u32,
+        array_element: u32,
+        descriptor_type: vk::DescriptorType,
+        id: Id,
+        sampler: vk::Sampler,
+    ) -> Self {
+        self.writes.push(DescriptorWrite {
+            binding,
+            array_element,
+            descriptor_type,
+            resource: DescriptorResource::Image { id, sampler },
+        });
+
+        self
+    }
+
+    pub fn sampler(mut self, binding: u32, sampler: vk::Sampler) -> Self {
+        self.writes.push(DescriptorWrite {
+            binding,
+            array_element: Default::default(),
+            descriptor_type: vk::DescriptorType::SAMPLER,
+            resource: DescriptorResource::Sampler(sampler),
+        });
+
+        self
+    }
+
+    pub fn update(
+        &self,
+        device: &super::DeviceManager,
+        set: vk::DescriptorSet,
+    ) -> EngineResult<()> {
+        // Infos are collected first, so their addresses stay put while the writes point at them.
+        let mut buffer_infos = Vec::with_capacity(self.writes.len());
+        let mut image_infos = Vec::with_capacity(self.writes.len());
+
+        for write in &self.writes {
+            match write.resource {
+                DescriptorResource::Buffer { id, offset, range } => {
+                    let Some(buffer) = (unsafe { RESOURCE_MANAGER.buffer(id) }) else {
+                        return Err(EngineError::UnknownError(std::format!(
+                            "Unable to find Buffer {id:?}"
+                        )));
+                    };
+
+                    buffer_infos.push(
+                        vk::DescriptorBufferInfo::default()
+                            .buffer(buffer.buffer)
+                            .offset(offset)
+                            .range(range),
+                    );
+                }
+                DescriptorResource::Image { id, sampler } => {
+                    let Some(image) = (unsafe { RESOURCE_MANAGER.image(id) }) else {
+                        return Err(EngineError::UnknownError(std::format!(
+                            "Unable to find Image {id:?}"
+                        )));
+                    };
+                    let image_layout = match write.descriptor_type {
+                        vk::DescriptorType::STORAGE_IMAGE => vk::ImageLayout::GENERAL,
+                        _ => vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    };
+
+                    image_infos.push(
+                        vk::DescriptorImageInfo::default()
+                            .image_view(image.view)
+                            .image_layout(image_layout)
+                            .sampler(sampler),
+                    );
+                }
+                DescriptorResource::Sampler(sampler) => {
+                    image_infos.push(vk::DescriptorImageInfo::default().sampler(sampler));
+                }
+            }
+        }
+
+        let (mut buffer_index, mut image_index) = (0, 0);
+        let writes = self
+            .writes
+            .iter()
+            .map(|write| {
+                let descriptor_write = vk::WriteDescriptorSet::default()
+                    .dst_set(set)
+                    .dst_binding(write.binding)
+                    .dst_array_element(write.array_element)
+                    .descriptor_type(write.descriptor_type);
+
+                match write.resource {
+                    DescriptorResource::Buffer { .. } => {
+                        buffer_index += 1;
+
+                        descriptor_write
+                            .buffer_info(std::slice::from_ref(&buffer_infos[buffer_index - 1]))
+                    }
+                    DescriptorResource::Image { .. } | DescriptorResource::Sampler(_) => {
+                        image_index += 1;
+
+                        descriptor_write
+                            .image_info(std::slice::from_ref(&image_infos[image_index - 1]))
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        device.update_descriptor_sets(&writes);
+
+        Ok(())
+    }
+}
@@ -5,254 +5,526 @@ use crate::{
     renderer::utils::to_cstr,
 };
 
+use super::features::{DeviceFeature, DeviceRequirements, EnabledFeatures, FeatureSet};
+
 use std::ffi::c_char;
 
-use ash::vk;
+use ash::{extensions::khr::Swapchain, vk};
+use smallvec::SmallVec;
 
-pub struct PresentModes {
-    pub mailbox: Option<vk::PresentModeKHR>,
-    pub fifo_relaxed: Option<vk::PresentModeKHR>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueueKind {
+    Graphics,
+    Compute,
+    Transfer,
 }
 
-impl PresentModes {
-    pub fn new(present_modes: &[vk::PresentModeKHR]) -> Option<Self> {
-        let does_support_mailbox = present_modes.contains(&vk::PresentModeKHR::MAILBOX);
-        let does_support_fifo = present_modes.contains(&vk::PresentModeKHR::FIFO);
+/// #### Description
+/// Queue used for a kind of work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueLocation {
+    pub family_index: u32,
+    /// Index of the queue in its family.
+    pub queue_index: u32,
+}
 
-        let (mailbox, fifo_relaxed) = match (does_support_mailbox, does_support_fifo) {
-            (true, true) => (
-                Some(vk::PresentModeKHR::MAILBOX),
-                Some(vk::PresentModeKHR::FIFO),
-            ),
-            (true, false) => (Some(vk::PresentModeKHR::MAILBOX), None),
-            (false, true) => (None, Some(vk::PresentModeKHR::FIFO)),
-            (false, false) => return None,
+/// #### Description
+/// Queues the Device uses for every kind of work. Dedicated compute and transfer families are preferred,
+/// otherwise the kind falls back to the graphics family: to its next queue if there is one, else to the same queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueTopology {
+    pub graphics: QueueLocation,
+    pub compute: QueueLocation,
+    pub transfer: QueueLocation,
+}
+
+impl QueueTopology {
+    /// #### Description
+    /// `does_support_present` is asked only about candidates for the graphics family.
+    fn discover(
+        queue_family_properties: &[vk::QueueFamilyProperties],
+        does_support_present: impl Fn(u32) -> bool,
+    ) -> Option<Self> {
+        let find_family = |predicate: &dyn Fn(u32, vk::QueueFlags) -> bool| {
+            queue_family_properties
+                .iter()
+                .enumerate()
+                .map(|(family_index, properties)| (family_index as u32, properties))
+                .find(|&(family_index, properties)| {
+                    properties.queue_count > 0 && predicate(family_index, properties.queue_flags)
+                })
+                .map(|(family_index, _)| family_index)
+        };
+
+        // Vulkan guarantees a family with both GRAPHICS and COMPUTE if there is any GRAPHICS family.
+        let graphics_family = find_family(&|family_index, queue_flags| {
+            queue_flags.contains(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+                && does_support_present(family_index)
+        })?;
+        let compute_family = find_family(&|_, queue_flags| {
+            queue_flags.contains(vk::QueueFlags::COMPUTE)
+                && !queue_flags.contains(vk::QueueFlags::GRAPHICS)
+        })
+        .unwrap_or(graphics_family);
+        let transfer_family = find_family(&|_, queue_flags| {
+            queue_flags.contains(vk::QueueFlags::TRANSFER)
+                && !queue_flags.intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+        })
+        .unwrap_or(graphics_family);
+
+        // Queues of a family are handed out in order, once they run out the last one is shared.
+        let mut used_queue_counts = vec![0_u32; queue_family_properties.len()];
+        let mut locate = |family_index: u32| {
+            let queue_count = queue_family_properties[family_index as usize].queue_count;
+            let used_queue_count = &mut used_queue_counts[family_index as usize];
+            let queue_index = (*used_queue_count).min(queue_count - 1);
+            *used_queue_count += 1;
+
+            QueueLocation {
+                family_index,
+                queue_index,
+            }
         };
 
         Some(Self {
-            mailbox,
-            fifo_relaxed,
+            graphics: locate(graphics_family),
+            compute: locate(compute_family),
+            transfer: locate(transfer_family),
         })
     }
+
+    #[inline(always)]
+    pub fn queue(&self, queue_kind: QueueKind) -> QueueLocation {
+        match queue_kind {
+            QueueKind::Graphics => self.graphics,
+            QueueKind::Compute => self.compute,
+            QueueKind::Transfer => self.transfer,
+        }
+    }
+
+    /// #### Description
+    /// The kind has its own family, so its work can overlap with graphics work.
+    #[inline(always)]
+    pub fn is_dedicated(&self, queue_kind: QueueKind) -> bool {
+        self.queue(queue_kind).family_index != self.graphics.family_index
+    }
+
+    /// #### Description
+    /// The kind has a queue of its own, possibly in the graphics family.
+    #[inline(always)]
+    pub fn is_separate(&self, queue_kind: QueueKind) -> bool {
+        queue_kind == QueueKind::Graphics || self.queue(queue_kind) != self.graphics
+    }
+
+    /// #### Description
+    /// Distinct families in use, the graphics family first.
+    pub fn family_indices(&self) -> SmallVec<[u32; 3]> {
+        let mut family_indices = SmallVec::new();
+        for queue in [self.graphics, self.compute, self.transfer] {
+            if !family_indices.contains(&queue.family_index) {
+                family_indices.push(queue.family_index);
+            }
+        }
+
+        family_indices
+    }
+
+    /// #### Description
+    /// Number of queues to create in every family in use.
+    fn queue_counts(&self) -> SmallVec<[(u32, u32); 3]> {
+        self.family_indices()
+            .into_iter()
+            .map(|family_index| {
+                let queue_count = [self.graphics, self.compute, self.transfer]
+                    .into_iter()
+                    .filter(|queue| queue.family_index == family_index)
+                    .map(|queue| queue.queue_index + 1)
+                    .max()
+                    .unwrap_or(1);
+
+                (family_index, queue_count)
+            })
+            .collect()
+    }
+}
+
+/// #### Description
+/// How the GPU is picked when there are several, `Auto` picks the suitable one with the highest score.
+///
+/// #### NOTE
+/// The `ENGINE_DEVICE` environment variable overrides the selection of the config, e.g.
+/// `ENGINE_DEVICE=1` or `index:1`, `name:radeon`, `id:0x10de:0x2684`, `auto`.
+/// Any other value is treated as a name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DeviceSelection {
+    #[default]
+    Auto,
+    /// Case insensitive substring of the device name.
+    Name(String),
+    /// Index in the order devices are enumerated by the driver.
+    Index(usize),
+    Id {
+        vendor_id: u32,
+        device_id: u32,
+    },
+}
+
+impl DeviceSelection {
+    pub const ENV_VAR: &str = "ENGINE_DEVICE";
+
+    fn matches(&self, candidate: &DeviceCandidate) -> bool {
+        match self {
+            Self::Auto => true,
+            Self::Name(name) => candidate.name.to_lowercase().contains(&name.to_lowercase()),
+            Self::Index(index) => candidate.index == *index,
+            Self::Id {
+                vendor_id,
+                device_id,
+            } => {
+                candidate.properties.vendor_id == *vendor_id
+                    && candidate.properties.device_id == *device_id
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for DeviceSelection {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse_hex = |value: &str| {
+            u32::from_str_radix(value.trim_start_matches("0x"), 16)
+                .map_err(|e| std::format!("Invalid ID {value} in device selection: {e}"))
+        };
+
+        let value = value.trim();
+        let selection = match value.split_once(':') {
+            _ if value.eq_ignore_ascii_case("auto") => Self::Auto,
+            Some(("index", index)) => Self::Index(
+                index
+                    .parse()
+                    .map_err(|e| std::format!("Invalid index {index} in device selection: {e}"))?,
+            ),
+            Some(("name", name)) => Self::Name(name.to_owned()),
+            Some(("id", ids)) => {
+                let Some((vendor_id, device_id)) = ids.split_once(':') else {
+                    return Err(std::format!(
+                        "Device selection {value} must be in form id:<vendor>:<device>"
+                    ));
+                };
+
+                Self::Id {
+                    vendor_id: parse_hex(vendor_id)?,
+                    device_id: parse_hex(device_id)?,
+                }
+            }
+            _ => match value.parse() {
+                Ok(index) => Self::Index(index),
+                Err(_) => Self::Name(value.to_owned()),
+            },
+        };
+
+        Ok(selection)
+    }
+}
+
+/// #### Description
+/// Enumerated physical device with the result of the suitability checks.
+struct DeviceCandidate {
+    index: usize,
+    physical_device: vk::PhysicalDevice,
+    properties: vk::PhysicalDeviceProperties,
+    name: String,
+    /// Reason of the rejection if the device isn't suitable.
+    suitability: Result<SuitableDevice, String>,
+}
+
+struct SuitableDevice {
+    queue_topology: QueueTopology,
+    surface_formats: Option<Vec<vk::SurfaceFormatKHR>>,
+    present_modes: Option<Vec<vk::PresentModeKHR>>,
+    enabled_features: EnabledFeatures,
+    score: u64,
 }
 
 pub struct DeviceManager {
-    _physical_device: vk::PhysicalDevice,
+    pub physical_device: vk::PhysicalDevice,
     device: ash::Device,
     pub device_properties: vk::PhysicalDeviceProperties,
-    pub surface_format: vk::SurfaceFormatKHR,
-    pub present_modes: PresentModes,
+    pub queue_topology: QueueTopology,
+    /// Formats supported by the surface, the swapchain picks one by the preferences of the application.
+    pub surface_formats: Option<Vec<vk::SurfaceFormatKHR>>,
+    /// Present modes supported by the surface, FIFO is always among them.
+    pub present_modes: Option<Vec<vk::PresentModeKHR>>,
+    pub memory_properties: vk::PhysicalDeviceMemoryProperties,
+    /// Extensions and features negotiated from the requirements of the engine and the application.
+    pub enabled_features: EnabledFeatures,
+    /// Descriptor indexing features required by the bindless mode are enabled.
+    pub is_bindless_enabled: bool,
+    /// Valid bits of timestamps written on the queue family, `0` means timestamps aren't supported.
+    pub timestamp_valid_bits: u32,
+    /// Limits of update-after-bind descriptors, which the bindless set is made of.
+    pub descriptor_indexing_properties: vk::PhysicalDeviceDescriptorIndexingProperties<'static>,
     pub graphics_queue: vk::Queue,
+    pub compute_queue: vk::Queue,
     pub transfer_queue: vk::Queue,
+    /// Loader used for object names and command buffer labels.
+    #[cfg(feature = "dev")]
+    debug_utils_loader: Option<ash::extensions::ext::DebugUtils>,
 }
 
 impl DeviceManager {
     const DEFAULT_QUEUE_PRIORITY: f32 = 1.0;
 
+    /// #### Description
+    /// Picks a GPU according to `selection` and creates a Device on it.
+    /// Without a surface (headless) present support, surface formats and present modes aren't required.
+    /// With `is_bindless` only devices which support descriptor indexing are suitable.
+    /// Devices missing anything required by `requirements` are rejected, optional ones are enabled when supported.
+    ///
+    /// #### NOTE
+    /// The `ENGINE_DEVICE` environment variable overrides `selection`, see `DeviceSelection`.
     pub fn new(
         instance: &ash::Instance,
-        surface_handle: &super::SurfaceManager,
+        surface_handle: Option<&super::SurfaceManager>,
+        is_bindless: bool,
+        selection: &DeviceSelection,
+        requirements: &DeviceRequirements,
     ) -> EngineResult<Self> {
-        let surface = surface_handle.surface;
-        let surface_loader = &surface_handle.surface_loader;
-
         debug!("Finding suitable device.");
 
         let required_layer_names = super::Context::REQUIRED_LAYERS;
-        let required_extension_names = [ash::extensions::khr::Swapchain::name().as_ptr()];
-        let (physical_device, device_properties, queue_family_index, surface_format, present_modes) = unsafe {
-            instance
-                .enumerate_physical_devices()?
-                .into_iter()
-                .filter_map(|physical_device| {
-                    let device_properties =
-                        instance.get_physical_device_properties(physical_device);
-                    let device_name = to_cstr(device_properties.device_name.as_ptr())
-                        .to_str()
-                        .unwrap()
-                        .to_owned();
+        let requirements =
+            Self::engine_requirements(surface_handle.is_some(), is_bindless).merge(requirements);
 
-                    debug!(std::format!(
-                        "Checking for compatibility GPU: {device_name}."
-                    ));
+        let selection = match std::env::var(DeviceSelection::ENV_VAR) {
+            Ok(value) => {
+                let selection = value
+                    .parse::<DeviceSelection>()
+                    .map_err(EngineError::DeviceCreationFailed)?;
+                info!(std::format!(
+                    "Device selection is overridden by {}: {selection:?}.",
+                    DeviceSelection::ENV_VAR
+                ));
 
-                    // TODO: Make more complicated algorithm of finding suitable family queue in the feature.
-                    debug!("Checking for Queue Families requirements.");
-
-                    let queue_family_index = match instance
-                        .get_physical_device_queue_family_properties(physical_device)
-                        .into_iter()
-                        .enumerate()
-                        .find(|(i, queue_family_property)| {
-                            let queue_flags = queue_family_property.queue_flags;
-
-                            // TODO: This implementation considers that queue family has both GRAPHICS and TRANSFER.
-                            // Later need to make it more flexible and general for the GPU of any kind.
-                            queue_family_property.queue_count > 1
-                                && queue_flags.contains(vk::QueueFlags::GRAPHICS)
-                                && queue_flags.contains(vk::QueueFlags::TRANSFER)
-                                && surface_loader
-                                    .get_physical_device_surface_support(
-                                        physical_device,
-                                        *i as u32,
-                                        surface,
-                                    )
-                                    .unwrap()
-                        }) {
-                        Some(queue_family) => queue_family.0,
-                        None => {
-                            debug!("Unable to find required Queues.");
-
-                            return None;
-                        }
-                    };
-
-                    debug!("Checking for Surface Format requirements.");
-
-                    let surface_formats = surface_loader
-                        .get_physical_device_surface_formats(physical_device, surface)
-                        .unwrap();
-                    let surface_format = match surface_formats.into_iter().find(|surface_format| {
-                        let format = surface_format.format;
-
-                        (format == vk::Format::R8G8B8A8_SRGB || format == vk::Format::B8G8R8A8_SRGB)
-                            && surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
-                    }) {
-                        Some(surface_format) => surface_format,
-                        None => {
-                            debug!(
-                                "Unable to find required Surface Format (RGBA8, SRGB Nonlinear)."
-                            );
-
-                            return None;
-                        }
-                    };
-
-                    debug!("Checking for Present Modes requirements.");
-
-                    let present_modes = surface_loader
-                        .get_physical_device_surface_present_modes(physical_device, surface)
-                        .unwrap();
-                    let present_modes = match PresentModes::new(&present_modes) {
-                        Some(present_modes) => present_modes,
-                        None => {
-                            debug!("Unable to find required Present Modes (MAILBOX, FIFO)");
-
-                            return None;
-                        }
-                    };
-
-                    debug!("Checking for Device Layers requirement.");
-
-                    #[cfg(feature = "dev")]
-                    {
-                        let available_layers = instance
-                            .enumerate_device_layer_properties(physical_device)
-                            .unwrap();
-                        let does_support = super::Context::does_support_layers(
-                            required_layer_names,
-                            &available_layers,
-                            &std::format!(
-                                "Failed to find required Layers of Device - {device_name}",
-                            ),
-                        );
-                        if !does_support {
-                            return None;
-                        }
-                    }
-
-                    debug!("Checking for Device Extensions requirement.");
-
-                    let available_extensions = instance
-                        .enumerate_device_extension_properties(physical_device)
-                        .unwrap();
-                    let does_support = super::Context::does_support_extensions(
-                        &required_extension_names,
-                        &available_extensions,
-                        &std::format!(
-                            "Failed to find required Extensions of Device - {device_name}",
-                        ),
-                    );
-                    if !does_support {
-                        return None;
-                    }
-
-                    Some((
-                        physical_device,
-                        device_properties,
-                        queue_family_index as u32,
-                        surface_format,
-                        present_modes,
-                    ))
-                })
-                .max_by_key(
-                    |(_, device_properties, _, _, _)| match device_properties.device_type {
-                        vk::PhysicalDeviceType::DISCRETE_GPU => 2,
-                        vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
-                        _ => Default::default(),
-                    },
+                selection
+            }
+            Err(_) => selection.clone(),
+        };
+
+        let mut candidates = unsafe { instance.enumerate_physical_devices()? }
+            .into_iter()
+            .enumerate()
+            .map(|(index, physical_device)| {
+                Self::check_device(
+                    instance,
+                    physical_device,
+                    index,
+                    surface_handle,
+                    &requirements,
                 )
-                .ok_or(EngineError::DeviceCreationFailed(
-                    "Unable to find suitable Device.".to_owned(),
-                ))?
+            })
+            .collect::<Vec<_>>();
+
+        let suitable_candidates = candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, candidate)| Some((i, candidate.suitability.as_ref().ok()?)));
+        let selected_index = match &selection {
+            DeviceSelection::Auto => suitable_candidates
+                .max_by_key(|(_, suitable_device)| suitable_device.score)
+                .map(|(i, _)| i),
+            _ => suitable_candidates
+                .map(|(i, _)| i)
+                .find(|&i| selection.matches(&candidates[i])),
+        };
+        let Some(selected_index) = selected_index else {
+            return Err(EngineError::DeviceCreationFailed(Self::rejection_report(
+                &selection,
+                &candidates,
+            )));
         };
 
-        let device_name = to_cstr(device_properties.device_name.as_ptr())
-            .to_str()
-            .unwrap()
-            .to_owned();
+        let candidate = candidates.swap_remove(selected_index);
+        let SuitableDevice {
+            queue_topology,
+            surface_formats,
+            present_modes,
+            enabled_features,
+            ..
+        } = candidate
+            .suitability
+            .map_err(EngineError::DeviceCreationFailed)?;
+        let physical_device = candidate.physical_device;
+        let device_properties = candidate.properties;
+        let device_name = candidate.name;
         info!(std::format!("Found suitable GPU: {device_name}.",));
         debug!("Creating Vulkan Device.");
 
-        let feature_names = [ash::extensions::khr::DynamicRendering::name()];
-        let mut device_features13 =
-            vk::PhysicalDeviceVulkan13Features::default().dynamic_rendering(true);
-        let mut device_features2 =
-            vk::PhysicalDeviceFeatures2::default().push_next(&mut device_features13);
+        let extension_names = enabled_features
+            .extensions()
+            .iter()
+            .map(|extension_name| extension_name.as_ptr())
+            .collect::<Vec<_>>();
 
-        let queue_infos = [vk::DeviceQueueCreateInfo::default()
-            .queue_family_index(queue_family_index)
-            .queue_priorities(&[Self::DEFAULT_QUEUE_PRIORITY, Self::DEFAULT_QUEUE_PRIORITY])];
+        // The picked Device supports Vulkan 1.3, so feature structs of every version can be chained.
+        let mut feature_set = FeatureSet::with_features(enabled_features.features());
+        let mut device_features2 = vk::PhysicalDeviceFeatures2::default()
+            .features(feature_set.core)
+            .push_next(&mut feature_set.vulkan11)
+            .push_next(&mut feature_set.vulkan12)
+            .push_next(&mut feature_set.vulkan13);
+
+        let queue_priorities = [Self::DEFAULT_QUEUE_PRIORITY; 3];
+        let queue_infos = queue_topology
+            .queue_counts()
+            .into_iter()
+            .map(|(family_index, queue_count)| {
+                vk::DeviceQueueCreateInfo::default()
+                    .queue_family_index(family_index)
+                    .queue_priorities(&queue_priorities[..queue_count as usize])
+            })
+            .collect::<SmallVec<[_; 3]>>();
 
         let device_info = vk::DeviceCreateInfo::default()
             .enabled_layer_names(required_layer_names)
-            .enabled_extension_names(&required_extension_names)
+            .enabled_extension_names(&extension_names)
             .queue_create_infos(&queue_infos)
             .push_next(&mut device_features2);
         let device = unsafe { instance.create_device(physical_device, &device_info, None)? };
 
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let mut descriptor_indexing_properties =
+            vk::PhysicalDeviceDescriptorIndexingProperties::default();
+        {
+            let mut properties2 = vk::PhysicalDeviceProperties2::default()
+                .push_next(&mut descriptor_indexing_properties);
+            unsafe { instance.get_physical_device_properties2(physical_device, &mut properties2) };
+        }
+        // Chain pointer would dangle once the properties are moved.
+        descriptor_indexing_properties.p_next = std::ptr::null_mut();
+        let timestamp_valid_bits = unsafe {
+            instance.get_physical_device_queue_family_properties(physical_device)
+                [queue_topology.graphics.family_index as usize]
+                .timestamp_valid_bits
+        };
+
         Self::print_info(
             &device_name,
-            queue_family_index,
+            &queue_topology,
             required_layer_names,
-            &required_extension_names,
-            &feature_names,
+            &enabled_features,
         );
 
         debug!("Picking Queues of Device.");
 
-        let graphics_queue =
-            unsafe { device.get_device_queue(queue_family_index, Default::default()) };
-        let transfer_queue =
-            unsafe { device.get_device_queue(queue_family_index, Default::default()) };
+        let get_queue = |queue: QueueLocation| unsafe {
+            device.get_device_queue(queue.family_index, queue.queue_index)
+        };
+        let graphics_queue = get_queue(queue_topology.graphics);
+        let compute_queue = get_queue(queue_topology.compute);
+        let transfer_queue = get_queue(queue_topology.transfer);
 
         Ok(Self {
-            _physical_device: physical_device,
+            physical_device,
             device_properties,
+            queue_topology,
             device,
-            surface_format,
+            surface_formats,
             present_modes,
+            memory_properties,
+            enabled_features,
+            is_bindless_enabled: is_bindless,
+            timestamp_valid_bits,
+            descriptor_indexing_properties,
             graphics_queue,
+            compute_queue,
             transfer_queue,
+            #[cfg(feature = "dev")]
+            debug_utils_loader: None,
         })
     }
 
+    /// #### Description
+    /// Enables object names and labels, they show up in validation messages and in captures.
+    #[cfg(feature = "dev")]
+    pub fn enable_debug_names(&mut self, debug_utils_loader: ash::extensions::ext::DebugUtils) {
+        self.debug_utils_loader = Some(debug_utils_loader);
+
+        self.set_object_name(self.graphics_queue, "Graphics Queue");
+        if self.compute_queue != self.graphics_queue {
+            self.set_object_name(self.compute_queue, "Compute Queue");
+        }
+        if self.transfer_queue != self.graphics_queue && self.transfer_queue != self.compute_queue {
+            self.set_object_name(self.transfer_queue, "Transfer Queue");
+        }
+    }
+
+    /// #### Description
+    /// Names the object for validation messages and captures, does nothing without the `dev` feature.
     #[inline(always)]
-    pub fn create_shader_module(
-        &self,
-        spirv_binary: &shaderc::CompilationArtifact,
-    ) -> EngineResult<vk::ShaderModule> {
-        let spirv_binary = spirv_binary.as_binary();
+    pub fn set_object_name<H: vk::Handle>(&self, handle: H, name: &str) {
+        #[cfg(feature = "dev")]
+        {
+            let Some(debug_utils_loader) = &self.debug_utils_loader else {
+                return;
+            };
+            let Ok(name) = std::ffi::CString::new(name) else {
+                return;
+            };
+
+            let name_info = vk::DebugUtilsObjectNameInfoEXT {
+                object_type: H::TYPE,
+                object_handle: handle.as_raw(),
+                p_object_name: name.as_ptr(),
+                ..Default::default()
+            };
+            // Names are only a debugging aid, so failure is ignored.
+            let _ = unsafe {
+                debug_utils_loader.set_debug_utils_object_name(self.device.handle(), &name_info)
+            };
+        }
+
+        #[cfg(not(feature = "dev"))]
+        let _ = (handle, name);
+    }
+
+    /// #### Description
+    /// Opens a labeled region of commands, does nothing without the `dev` feature.
+    #[inline(always)]
+    pub fn cmd_begin_label(&self, command_buffer: vk::CommandBuffer, name: &str) {
+        #[cfg(feature = "dev")]
+        {
+            let Some(debug_utils_loader) = &self.debug_utils_loader else {
+                return;
+            };
+            // The label must be opened anyway, otherwise `cmd_end_label` would be unbalanced.
+            let name = std::ffi::CString::new(name).unwrap_or_default();
+
+            let label = vk::DebugUtilsLabelEXT::default().label_name(&name);
+            unsafe { debug_utils_loader.cmd_begin_debug_utils_label(command_buffer, &label) };
+        }
+
+        #[cfg(not(feature = "dev"))]
+        let _ = (command_buffer, name);
+    }
+
+    /// #### Description
+    /// Closes the region opened by `cmd_begin_label`.
+    #[inline(always)]
+    pub fn cmd_end_label(&self, command_buffer: vk::CommandBuffer) {
+        #[cfg(feature = "dev")]
+        if let Some(debug_utils_loader) = &self.debug_utils_loader {
+            unsafe { debug_utils_loader.cmd_end_debug_utils_label(command_buffer) };
+        }
+
+        #[cfg(not(feature = "dev"))]
+        let _ = command_buffer;
+    }
+
+    #[inline(always)]
+    pub fn create_shader_module(&self, spirv_binary: &[u32]) -> EngineResult<vk::ShaderModule> {
         let shader_module_info = vk::ShaderModuleCreateInfo::default().code(spirv_binary);
 
         unsafe {
@@ -290,6 +562,380 @@ impl DeviceManager {
         unsafe { Ok(self.device.create_fence(fence_info, None)?) }
     }
 
+    #[inline(always)]
+    pub fn queue(&self, queue_kind: QueueKind) -> vk::Queue {
+        match queue_kind {
+            QueueKind::Graphics => self.graphics_queue,
+            QueueKind::Compute => self.compute_queue,
+            QueueKind::Transfer => self.transfer_queue,
+        }
+    }
+
+    #[inline(always)]
+    pub fn queue_family_index(&self, queue_kind: QueueKind) -> u32 {
+        self.queue_topology.queue(queue_kind).family_index
+    }
+
+    /// #### Description
+    /// Families which resources accessed from several queues are created for,
+    /// more than one family means the resources use `CONCURRENT` sharing.
+    #[inline(always)]
+    pub fn concurrent_family_indices(&self) -> SmallVec<[u32; 3]> {
+        self.queue_topology.family_indices()
+    }
+
+    /// #### Description
+    /// Raw handle of the Device, intended only for recording of commands.
+    #[inline(always)]
+    pub fn raw(&self) -> &ash::Device {
+        &self.device
+    }
+
+    #[inline(always)]
+    pub fn create_command_pool(
+        &self,
+        command_pool_info: &vk::CommandPoolCreateInfo,
+    ) -> EngineResult<vk::CommandPool> {
+        unsafe { Ok(self.device.create_command_pool(command_pool_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_command_pool(&self, command_pool: vk::CommandPool) {
+        unsafe { self.device.destroy_command_pool(command_pool, None) }
+    }
+
+    #[inline(always)]
+    pub fn reset_command_pool(&self, command_pool: vk::CommandPool) -> EngineResult<()> {
+        unsafe {
+            Ok(self
+                .device
+                .reset_command_pool(command_pool, vk::CommandPoolResetFlags::empty())?)
+        }
+    }
+
+    #[inline(always)]
+    pub fn allocate_command_buffers(
+        &self,
+        command_buffer_info: &vk::CommandBufferAllocateInfo,
+    ) -> EngineResult<Vec<vk::CommandBuffer>> {
+        unsafe { Ok(self.device.allocate_command_buffers(command_buffer_info)?) }
+    }
+
+    #[inline(always)]
+    pub fn create_swapchain_loader(&self, instance: &ash::Instance) -> Swapchain {
+        Swapchain::new(instance, &self.device)
+    }
+
+    #[inline(always)]
+    pub fn create_image_view(
+        &self,
+        image_view_info: &vk::ImageViewCreateInfo,
+    ) -> EngineResult<vk::ImageView> {
+        unsafe { Ok(self.device.create_image_view(image_view_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_image_view(&self, image_view: vk::ImageView) {
+        unsafe { self.device.destroy_image_view(image_view, None) }
+    }
+
+    #[inline(always)]
+    pub fn create_sampler(
+        &self,
+        sampler_info: &vk::SamplerCreateInfo,
+    ) -> EngineResult<vk::Sampler> {
+        unsafe { Ok(self.device.create_sampler(sampler_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_sampler(&self, sampler: vk::Sampler) {
+        unsafe { self.device.destroy_sampler(sampler, None) }
+    }
+
+    #[inline(always)]
+    pub fn create_descriptor_set_layout(
+        &self,
+        layout_info: &vk::DescriptorSetLayoutCreateInfo,
+    ) -> EngineResult<vk::DescriptorSetLayout> {
+        unsafe {
+            Ok(self
+                .device
+                .create_descriptor_set_layout(layout_info, None)?)
+        }
+    }
+
+    #[inline(always)]
+    pub fn destroy_descriptor_set_layout(&self, layout: vk::DescriptorSetLayout) {
+        unsafe { self.device.destroy_descriptor_set_layout(layout, None) }
+    }
+
+    #[inline(always)]
+    pub fn create_descriptor_pool(
+        &self,
+        pool_info: &vk::DescriptorPoolCreateInfo,
+    ) -> EngineResult<vk::DescriptorPool> {
+        unsafe { Ok(self.device.create_descriptor_pool(pool_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_descriptor_pool(&self, pool: vk::DescriptorPool) {
+        unsafe { self.device.destroy_descriptor_pool(pool, None) }
+    }
+
+    #[inline(always)]
+    pub fn reset_descriptor_pool(&self, pool: vk::DescriptorPool) -> EngineResult<()> {
+        unsafe {
+            Ok(self
+                .device
+                .reset_descriptor_pool(pool, vk::DescriptorPoolResetFlags::empty())?)
+        }
+    }
+
+    #[inline(always)]
+    pub fn allocate_descriptor_sets(
+        &self,
+        set_info: &vk::DescriptorSetAllocateInfo,
+    ) -> EngineResult<Vec<vk::DescriptorSet>> {
+        unsafe { Ok(self.device.allocate_descriptor_sets(set_info)?) }
+    }
+
+    #[inline(always)]
+    pub fn update_descriptor_sets(&self, writes: &[vk::WriteDescriptorSet]) {
+        unsafe { self.device.update_descriptor_sets(writes, &[]) }
+    }
+
+    #[inline(always)]
+    pub fn create_pipeline_cache(
+        &self,
+        cache_info: &vk::PipelineCacheCreateInfo,
+    ) -> EngineResult<vk::PipelineCache> {
+        unsafe { Ok(self.device.create_pipeline_cache(cache_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_pipeline_cache(&self, pipeline_cache: vk::PipelineCache) {
+        unsafe { self.device.destroy_pipeline_cache(pipeline_cache, None) }
+    }
+
+    #[inline(always)]
+    pub fn get_pipeline_cache_data(
+        &self,
+        pipeline_cache: vk::PipelineCache,
+    ) -> EngineResult<Vec<u8>> {
+        unsafe { Ok(self.device.get_pipeline_cache_data(pipeline_cache)?) }
+    }
+
+    #[inline(always)]
+    pub fn create_pipeline_layout(
+        &self,
+        layout_info: &vk::PipelineLayoutCreateInfo,
+    ) -> EngineResult<vk::PipelineLayout> {
+        unsafe { Ok(self.device.create_pipeline_layout(layout_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_pipeline_layout(&self, layout: vk::PipelineLayout) {
+        unsafe { self.device.destroy_pipeline_layout(layout, None) }
+    }
+
+    #[inline(always)]
+    pub fn create_graphics_pipeline(
+        &self,
+        pipeline_cache: vk::PipelineCache,
+        pipeline_info: &vk::GraphicsPipelineCreateInfo,
+    ) -> EngineResult<vk::Pipeline> {
+        unsafe {
+            self.device
+                .create_graphics_pipelines(
+                    pipeline_cache,
+                    std::slice::from_ref(pipeline_info),
+                    None,
+                )
+                .map(|pipelines| pipelines[0])
+                .map_err(|(_, e)| e.into())
+        }
+    }
+
+    #[inline(always)]
+    pub fn create_compute_pipeline(
+        &self,
+        pipeline_cache: vk::PipelineCache,
+        pipeline_info: &vk::ComputePipelineCreateInfo,
+    ) -> EngineResult<vk::Pipeline> {
+        unsafe {
+            self.device
+                .create_compute_pipelines(pipeline_cache, std::slice::from_ref(pipeline_info), None)
+                .map(|pipelines| pipelines[0])
+                .map_err(|(_, e)| e.into())
+        }
+    }
+
+    #[inline(always)]
+    pub fn destroy_pipeline(&self, pipeline: vk::Pipeline) {
+        unsafe { self.device.destroy_pipeline(pipeline, None) }
+    }
+
+    #[inline(always)]
+    pub fn create_image(&self, image_info: &vk::ImageCreateInfo) -> EngineResult<vk::Image> {
+        unsafe { Ok(self.device.create_image(image_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_image(&self, image: vk::Image) {
+        unsafe { self.device.destroy_image(image, None) }
+    }
+
+    #[inline(always)]
+    pub fn get_image_memory_requirements(&self, image: vk::Image) -> vk::MemoryRequirements {
+        unsafe { self.device.get_image_memory_requirements(image) }
+    }
+
+    #[inline(always)]
+    pub fn bind_image_memory(
+        &self,
+        image: vk::Image,
+        memory: vk::DeviceMemory,
+        offset: vk::DeviceSize,
+    ) -> EngineResult<()> {
+        unsafe { Ok(self.device.bind_image_memory(image, memory, offset)?) }
+    }
+
+    #[inline(always)]
+    pub fn allocate_memory(
+        &self,
+        memory_info: &vk::MemoryAllocateInfo,
+    ) -> EngineResult<vk::DeviceMemory> {
+        unsafe { Ok(self.device.allocate_memory(memory_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn free_memory(&self, memory: vk::DeviceMemory) {
+        unsafe { self.device.free_memory(memory, None) }
+    }
+
+    #[inline(always)]
+    pub fn create_buffer(&self, buffer_info: &vk::BufferCreateInfo) -> EngineResult<vk::Buffer> {
+        unsafe { Ok(self.device.create_buffer(buffer_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_buffer(&self, buffer: vk::Buffer) {
+        unsafe { self.device.destroy_buffer(buffer, None) }
+    }
+
+    #[inline(always)]
+    pub fn get_buffer_memory_requirements(&self, buffer: vk::Buffer) -> vk::MemoryRequirements {
+        unsafe { self.device.get_buffer_memory_requirements(buffer) }
+    }
+
+    #[inline(always)]
+    pub fn bind_buffer_memory(
+        &self,
+        buffer: vk::Buffer,
+        memory: vk::DeviceMemory,
+        offset: vk::DeviceSize,
+    ) -> EngineResult<()> {
+        unsafe { Ok(self.device.bind_buffer_memory(buffer, memory, offset)?) }
+    }
+
+    #[inline(always)]
+    pub fn map_memory(
+        &self,
+        memory: vk::DeviceMemory,
+        offset: vk::DeviceSize,
+        size: vk::DeviceSize,
+    ) -> EngineResult<*mut std::ffi::c_void> {
+        unsafe {
+            Ok(self
+                .device
+                .map_memory(memory, offset, size, vk::MemoryMapFlags::empty())?)
+        }
+    }
+
+    #[inline(always)]
+    pub fn unmap_memory(&self, memory: vk::DeviceMemory) {
+        unsafe { self.device.unmap_memory(memory) }
+    }
+
+    /// #### Description
+    /// Finds the first memory type allowed by `type_bits` which has all the `required_flags`.
+    pub fn find_memory_type_index(
+        &self,
+        type_bits: u32,
+        required_flags: vk::MemoryPropertyFlags,
+    ) -> Option<u32> {
+        self.memory_properties.memory_types[..self.memory_properties.memory_type_count as usize]
+            .iter()
+            .enumerate()
+            .find(|(i, memory_type)| {
+                (type_bits & (1 << i)) != Default::default()
+                    && memory_type.property_flags.contains(required_flags)
+            })
+            .map(|(i, _)| i as u32)
+    }
+
+    #[inline(always)]
+    pub fn create_query_pool(
+        &self,
+        query_pool_info: &vk::QueryPoolCreateInfo,
+    ) -> EngineResult<vk::QueryPool> {
+        unsafe { Ok(self.device.create_query_pool(query_pool_info, None)?) }
+    }
+
+    #[inline(always)]
+    pub fn destroy_query_pool(&self, query_pool: vk::QueryPool) {
+        unsafe { self.device.destroy_query_pool(query_pool, None) }
+    }
+
+    /// #### Description
+    /// Resets queries from the host, requires `DeviceFeature::HostQueryReset`.
+    #[inline(always)]
+    pub fn reset_query_pool(&self, query_pool: vk::QueryPool, first_query: u32, query_count: u32) {
+        unsafe {
+            self.device
+                .reset_query_pool(query_pool, first_query, query_count)
+        }
+    }
+
+    /// #### Description
+    /// Results with availability, `vk::Result::NOT_READY` is returned if any query isn't available yet.
+    #[inline(always)]
+    pub fn get_query_pool_results(
+        &self,
+        query_pool: vk::QueryPool,
+        first_query: u32,
+        results: &mut [[u64; 2]],
+    ) -> EngineResult<()> {
+        unsafe {
+            Ok(self.device.get_query_pool_results(
+                query_pool,
+                first_query,
+                results,
+                vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WITH_AVAILABILITY,
+            )?)
+        }
+    }
+
+    #[inline(always)]
+    pub fn wait_for_fences(&self, fences: &[vk::Fence], timeout: u64) -> EngineResult<()> {
+        unsafe { Ok(self.device.wait_for_fences(fences, true, timeout)?) }
+    }
+
+    #[inline(always)]
+    pub fn reset_fences(&self, fences: &[vk::Fence]) -> EngineResult<()> {
+        unsafe { Ok(self.device.reset_fences(fences)?) }
+    }
+
+    #[inline(always)]
+    pub fn queue_submit(
+        &self,
+        queue: vk::Queue,
+        submit_infos: &[vk::SubmitInfo],
+        fence: vk::Fence,
+    ) -> EngineResult<()> {
+        unsafe { Ok(self.device.queue_submit(queue, submit_infos, fence)?) }
+    }
+
     #[inline(always)]
     pub fn wait_for_idle(&self) -> EngineResult<()> {
         unsafe { Ok(self.device.device_wait_idle()?) }
@@ -300,12 +946,274 @@ impl DeviceManager {
         unsafe { self.device.destroy_device(None) }
     }
 
+    /// #### Description
+    /// What the engine itself needs from the Device, merged with the requirements of the application.
+    fn engine_requirements(has_surface: bool, is_bindless: bool) -> DeviceRequirements {
+        let mut requirements = DeviceRequirements::default()
+            .with_required_features(&[
+                DeviceFeature::DynamicRendering,
+                DeviceFeature::Synchronization2,
+            ])
+            .with_optional_features(&[
+                DeviceFeature::SamplerAnisotropy,
+                DeviceFeature::HostQueryReset,
+            ]);
+        if has_surface {
+            requirements = requirements.with_required_extension(Swapchain::name());
+        }
+        if is_bindless {
+            requirements = requirements.with_required_features(DeviceFeature::BINDLESS);
+        }
+
+        requirements
+    }
+
+    /// #### Description
+    /// Runs every suitability check on the device, the first failed check is kept as the rejection reason.
+    fn check_device(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        index: usize,
+        surface_handle: Option<&super::SurfaceManager>,
+        requirements: &DeviceRequirements,
+    ) -> DeviceCandidate {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let name = to_cstr(properties.device_name.as_ptr())
+            .to_string_lossy()
+            .into_owned();
+
+        debug!(std::format!(
+            "Checking for compatibility GPU [{index}]: {name}."
+        ));
+
+        let suitability = Self::check_suitability(
+            instance,
+            physical_device,
+            &properties,
+            &name,
+            surface_handle,
+            requirements,
+        );
+        match &suitability {
+            Ok(suitable_device) => debug!(std::format!(
+                "GPU [{index}] {name} is suitable with score {}.",
+                suitable_device.score
+            )),
+            Err(reason) => debug!(std::format!("GPU [{index}] {name} is rejected: {reason}.")),
+        }
+
+        DeviceCandidate {
+            index,
+            physical_device,
+            properties,
+            name,
+            suitability,
+        }
+    }
+
+    fn check_suitability(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        properties: &vk::PhysicalDeviceProperties,
+        device_name: &str,
+        surface_handle: Option<&super::SurfaceManager>,
+        requirements: &DeviceRequirements,
+    ) -> Result<SuitableDevice, String> {
+        debug!("Checking for API Version requirement.");
+
+        // Dynamic rendering and synchronization2 are used as core features of Vulkan 1.3.
+        if properties.api_version < vk::API_VERSION_1_3 {
+            return Err(std::format!(
+                "Vulkan 1.3 is required, the Device supports {}.{}",
+                vk::api_version_major(properties.api_version),
+                vk::api_version_minor(properties.api_version)
+            ));
+        }
+
+        debug!("Checking for Queue Families requirements.");
+
+        let queue_family_properties =
+            unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+        let queue_topology =
+            QueueTopology::discover(
+                &queue_family_properties,
+                |family_index| match surface_handle {
+                    Some(surface_handle) => unsafe {
+                        surface_handle
+                            .surface_loader
+                            .get_physical_device_surface_support(
+                                physical_device,
+                                family_index,
+                                surface_handle.surface,
+                            )
+                            .unwrap()
+                    },
+                    None => true,
+                },
+            )
+            .ok_or("Unable to find required Queues")?;
+
+        let (surface_formats, present_modes) = match surface_handle {
+            Some(surface_handle) => {
+                let surface = surface_handle.surface;
+                let surface_loader = &surface_handle.surface_loader;
+
+                debug!("Checking for Surface Formats requirement.");
+
+                // Any format is fine, the swapchain falls back to what the surface supports.
+                let surface_formats = unsafe {
+                    surface_loader
+                        .get_physical_device_surface_formats(physical_device, surface)
+                        .unwrap()
+                };
+                if surface_formats.is_empty() {
+                    return Err("Unable to find any Surface Format".to_owned());
+                }
+
+                // FIFO is supported by every surface, vsync modes fall back to it.
+                let present_modes = unsafe {
+                    surface_loader
+                        .get_physical_device_surface_present_modes(physical_device, surface)
+                        .unwrap()
+                };
+
+                (Some(surface_formats), Some(present_modes))
+            }
+            None => (None, None),
+        };
+
+        debug!("Checking for Device Layers requirement.");
+
+        #[cfg(feature = "dev")]
+        {
+            let available_layers = unsafe {
+                instance
+                    .enumerate_device_layer_properties(physical_device)
+                    .unwrap()
+            };
+            let does_support = super::Context::does_support_layers(
+                super::Context::REQUIRED_LAYERS,
+                &available_layers,
+                &std::format!("Failed to find required Layers of Device - {device_name}",),
+            );
+            if !does_support {
+                return Err("Unable to find required Layers".to_owned());
+            }
+        }
+        #[cfg(not(feature = "dev"))]
+        let _ = device_name;
+
+        debug!("Checking for Device Extensions and Features requirements.");
+
+        let available_extensions = unsafe {
+            instance
+                .enumerate_device_extension_properties(physical_device)
+                .unwrap()
+        };
+        let supported_features =
+            FeatureSet::supported(instance, physical_device, properties.api_version);
+        let enabled_features = requirements
+            .negotiate(&supported_features, &available_extensions)
+            .map_err(|missing| {
+                std::format!(
+                    "Unable to find required Extensions and Features ({})",
+                    missing.join(", ")
+                )
+            })?;
+
+        let optional_features = requirements
+            .optional_extensions
+            .iter()
+            .map(|&extension| enabled_features.is_extension_enabled(extension))
+            .chain(
+                requirements
+                    .optional_features
+                    .iter()
+                    .map(|&feature| enabled_features.is_enabled(feature)),
+            )
+            .chain(std::iter::once(
+                queue_family_properties[queue_topology.graphics.family_index as usize]
+                    .timestamp_valid_bits
+                    > 0,
+            ))
+            .collect::<Vec<_>>();
+        let memory_properties =
+            unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let score = Self::score(properties, &memory_properties, &optional_features);
+
+        Ok(SuitableDevice {
+            queue_topology,
+            surface_formats,
+            present_modes,
+            enabled_features,
+            score,
+        })
+    }
+
+    /// #### Description
+    /// Default preference among suitable devices: device type dominates,
+    /// then device local memory, supported optional features and API version.
+    fn score(
+        properties: &vk::PhysicalDeviceProperties,
+        memory_properties: &vk::PhysicalDeviceMemoryProperties,
+        optional_features: &[bool],
+    ) -> u64 {
+        const GIB: u64 = 1024 * 1024 * 1024;
+
+        let device_type_score = match properties.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => 10_000,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => 5_000,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => 1_000,
+            _ => Default::default(),
+        };
+        let device_local_memory = memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .max()
+            .unwrap_or_default();
+        let memory_score = (device_local_memory / GIB).min(64) * 100;
+        let features_score = optional_features
+            .iter()
+            .filter(|&&is_supported| is_supported)
+            .count() as u64
+            * 200;
+        let api_version_score = vk::api_version_minor(properties.api_version) as u64 * 250;
+
+        device_type_score + memory_score + features_score + api_version_score
+    }
+
+    fn rejection_report(selection: &DeviceSelection, candidates: &[DeviceCandidate]) -> String {
+        let mut report =
+            std::format!("Unable to find suitable Device for selection {selection:?}.");
+        if candidates.is_empty() {
+            report.push_str(" No Devices with Vulkan support were found.");
+        }
+
+        for candidate in candidates {
+            let reason = match &candidate.suitability {
+                Err(reason) => reason.as_str(),
+                Ok(_) => "doesn't match the selection",
+            };
+
+            report.push_str(&std::format!(
+                "\n{TAB_IN_SPACES}- [{}] {} (vendor 0x{:04x}, device 0x{:04x}): {reason}",
+                candidate.index,
+                candidate.name,
+                candidate.properties.vendor_id,
+                candidate.properties.device_id,
+            ));
+        }
+
+        report
+    }
+
     fn print_info(
         device_name: &str,
-        queue_family_index: u32,
+        queue_topology: &QueueTopology,
         layer_names: &[*const c_char],
-        extension_names: &[*const c_char],
-        features_names: &[&std::ffi::CStr],
+        enabled_features: &EnabledFeatures,
     ) {
         let mut device_info = String::from("Created a Device.\n\n");
 
@@ -314,11 +1222,23 @@ impl DeviceManager {
         let device_name = std::format!("{TAB_IN_SPACES}- Device Name: {device_name}\n");
         device_info.push_str(&device_name);
 
-        device_info.push_str(&device_name);
-
-        let queue_info =
-            std::format!("{TAB_IN_SPACES}- Using Queue Family Index: {queue_family_index}\n\n");
-        device_info.push_str(&queue_info);
+        for (queue_kind, queue) in [
+            (QueueKind::Graphics, queue_topology.graphics),
+            (QueueKind::Compute, queue_topology.compute),
+            (QueueKind::Transfer, queue_topology.transfer),
+        ] {
+            let dedicated = if queue_topology.is_dedicated(queue_kind) {
+                ", dedicated family"
+            } else {
+                ""
+            };
+            device_info.push_str(&std::format!(
+                "{TAB_IN_SPACES}- {queue_kind:?} Queue: family {}, index {}{dedicated}\n",
+                queue.family_index,
+                queue.queue_index
+            ));
+        }
+        device_info.push('\n');
 
         #[cfg(feature = "dev")]
         {
@@ -332,21 +1252,160 @@ impl DeviceManager {
         }
 
         device_info.push_str(&std::format!("\n{TAB_IN_SPACES}With Extensions:\n"));
-        extension_names.iter().for_each(|extension_name| {
-            device_info.push_str(&std::format!(
-                "{TAB_IN_SPACES}- {}\n",
-                to_cstr(*extension_name).to_str().unwrap()
-            ));
-        });
+        enabled_features
+            .extensions()
+            .iter()
+            .for_each(|extension_name| {
+                device_info.push_str(&std::format!(
+                    "{TAB_IN_SPACES}- {}\n",
+                    extension_name.to_str().unwrap()
+                ));
+            });
 
         device_info.push_str(&std::format!("\n{TAB_IN_SPACES}With Features:\n"));
-        features_names.iter().for_each(|feature_name| {
-            device_info.push_str(&std::format!(
-                "{TAB_IN_SPACES}- {}\n",
-                feature_name.to_str().unwrap()
-            ));
+        enabled_features.features().iter().for_each(|feature| {
+            device_info.push_str(&std::format!("{TAB_IN_SPACES}- {feature:?}\n"));
         });
 
         debug!(device_info);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    fn family(queue_flags: vk::QueueFlags, queue_count: u32) -> vk::QueueFamilyProperties {
+        vk::QueueFamilyProperties {
+            queue_flags,
+            queue_count,
+            ..Default::default()
+        }
+    }
+
+    fn location(family_index: u32, queue_index: u32) -> QueueLocation {
+        QueueLocation {
+            family_index,
+            queue_index,
+        }
+    }
+
+    const UNIVERSAL: vk::QueueFlags = vk::QueueFlags::from_raw(
+        vk::QueueFlags::GRAPHICS.as_raw()
+            | vk::QueueFlags::COMPUTE.as_raw()
+            | vk::QueueFlags::TRANSFER.as_raw(),
+    );
+
+    #[test]
+    fn dedicated_families_are_preferred() {
+        let families = [
+            family(UNIVERSAL, 16),
+            family(vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER, 8),
+            family(vk::QueueFlags::TRANSFER, 2),
+        ];
+
+        let topology = QueueTopology::discover(&families, |_| true).unwrap();
+
+        assert_eq!(topology.graphics, location(0, 0));
+        assert_eq!(topology.compute, location(1, 0));
+        assert_eq!(topology.transfer, location(2, 0));
+        assert!(topology.is_dedicated(QueueKind::Compute));
+        assert_eq!(topology.family_indices().as_slice(), &[0, 1, 2]);
+    }
+
+    #[test]
+    fn missing_families_fall_back_to_next_graphics_queue() {
+        let families = [family(UNIVERSAL, 2)];
+
+        let topology = QueueTopology::discover(&families, |_| true).unwrap();
+
+        assert_eq!(topology.graphics, location(0, 0));
+        assert_eq!(topology.compute, location(0, 1));
+        // Queues ran out, so transfer shares the last one.
+        assert_eq!(topology.transfer, location(0, 1));
+        assert!(!topology.is_dedicated(QueueKind::Compute));
+        assert!(topology.is_separate(QueueKind::Compute));
+        assert_eq!(topology.family_indices().as_slice(), &[0]);
+    }
+
+    #[test]
+    fn single_queue_is_shared_by_every_kind() {
+        let families = [family(UNIVERSAL, 1)];
+
+        let topology = QueueTopology::discover(&families, |_| true).unwrap();
+
+        assert_eq!(topology.compute, topology.graphics);
+        assert_eq!(topology.transfer, topology.graphics);
+        assert!(!topology.is_separate(QueueKind::Compute));
+        assert!(!topology.is_separate(QueueKind::Transfer));
+    }
+
+    #[test]
+    fn graphics_family_must_support_present_and_have_queues() {
+        let families = [
+            family(UNIVERSAL, 0),
+            family(UNIVERSAL, 1),
+            family(UNIVERSAL, 1),
+        ];
+
+        let topology =
+            QueueTopology::discover(&families, |family_index| family_index != 1).unwrap();
+
+        assert_eq!(topology.graphics, location(2, 0));
+        assert_eq!(topology.compute, location(2, 0));
+    }
+
+    #[test]
+    fn discovery_fails_without_graphics_family() {
+        let families = [family(
+            vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER,
+            4,
+        )];
+
+        assert_eq!(QueueTopology::discover(&families, |_| true), None);
+        assert_eq!(
+            QueueTopology::discover(&[family(UNIVERSAL, 1)], |_| false),
+            None
+        );
+    }
+
+    #[test]
+    fn device_selection_is_parsed_from_every_form() {
+        assert_eq!(
+            DeviceSelection::from_str(" AUTO "),
+            Ok(DeviceSelection::Auto)
+        );
+        assert_eq!(
+            DeviceSelection::from_str("2"),
+            Ok(DeviceSelection::Index(2))
+        );
+        assert_eq!(
+            DeviceSelection::from_str("index:3"),
+            Ok(DeviceSelection::Index(3))
+        );
+        assert_eq!(
+            DeviceSelection::from_str("name:Radeon RX"),
+            Ok(DeviceSelection::Name("Radeon RX".to_owned()))
+        );
+        assert_eq!(
+            DeviceSelection::from_str("GeForce"),
+            Ok(DeviceSelection::Name("GeForce".to_owned()))
+        );
+        assert_eq!(
+            DeviceSelection::from_str("id:0x10de:2684"),
+            Ok(DeviceSelection::Id {
+                vendor_id: 0x10de,
+                device_id: 0x2684,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_device_selection_is_rejected() {
+        assert!(DeviceSelection::from_str("index:first").is_err());
+        assert!(DeviceSelection::from_str("id:0x10de").is_err());
+        assert!(DeviceSelection::from_str("id:nvidia:0x2684").is_err());
+    }
+}
@@ -0,0 +1,422 @@
+use std::ffi::CStr;
+
+use ash::vk;
+
+/// Declares `DeviceFeature` with the field of the Vulkan feature struct behind every variant.
+macro_rules! device_features {
+    ($($feature:ident => $group:ident.$field:ident,)*) => {
+        /// #### Description
+        /// Feature bit of Vulkan 1.0 core, 1.1, 1.2 or 1.3 feature structs.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+        pub enum DeviceFeature {
+            $($feature,)*
+        }
+
+        impl DeviceFeature {
+            pub const ALL: &[Self] = &[$(Self::$feature,)*];
+
+            #[inline(always)]
+            fn is_set(self, feature_set: &FeatureSet) -> bool {
+                match self {
+                    $(Self::$feature => feature_set.$group.$field == vk::TRUE,)*
+                }
+            }
+
+            #[inline(always)]
+            fn set(self, feature_set: &mut FeatureSet) {
+                match self {
+                    $(Self::$feature => feature_set.$group.$field = vk::TRUE,)*
+                }
+            }
+        }
+    };
+}
+
+device_features! {
+    // Vulkan 1.0
+    SamplerAnisotropy => core.sampler_anisotropy,
+    FillModeNonSolid => core.fill_mode_non_solid,
+    WideLines => core.wide_lines,
+    DepthClamp => core.depth_clamp,
+    IndependentBlend => core.independent_blend,
+    GeometryShader => core.geometry_shader,
+    TessellationShader => core.tessellation_shader,
+    MultiDrawIndirect => core.multi_draw_indirect,
+    PipelineStatisticsQuery => core.pipeline_statistics_query,
+    TextureCompressionBc => core.texture_compression_bc,
+    ShaderInt64 => core.shader_int64,
+    ShaderFloat64 => core.shader_float64,
+    // Vulkan 1.1
+    StorageBuffer16BitAccess => vulkan11.storage_buffer16_bit_access,
+    Multiview => vulkan11.multiview,
+    VariablePointers => vulkan11.variable_pointers,
+    ShaderDrawParameters => vulkan11.shader_draw_parameters,
+    // Vulkan 1.2
+    DrawIndirectCount => vulkan12.draw_indirect_count,
+    ShaderFloat16 => vulkan12.shader_float16,
+    ShaderInt8 => vulkan12.shader_int8,
+    DescriptorIndexing => vulkan12.descriptor_indexing,
+    RuntimeDescriptorArray => vulkan12.runtime_descriptor_array,
+    DescriptorBindingPartiallyBound => vulkan12.descriptor_binding_partially_bound,
+    DescriptorBindingUpdateUnusedWhilePending => vulkan12.descriptor_binding_update_unused_while_pending,
+    DescriptorBindingSampledImageUpdateAfterBind => vulkan12.descriptor_binding_sampled_image_update_after_bind,
+    DescriptorBindingStorageImageUpdateAfterBind => vulkan12.descriptor_binding_storage_image_update_after_bind,
+    DescriptorBindingStorageBufferUpdateAfterBind => vulkan12.descriptor_binding_storage_buffer_update_after_bind,
+    ShaderSampledImageArrayNonUniformIndexing => vulkan12.shader_sampled_image_array_non_uniform_indexing,
+    ShaderStorageImageArrayNonUniformIndexing => vulkan12.shader_storage_image_array_non_uniform_indexing,
+    ShaderStorageBufferArrayNonUniformIndexing => vulkan12.shader_storage_buffer_array_non_uniform_indexing,
+    SamplerFilterMinmax => vulkan12.sampler_filter_minmax,
+    ScalarBlockLayout => vulkan12.scalar_block_layout,
+    HostQueryReset => vulkan12.host_query_reset,
+    TimelineSemaphore => vulkan12.timeline_semaphore,
+    BufferDeviceAddress => vulkan12.buffer_device_address,
+    VulkanMemoryModel => vulkan12.vulkan_memory_model,
+    // Vulkan 1.3
+    InlineUniformBlock => vulkan13.inline_uniform_block,
+    ShaderDemoteToHelperInvocation => vulkan13.shader_demote_to_helper_invocation,
+    SubgroupSizeControl => vulkan13.subgroup_size_control,
+    ComputeFullSubgroups => vulkan13.compute_full_subgroups,
+    Synchronization2 => vulkan13.synchronization2,
+    DynamicRendering => vulkan13.dynamic_rendering,
+    Maintenance4 => vulkan13.maintenance4,
+}
+
+impl DeviceFeature {
+    /// Descriptor indexing features needed for one global set of partially bound, update-after-bind arrays.
+    pub const BINDLESS: &[Self] = &[
+        Self::DescriptorIndexing,
+        Self::RuntimeDescriptorArray,
+        Self::DescriptorBindingPartiallyBound,
+        Self::DescriptorBindingUpdateUnusedWhilePending,
+        Self::DescriptorBindingSampledImageUpdateAfterBind,
+        Self::DescriptorBindingStorageImageUpdateAfterBind,
+        Self::DescriptorBindingStorageBufferUpdateAfterBind,
+        Self::ShaderSampledImageArrayNonUniformIndexing,
+        Self::ShaderStorageImageArrayNonUniformIndexing,
+        Self::ShaderStorageBufferArrayNonUniformIndexing,
+    ];
+}
+
+/// #### Description
+/// Feature structs of every Vulkan version, either supported by a device or to be enabled.
+#[derive(Default, Clone, Copy)]
+pub struct FeatureSet {
+    pub core: vk::PhysicalDeviceFeatures,
+    pub vulkan11: vk::PhysicalDeviceVulkan11Features<'static>,
+    pub vulkan12: vk::PhysicalDeviceVulkan12Features<'static>,
+    pub vulkan13: vk::PhysicalDeviceVulkan13Features<'static>,
+}
+
+impl FeatureSet {
+    /// #### Description
+    /// Features supported by the device. Structs of versions above `api_version` of the device
+    /// aren't chained, as the device doesn't know them, and stay empty.
+    pub fn supported(
+        instance: &ash::Instance,
+        physical_device: vk::PhysicalDevice,
+        api_version: u32,
+    ) -> Self {
+        let mut feature_set = Self::default();
+
+        // Vulkan 1.1 feature struct was introduced together with the 1.2 one.
+        let mut features2 = vk::PhysicalDeviceFeatures2::default();
+        if api_version >= vk::API_VERSION_1_2 {
+            features2 = features2
+                .push_next(&mut feature_set.vulkan11)
+                .push_next(&mut feature_set.vulkan12);
+        }
+        if api_version >= vk::API_VERSION_1_3 {
+            features2 = features2.push_next(&mut feature_set.vulkan13);
+        }
+        unsafe { instance.get_physical_device_features2(physical_device, &mut features2) };
+        let core = features2.features;
+
+        // Chain pointers would dangle once the set is moved.
+        feature_set.core = core;
+        feature_set.vulkan11.p_next = std::ptr::null_mut();
+        feature_set.vulkan12.p_next = std::ptr::null_mut();
+        feature_set.vulkan13.p_next = std::ptr::null_mut();
+
+        feature_set
+    }
+
+    pub fn with_features(features: &[DeviceFeature]) -> Self {
+        let mut feature_set = Self::default();
+        for feature in features {
+            feature.set(&mut feature_set);
+        }
+
+        feature_set
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, feature: DeviceFeature) -> bool {
+        feature.is_set(self)
+    }
+}
+
+/// #### Description
+/// Extensions and features the application needs from the Device.
+/// Devices without any required one are rejected, optional ones are enabled when supported.
+///
+/// #### NOTE
+/// Requirements of the engine itself (swapchain, dynamic rendering, synchronization2,
+/// descriptor indexing in bindless mode) are always added.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceRequirements {
+    pub required_extensions: Vec<&'static CStr>,
+    pub optional_extensions: Vec<&'static CStr>,
+    pub required_features: Vec<DeviceFeature>,
+    pub optional_features: Vec<DeviceFeature>,
+}
+
+impl DeviceRequirements {
+    #[inline(always)]
+    pub fn with_required_extension(mut self, extension: &'static CStr) -> Self {
+        self.required_extensions.push(extension);
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_optional_extension(mut self, extension: &'static CStr) -> Self {
+        self.optional_extensions.push(extension);
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_required_features(mut self, features: &[DeviceFeature]) -> Self {
+        self.required_features.extend_from_slice(features);
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_optional_features(mut self, features: &[DeviceFeature]) -> Self {
+        self.optional_features.extend_from_slice(features);
+
+        self
+    }
+
+    /// #### Description
+    /// Requirements of both, a requirement required by either of them stays required.
+    pub fn merge(mut self, other: &Self) -> Self {
+        for &extension in &other.required_extensions {
+            if !self.required_extensions.contains(&extension) {
+                self.required_extensions.push(extension);
+            }
+        }
+        for &extension in &other.optional_extensions {
+            if !self.optional_extensions.contains(&extension) {
+                self.optional_extensions.push(extension);
+            }
+        }
+        for &feature in &other.required_features {
+            if !self.required_features.contains(&feature) {
+                self.required_features.push(feature);
+            }
+        }
+        for &feature in &other.optional_features {
+            if !self.optional_features.contains(&feature) {
+                self.optional_features.push(feature);
+            }
+        }
+
+        self.optional_extensions
+            .retain(|extension| !self.required_extensions.contains(extension));
+        self.optional_features
+            .retain(|feature| !self.required_features.contains(feature));
+
+        self
+    }
+
+    /// #### Description
+    /// What would be enabled on a device with the given support,
+    /// or the names of missing required extensions and features.
+    pub fn negotiate(
+        &self,
+        supported_features: &FeatureSet,
+        available_extensions: &[vk::ExtensionProperties],
+    ) -> Result<EnabledFeatures, Vec<String>> {
+        let is_extension_available = |extension: &CStr| {
+            available_extensions.iter().any(|available_extension| {
+                let available_extension_name =
+                    unsafe { CStr::from_ptr(available_extension.extension_name.as_ptr()) };
+
+                available_extension_name == extension
+            })
+        };
+
+        let missing = self
+            .required_extensions
+            .iter()
+            .filter(|extension| !is_extension_available(extension))
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .chain(
+                self.required_features
+                    .iter()
+                    .filter(|&&feature| !supported_features.contains(feature))
+                    .map(|feature| std::format!("{feature:?}")),
+            )
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+
+        let extensions = self
+            .required_extensions
+            .iter()
+            .copied()
+            .chain(
+                self.optional_extensions
+                    .iter()
+                    .copied()
+                    .filter(|extension| is_extension_available(extension)),
+            )
+            .collect();
+        let features = self
+            .required_features
+            .iter()
+            .copied()
+            .chain(
+                self.optional_features
+                    .iter()
+                    .copied()
+                    .filter(|&feature| supported_features.contains(feature)),
+            )
+            .collect();
+
+        Ok(EnabledFeatures {
+            extensions,
+            features,
+        })
+    }
+}
+
+/// #### Description
+/// Extensions and features enabled on the Device, renderer code branches on them
+/// instead of assuming optional requirements are present.
+#[derive(Debug, Clone, Default)]
+pub struct EnabledFeatures {
+    extensions: Vec<&'static CStr>,
+    features: Vec<DeviceFeature>,
+}
+
+impl EnabledFeatures {
+    #[inline(always)]
+    pub fn is_enabled(&self, feature: DeviceFeature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    #[inline(always)]
+    pub fn is_extension_enabled(&self, extension: &CStr) -> bool {
+        self.extensions.contains(&extension)
+    }
+
+    #[inline(always)]
+    pub fn features(&self) -> &[DeviceFeature] {
+        &self.features
+    }
+
+    #[inline(always)]
+    pub fn extensions(&self) -> &[&'static CStr] {
+        &self.extensions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SWAPCHAIN: &CStr = c"VK_KHR_swapchain";
+    const PUSH_DESCRIPTOR: &CStr = c"VK_KHR_push_descriptor";
+    const MEMORY_BUDGET: &CStr = c"VK_EXT_memory_budget";
+
+    fn extension_properties(name: &CStr) -> vk::ExtensionProperties {
+        let mut properties = vk::ExtensionProperties::default();
+        for (dst, &src) in properties.extension_name.iter_mut().zip(name.to_bytes()) {
+            *dst = src as std::ffi::c_char;
+        }
+
+        properties
+    }
+
+    #[test]
+    fn merge_deduplicates_and_keeps_required_over_optional() {
+        let engine = DeviceRequirements::default()
+            .with_required_extension(SWAPCHAIN)
+            .with_required_features(&[DeviceFeature::DynamicRendering])
+            .with_optional_features(&[DeviceFeature::SamplerAnisotropy]);
+        let application = DeviceRequirements::default()
+            .with_required_extension(SWAPCHAIN)
+            .with_optional_extension(PUSH_DESCRIPTOR)
+            .with_required_features(&[DeviceFeature::SamplerAnisotropy])
+            .with_optional_features(&[DeviceFeature::DynamicRendering, DeviceFeature::WideLines]);
+
+        let merged = engine.merge(&application);
+
+        assert_eq!(merged.required_extensions, vec![SWAPCHAIN]);
+        assert_eq!(merged.optional_extensions, vec![PUSH_DESCRIPTOR]);
+        assert_eq!(
+            merged.required_features,
+            vec![
+                DeviceFeature::DynamicRendering,
+                DeviceFeature::SamplerAnisotropy
+            ]
+        );
+        assert_eq!(merged.optional_features, vec![DeviceFeature::WideLines]);
+    }
+
+    #[test]
+    fn negotiate_enables_only_supported_optional_requirements() {
+        let requirements = DeviceRequirements::default()
+            .with_required_extension(SWAPCHAIN)
+            .with_optional_extension(PUSH_DESCRIPTOR)
+            .with_optional_extension(MEMORY_BUDGET)
+            .with_required_features(&[DeviceFeature::Synchronization2])
+            .with_optional_features(&[DeviceFeature::WideLines, DeviceFeature::ShaderInt64]);
+        let supported_features = FeatureSet::with_features(&[
+            DeviceFeature::Synchronization2,
+            DeviceFeature::ShaderInt64,
+        ]);
+        let available_extensions = [
+            extension_properties(SWAPCHAIN),
+            extension_properties(MEMORY_BUDGET),
+        ];
+
+        let enabled_features = requirements
+            .negotiate(&supported_features, &available_extensions)
+            .unwrap();
+
+        assert_eq!(enabled_features.extensions(), &[SWAPCHAIN, MEMORY_BUDGET]);
+        assert_eq!(
+            enabled_features.features(),
+            &[DeviceFeature::Synchronization2, DeviceFeature::ShaderInt64]
+        );
+        assert!(!enabled_features.is_enabled(DeviceFeature::WideLines));
+        assert!(!enabled_features.is_extension_enabled(PUSH_DESCRIPTOR));
+    }
+
+    #[test]
+    fn negotiate_reports_every_missing_requirement() {
+        let requirements = DeviceRequirements::default()
+            .with_required_extension(SWAPCHAIN)
+            .with_required_extension(PUSH_DESCRIPTOR)
+            .with_required_features(&[DeviceFeature::DynamicRendering, DeviceFeature::WideLines]);
+        let supported_features = FeatureSet::with_features(&[DeviceFeature::DynamicRendering]);
+        let available_extensions = [extension_properties(SWAPCHAIN)];
+
+        let missing = requirements
+            .negotiate(&supported_features, &available_extensions)
+            .unwrap_err();
+
+        assert_eq!(
+            missing,
+            vec![
+                PUSH_DESCRIPTOR.to_string_lossy().into_owned(),
+                "WideLines".to_owned(),
+            ]
+        );
+    }
+}
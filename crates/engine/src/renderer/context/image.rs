@@ -0,0 +1,343 @@
+use crate::{common::Id, error::EngineResult};
+
+use super::allocator::{Allocation, AllocationKind, GpuAllocator, MemoryLocation};
+
+use ash::vk;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageKind {
+    Texture2D,
+    Texture2DArray { layers: u32 },
+    Cube,
+    Texture3D { depth: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageDesc {
+    pub kind: ImageKind,
+    pub format: vk::Format,
+    pub width: u32,
+    pub height: u32,
+    pub mip_levels: u32,
+    pub usage: vk::ImageUsageFlags,
+}
+
+impl ImageDesc {
+    const TEXTURE_USAGE: vk::ImageUsageFlags = vk::ImageUsageFlags::from_raw(
+        vk::ImageUsageFlags::SAMPLED.as_raw() | vk::ImageUsageFlags::TRANSFER_DST.as_raw(),
+    );
+
+    #[inline(always)]
+    pub fn texture_2d(format: vk::Format, width: u32, height: u32) -> Self {
+        Self::texture(ImageKind::Texture2D, format, width, height)
+    }
+
+    #[inline(always)]
+    pub fn texture_2d_array(format: vk::Format, width: u32, height: u32, layers: u32) -> Self {
+        Self::texture(ImageKind::Texture2DArray { layers }, format, width, height)
+    }
+
+    /// #### Description
+    /// Faces are expected in the order +X, -X, +Y, -Y, +Z, -Z.
+    #[inline(always)]
+    pub fn cube(format: vk::Format, size: u32) -> Self {
+        Self::texture(ImageKind::Cube, format, size, size)
+    }
+
+    #[inline(always)]
+    pub fn texture_3d(format: vk::Format, width: u32, height: u32, depth: u32) -> Self {
+        Self::texture(ImageKind::Texture3D { depth }, format, width, height)
+    }
+
+    /// #### Description
+    /// Image rendered into by the GPU, e.g. G-Buffer or depth buffer.
+    #[inline(always)]
+    pub fn attachment(format: vk::Format, width: u32, height: u32) -> Self {
+        let usage = if is_depth_format(format) {
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT
+        } else {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+        };
+
+        Self {
+            usage: usage | vk::ImageUsageFlags::SAMPLED,
+            ..Self::texture_2d(format, width, height)
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_mip_levels(self, mip_levels: u32) -> Self {
+        Self { mip_levels, ..self }
+    }
+
+    #[inline(always)]
+    pub fn with_usage(self, usage: vk::ImageUsageFlags) -> Self {
+        Self { usage, ..self }
+    }
+
+    #[inline(always)]
+    pub fn layer_count(&self) -> u32 {
+        match self.kind {
+            ImageKind::Texture2D | ImageKind::Texture3D { .. } => 1,
+            ImageKind::Texture2DArray { layers } => layers,
+            ImageKind::Cube => 6,
+        }
+    }
+
+    #[inline(always)]
+    pub fn extent(&self) -> vk::Extent3D {
+        let depth = match self.kind {
+            ImageKind::Texture3D { depth } => depth,
+            _ => 1,
+        };
+
+        vk::Extent3D {
+            width: self.width,
+            height: self.height,
+            depth,
+        }
+    }
+
+    #[inline(always)]
+    pub fn aspect_mask(&self) -> vk::ImageAspectFlags {
+        if is_depth_format(self.format) {
+            vk::ImageAspectFlags::DEPTH
+        } else {
+            vk::ImageAspectFlags::COLOR
+        }
+    }
+
+    #[inline(always)]
+    pub fn subresource_range(&self) -> vk::ImageSubresourceRange {
+        vk::ImageSubresourceRange::default()
+            .aspect_mask(self.aspect_mask())
+            .level_count(self.mip_levels)
+            .layer_count(self.layer_count())
+    }
+
+    /// #### Description
+    /// Bytes of the base mip level of every layer tightly packed, `None` for formats of unknown size.
+    pub fn base_level_size(&self) -> Option<vk::DeviceSize> {
+        let (block_size, block_width, block_height) = format_block(self.format)?;
+        let extent = self.extent();
+        let blocks_x = extent.width.div_ceil(block_width) as vk::DeviceSize;
+        let blocks_y = extent.height.div_ceil(block_height) as vk::DeviceSize;
+
+        Some(
+            blocks_x
+                * blocks_y
+                * extent.depth as vk::DeviceSize
+                * self.layer_count() as vk::DeviceSize
+                * block_size,
+        )
+    }
+
+    #[inline(always)]
+    fn texture(kind: ImageKind, format: vk::Format, width: u32, height: u32) -> Self {
+        Self {
+            kind,
+            format,
+            width,
+            height,
+            mip_levels: 1,
+            usage: Self::TEXTURE_USAGE,
+        }
+    }
+
+    #[inline(always)]
+    fn image_type(&self) -> vk::ImageType {
+        match self.kind {
+            ImageKind::Texture3D { .. } => vk::ImageType::TYPE_3D,
+            _ => vk::ImageType::TYPE_2D,
+        }
+    }
+
+    #[inline(always)]
+    fn view_type(&self) -> vk::ImageViewType {
+        match self.kind {
+            ImageKind::Texture2D => vk::ImageViewType::TYPE_2D,
+            ImageKind::Texture2DArray { .. } => vk::ImageViewType::TYPE_2D_ARRAY,
+            ImageKind::Cube => vk::ImageViewType::CUBE,
+            ImageKind::Texture3D { .. } => vk::ImageViewType::TYPE_3D,
+        }
+    }
+}
+
+/// #### Description
+/// Image with its default view. Textures are images created with sampled usage.
+pub struct Image {
+    pub id: Id,
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub desc: ImageDesc,
+    /// Layout the image is in after all submitted work, kept up to date by the engine.
+    pub layout: vk::ImageLayout,
+    /// Shared between queue families without ownership transfers.
+    pub is_concurrent: bool,
+    allocation: Allocation,
+}
+
+pub type Texture = Image;
+
+impl Image {
+    pub fn new(
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+        id: Id,
+        desc: ImageDesc,
+    ) -> EngineResult<Self> {
+        let flags = match desc.kind {
+            ImageKind::Cube => vk::ImageCreateFlags::CUBE_COMPATIBLE,
+            _ => vk::ImageCreateFlags::empty(),
+        };
+        // Concurrent sharing can disable compression, so only storage images written by
+        // compute jobs are shared, other images are transferred to the graphics queue.
+        let family_indices = device.concurrent_family_indices();
+        let is_concurrent =
+            family_indices.len() > 1 && desc.usage.contains(vk::ImageUsageFlags::STORAGE);
+        let sharing_mode = if is_concurrent {
+            vk::SharingMode::CONCURRENT
+        } else {
+            vk::SharingMode::EXCLUSIVE
+        };
+        let mut image_info = vk::ImageCreateInfo::default()
+            .flags(flags)
+            .image_type(desc.image_type())
+            .format(desc.format)
+            .extent(desc.extent())
+            .mip_levels(desc.mip_levels)
+            .array_layers(desc.layer_count())
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(desc.usage)
+            .sharing_mode(sharing_mode)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        if is_concurrent {
+            image_info = image_info.queue_family_indices(&family_indices);
+        }
+        let image = device.create_image(&image_info)?;
+
+        let memory_requirements = device.get_image_memory_requirements(image);
+        let allocation = match allocator.allocate(
+            device,
+            memory_requirements,
+            MemoryLocation::GpuOnly,
+            AllocationKind::Optimal,
+        ) {
+            Ok(allocation) => allocation,
+            Err(e) => {
+                device.destroy_image(image);
+
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = device.bind_image_memory(image, allocation.memory, allocation.offset) {
+            allocator.free(device, allocation);
+            device.destroy_image(image);
+
+            return Err(e);
+        }
+
+        let image_view_info = vk::ImageViewCreateInfo::default()
+            .image(image)
+            .view_type(desc.view_type())
+            .format(desc.format)
+            .subresource_range(desc.subresource_range());
+        let view = match device.create_image_view(&image_view_info) {
+            Ok(view) => view,
+            Err(e) => {
+                allocator.free(device, allocation);
+                device.destroy_image(image);
+
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            id,
+            image,
+            view,
+            desc,
+            layout: vk::ImageLayout::UNDEFINED,
+            is_concurrent,
+            allocation,
+        })
+    }
+
+    #[inline(always)]
+    pub fn destroy(self, device: &super::DeviceManager, allocator: &mut GpuAllocator) {
+        device.destroy_image_view(self.view);
+        device.destroy_image(self.image);
+        allocator.free(device, self.allocation);
+    }
+}
+
+#[inline(always)]
+pub fn is_depth_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::D16_UNORM
+            | vk::Format::X8_D24_UNORM_PACK32
+            | vk::Format::D32_SFLOAT
+            | vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+/// #### Description
+/// Bytes of one texel block and its width and height in texels, `1x1` for uncompressed formats.
+fn format_block(format: vk::Format) -> Option<(vk::DeviceSize, u32, u32)> {
+    let block = match format {
+        vk::Format::R8_UNORM | vk::Format::R8_SNORM | vk::Format::R8_UINT | vk::Format::R8_SRGB => {
+            (1, 1, 1)
+        }
+        vk::Format::R8G8_UNORM
+        | vk::Format::R8G8_SNORM
+        | vk::Format::R8G8_UINT
+        | vk::Format::R16_UNORM
+        | vk::Format::R16_UINT
+        | vk::Format::R16_SFLOAT
+        | vk::Format::D16_UNORM => (2, 1, 1),
+        vk::Format::R8G8B8A8_UNORM
+        | vk::Format::R8G8B8A8_SNORM
+        | vk::Format::R8G8B8A8_UINT
+        | vk::Format::R8G8B8A8_SRGB
+        | vk::Format::B8G8R8A8_UNORM
+        | vk::Format::B8G8R8A8_SRGB
+        | vk::Format::A2B10G10R10_UNORM_PACK32
+        | vk::Format::A2R10G10B10_UNORM_PACK32
+        | vk::Format::B10G11R11_UFLOAT_PACK32
+        | vk::Format::E5B9G9R9_UFLOAT_PACK32
+        | vk::Format::R16G16_UNORM
+        | vk::Format::R16G16_SFLOAT
+        | vk::Format::R32_UINT
+        | vk::Format::R32_SFLOAT
+        | vk::Format::D32_SFLOAT => (4, 1, 1),
+        vk::Format::R16G16B16A16_UNORM
+        | vk::Format::R16G16B16A16_SFLOAT
+        | vk::Format::R32G32_UINT
+        | vk::Format::R32G32_SFLOAT => (8, 1, 1),
+        vk::Format::R32G32B32A32_UINT | vk::Format::R32G32B32A32_SFLOAT => (16, 1, 1),
+        vk::Format::BC1_RGB_UNORM_BLOCK
+        | vk::Format::BC1_RGB_SRGB_BLOCK
+        | vk::Format::BC1_RGBA_UNORM_BLOCK
+        | vk::Format::BC1_RGBA_SRGB_BLOCK
+        | vk::Format::BC4_UNORM_BLOCK
+        | vk::Format::BC4_SNORM_BLOCK => (8, 4, 4),
+        vk::Format::BC2_UNORM_BLOCK
+        | vk::Format::BC2_SRGB_BLOCK
+        | vk::Format::BC3_UNORM_BLOCK
+        | vk::Format::BC3_SRGB_BLOCK
+        | vk::Format::BC5_UNORM_BLOCK
+        | vk::Format::BC5_SNORM_BLOCK
+        | vk::Format::BC6H_UFLOAT_BLOCK
+        | vk::Format::BC6H_SFLOAT_BLOCK
+        | vk::Format::BC7_UNORM_BLOCK
+        | vk::Format::BC7_SRGB_BLOCK => (16, 4, 4),
+        _ => return None,
+    };
+
+    Some(block)
+}
@@ -18,7 +18,17 @@ impl InstanceHandle {
     const ENGINE_VERSION: (u32, u32, u32) = (0, 1, 0);
     const APPLICATION_VERSION: (u32, u32, u32) = (0, 1, 0);
 
-    pub fn new(entry: &ash::Entry, window: &winit::window::Window) -> EngineResult<Self> {
+    /// #### Description
+    /// Without a window (headless) extensions of the windowing system aren't required.
+    ///
+    /// #### NOTE
+    /// Validation features are enabled only with the `dev` feature and when the layer provides
+    /// `VK_EXT_validation_features`, otherwise the layer runs with default settings.
+    pub fn new(
+        entry: &ash::Entry,
+        window: Option<&winit::window::Window>,
+        validation_features: &super::ValidationFeatures,
+    ) -> EngineResult<Self> {
         debug!("Creating Application Information.");
 
         let engine_name = to_cstr(Self::ENGINE_NAME);
@@ -66,8 +76,12 @@ impl InstanceHandle {
 
         debug!("Checking for Instance Extensions requirement.");
 
-        let mut required_extension_names =
-            ash_window::enumerate_required_extensions(window.raw_display_handle())?.to_vec();
+        let mut required_extension_names = match window {
+            Some(window) => {
+                ash_window::enumerate_required_extensions(window.raw_display_handle())?.to_vec()
+            }
+            None => Vec::new(),
+        };
         required_extension_names.push(ash::extensions::ext::DebugUtils::name().as_ptr());
         let available_extensions =
             entry.enumerate_instance_extension_properties(Default::default())?;
@@ -82,12 +96,38 @@ impl InstanceHandle {
             ));
         }
 
+        // Without it surfaces report only sRGB nonlinear color spaces, HDR ones are hidden.
+        let swapchain_colorspace_name = vk::ExtSwapchainColorspaceFn::name();
+        let does_support_swapchain_colorspace = available_extensions.iter().any(|extension| {
+            to_cstr(extension.extension_name.as_ptr()) == swapchain_colorspace_name
+        });
+        if window.is_some() && does_support_swapchain_colorspace {
+            required_extension_names.push(swapchain_colorspace_name.as_ptr());
+        }
+
+        #[cfg(feature = "dev")]
+        let validation_feature_enables = Self::validation_feature_enables(
+            entry,
+            validation_features,
+            &mut required_extension_names,
+        )?;
+        #[cfg(not(feature = "dev"))]
+        let _ = validation_features;
+
         debug!("Creating an Instance.");
 
-        let instance_info = vk::InstanceCreateInfo::default()
+        #[cfg_attr(not(feature = "dev"), allow(unused_mut))]
+        let mut instance_info = vk::InstanceCreateInfo::default()
             .application_info(&application_info)
             .enabled_layer_names(required_layer_names)
             .enabled_extension_names(&required_extension_names);
+        #[cfg(feature = "dev")]
+        let mut validation_features_info = vk::ValidationFeaturesEXT::default()
+            .enabled_validation_features(&validation_feature_enables);
+        #[cfg(feature = "dev")]
+        if !validation_feature_enables.is_empty() {
+            instance_info = instance_info.push_next(&mut validation_features_info);
+        }
         let instance = unsafe { entry.create_instance(&instance_info, None)? };
 
         let application_name = application_name.to_str().unwrap().to_owned();
@@ -104,13 +144,50 @@ impl InstanceHandle {
         Ok(Self { instance })
     }
 
+    /// #### Description
+    /// Adds `VK_EXT_validation_features` to the extensions if any feature is requested and the layer supports it.
+    #[cfg(feature = "dev")]
+    fn validation_feature_enables(
+        entry: &ash::Entry,
+        validation_features: &super::ValidationFeatures,
+        extension_names: &mut Vec<*const c_char>,
+    ) -> EngineResult<Vec<vk::ValidationFeatureEnableEXT>> {
+        let enables = validation_features.enables();
+        if enables.is_empty() {
+            return Ok(enables);
+        }
+
+        debug!("Checking for Validation Features requirement.");
+
+        let layer_extensions = entry.enumerate_instance_extension_properties(Some(to_cstr(
+            super::Context::VALIDATION_LAYER_NAME,
+        )))?;
+        let validation_features_name = vk::ExtValidationFeaturesFn::name();
+        let does_support = layer_extensions.iter().any(|extension| {
+            to_cstr(extension.extension_name.as_ptr()) == validation_features_name
+        });
+        if !does_support {
+            warning!(std::format!(
+                "Validation Layer doesn't provide {}, requested Validation Features are disabled.",
+                validation_features_name.to_string_lossy()
+            ));
+
+            return Ok(Vec::new());
+        }
+
+        extension_names.push(validation_features_name.as_ptr());
+        debug!(std::format!("Enabling Validation Features: {enables:?}."));
+
+        Ok(enables)
+    }
+
     fn print_metadata(
         application_name: &str,
         application_version: (u32, u32, u32),
         engine_name: &str,
         engine_version: (u32, u32, u32),
-        extension_names: &[*const i8],
         layer_names: &[*const i8],
+        extension_names: &[*const i8],
     ) {
         let mut instance_info = String::from("Created an Instance.\n\n");
 
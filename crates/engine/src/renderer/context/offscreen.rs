@@ -0,0 +1,159 @@
+use logging::*;
+
+use crate::error::EngineResult;
+
+use super::allocator::{Allocation, AllocationKind, GpuAllocator, MemoryLocation};
+
+use ash::vk;
+
+/// #### Description
+/// Replacement of the swapchain for the headless Context, frames are rendered into device local images.
+pub struct OffscreenManager {
+    pub images: Vec<vk::Image>,
+    pub image_views: Vec<vk::ImageView>,
+    allocations: Vec<Allocation>,
+    pub extent: vk::Extent2D,
+    pub format: vk::Format,
+    next_image_index: u32,
+}
+
+impl OffscreenManager {
+    pub const IMAGE_COUNT: usize = 2;
+    pub const FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+    pub fn new(
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+        extent: vk::Extent2D,
+    ) -> EngineResult<Self> {
+        debug!("Creating Offscreen images.");
+
+        let mut offscreen_manager = Self {
+            images: Default::default(),
+            image_views: Default::default(),
+            allocations: Default::default(),
+            extent,
+            format: Self::FORMAT,
+            next_image_index: Default::default(),
+        };
+        if let Err(e) = offscreen_manager.create_images(device, allocator) {
+            offscreen_manager.destroy_resources(device, allocator);
+
+            return Err(e);
+        }
+
+        Ok(offscreen_manager)
+    }
+
+    /// #### Description
+    /// New images are created before the old ones are destroyed, so on failure the old ones stay usable.
+    pub fn resize(
+        &mut self,
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+        extent: vk::Extent2D,
+    ) -> EngineResult<()> {
+        debug!(std::format!(
+            "Recreating Offscreen images with extent {}x{}.",
+            extent.width,
+            extent.height
+        ));
+
+        let resized = Self::new(device, allocator, extent)?;
+
+        device.wait_for_idle()?;
+
+        self.destroy_resources(device, allocator);
+        *self = resized;
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn acquire_next_image(&mut self) -> u32 {
+        let image_index = self.next_image_index;
+        self.next_image_index = (self.next_image_index + 1) % self.images.len() as u32;
+
+        image_index
+    }
+
+    pub fn destroy_resources(
+        &mut self,
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+    ) {
+        for image_view in self.image_views.drain(..) {
+            device.destroy_image_view(image_view);
+        }
+
+        for image in self.images.drain(..) {
+            device.destroy_image(image);
+        }
+
+        for allocation in self.allocations.drain(..) {
+            allocator.free(device, allocation);
+        }
+    }
+
+    fn create_images(
+        &mut self,
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+    ) -> EngineResult<()> {
+        let image_info = vk::ImageCreateInfo::default()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(self.format)
+            .extent(vk::Extent3D {
+                width: self.extent.width,
+                height: self.extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::TRANSFER_DST,
+            )
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+
+        for i in 0..Self::IMAGE_COUNT {
+            let image = device.create_image(&image_info)?;
+            self.images.push(image);
+            device.set_object_name(image, &std::format!("Offscreen Image {i}"));
+
+            let memory_requirements = device.get_image_memory_requirements(image);
+            let allocation = allocator.allocate(
+                device,
+                memory_requirements,
+                MemoryLocation::GpuOnly,
+                AllocationKind::Optimal,
+            )?;
+            let (memory, offset) = (allocation.memory, allocation.offset);
+            self.allocations.push(allocation);
+
+            device.bind_image_memory(image, memory, offset)?;
+
+            let image_view_info = vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(self.format)
+                .subresource_range(
+                    vk::ImageSubresourceRange::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .level_count(1)
+                        .layer_count(1),
+                );
+            let image_view = device.create_image_view(&image_view_info)?;
+            self.image_views.push(image_view);
+            device.set_object_name(image_view, &std::format!("Offscreen Image View {i}"));
+        }
+
+        self.next_image_index = Default::default();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,610 @@
+use logging::*;
+
+use crate::error::{EngineError, EngineResult};
+
+use super::{pipeline_cache::PipelineCacheManager, shader::Shader};
+
+use ahash::AHashMap;
+use ash::vk;
+use smallvec::SmallVec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderStage {
+    pub stage: vk::ShaderStageFlags,
+    pub module: vk::ShaderModule,
+}
+
+impl From<&Shader> for ShaderStage {
+    #[inline(always)]
+    fn from(shader: &Shader) -> Self {
+        Self {
+            stage: shader.stage,
+            module: shader.module,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VertexBinding {
+    pub binding: u32,
+    pub stride: u32,
+    pub input_rate: vk::VertexInputRate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VertexAttribute {
+    pub location: u32,
+    pub binding: u32,
+    pub format: vk::Format,
+    pub offset: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RasterizationState {
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    pub is_depth_clamp_enabled: bool,
+}
+
+impl Default for RasterizationState {
+    fn default() -> Self {
+        Self {
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::BACK,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            is_depth_clamp_enabled: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthStencilState {
+    pub is_depth_test_enabled: bool,
+    pub is_depth_write_enabled: bool,
+    pub depth_compare_op: vk::CompareOp,
+    /// Front and back stencil state, `None` disables the stencil test.
+    pub stencil: Option<(vk::StencilOpState, vk::StencilOpState)>,
+}
+
+impl Default for DepthStencilState {
+    fn default() -> Self {
+        Self {
+            is_depth_test_enabled: true,
+            is_depth_write_enabled: true,
+            depth_compare_op: vk::CompareOp::LESS_OR_EQUAL,
+            stencil: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlendState {
+    pub is_enabled: bool,
+    pub src_color_factor: vk::BlendFactor,
+    pub dst_color_factor: vk::BlendFactor,
+    pub color_op: vk::BlendOp,
+    pub src_alpha_factor: vk::BlendFactor,
+    pub dst_alpha_factor: vk::BlendFactor,
+    pub alpha_op: vk::BlendOp,
+    pub write_mask: vk::ColorComponentFlags,
+}
+
+impl BlendState {
+    pub const OPAQUE: Self = Self {
+        is_enabled: false,
+        src_color_factor: vk::BlendFactor::ONE,
+        dst_color_factor: vk::BlendFactor::ZERO,
+        color_op: vk::BlendOp::ADD,
+        src_alpha_factor: vk::BlendFactor::ONE,
+        dst_alpha_factor: vk::BlendFactor::ZERO,
+        alpha_op: vk::BlendOp::ADD,
+        write_mask: vk::ColorComponentFlags::RGBA,
+    };
+
+    pub const ALPHA: Self = Self {
+        is_enabled: true,
+        src_color_factor: vk::BlendFactor::SRC_ALPHA,
+        dst_color_factor: vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
+        ..Self::OPAQUE
+    };
+
+    pub const ADDITIVE: Self = Self {
+        is_enabled: true,
+        src_color_factor: vk::BlendFactor::ONE,
+        dst_color_factor: vk::BlendFactor::ONE,
+        ..Self::OPAQUE
+    };
+}
+
+impl Default for BlendState {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::OPAQUE
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PushConstantRange {
+    pub stages: vk::ShaderStageFlags,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct PipelineLayoutDesc {
+    pub set_layouts: SmallVec<[vk::DescriptorSetLayout; 4]>,
+    pub push_constant_ranges: SmallVec<[PushConstantRange; 2]>,
+}
+
+/// #### Description
+/// Everything needed to create a graphics pipeline which renders with dynamic rendering (no render passes).
+/// Viewport and scissor are dynamic by default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GraphicsPipelineDesc {
+    pub shader_stages: SmallVec<[ShaderStage; 2]>,
+    pub vertex_bindings: SmallVec<[VertexBinding; 2]>,
+    pub vertex_attributes: SmallVec<[VertexAttribute; 4]>,
+    pub topology: vk::PrimitiveTopology,
+    pub rasterization: RasterizationState,
+    pub depth_stencil: Option<DepthStencilState>,
+    pub color_formats: SmallVec<[vk::Format; 4]>,
+    /// One per color attachment.
+    pub blend_states: SmallVec<[BlendState; 4]>,
+    pub depth_format: vk::Format,
+    pub stencil_format: vk::Format,
+    pub samples: vk::SampleCountFlags,
+    pub dynamic_states: SmallVec<[vk::DynamicState; 4]>,
+    pub layout: PipelineLayoutDesc,
+}
+
+impl Default for GraphicsPipelineDesc {
+    fn default() -> Self {
+        Self {
+            shader_stages: Default::default(),
+            vertex_bindings: Default::default(),
+            vertex_attributes: Default::default(),
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            rasterization: Default::default(),
+            depth_stencil: None,
+            color_formats: Default::default(),
+            blend_states: Default::default(),
+            depth_format: vk::Format::UNDEFINED,
+            stencil_format: vk::Format::UNDEFINED,
+            samples: vk::SampleCountFlags::TYPE_1,
+            dynamic_states: SmallVec::from_slice(&[
+                vk::DynamicState::VIEWPORT,
+                vk::DynamicState::SCISSOR,
+            ]),
+            layout: Default::default(),
+        }
+    }
+}
+
+impl GraphicsPipelineDesc {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline(always)]
+    pub fn with_shader(mut self, shader: &Shader) -> Self {
+        self.shader_stages.push(shader.into());
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_vertex_binding(
+        mut self,
+        binding: u32,
+        stride: u32,
+        input_rate: vk::VertexInputRate,
+    ) -> Self {
+        self.vertex_bindings.push(VertexBinding {
+            binding,
+            stride,
+            input_rate,
+        });
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_vertex_attribute(
+        mut self,
+        location: u32,
+        binding: u32,
+        format: vk::Format,
+        offset: u32,
+    ) -> Self {
+        self.vertex_attributes.push(VertexAttribute {
+            location,
+            binding,
+            format,
+            offset,
+        });
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_topology(self, topology: vk::PrimitiveTopology) -> Self {
+        Self { topology, ..self }
+    }
+
+    #[inline(always)]
+    pub fn with_rasterization(self, rasterization: RasterizationState) -> Self {
+        Self {
+            rasterization,
+            ..self
+        }
+    }
+
+    /// #### Description
+    /// Depth attachment with its test state, stencil format is set too for combined formats.
+    #[inline(always)]
+    pub fn with_depth(self, format: vk::Format, depth_stencil: DepthStencilState) -> Self {
+        let stencil_format = match format {
+            vk::Format::D16_UNORM_S8_UINT
+            | vk::Format::D24_UNORM_S8_UINT
+            | vk::Format::D32_SFLOAT_S8_UINT => format,
+            _ => vk::Format::UNDEFINED,
+        };
+
+        Self {
+            depth_format: format,
+            stencil_format,
+            depth_stencil: Some(depth_stencil),
+            ..self
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_color_attachment(mut self, format: vk::Format, blend_state: BlendState) -> Self {
+        self.color_formats.push(format);
+        self.blend_states.push(blend_state);
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_samples(self, samples: vk::SampleCountFlags) -> Self {
+        Self { samples, ..self }
+    }
+
+    #[inline(always)]
+    pub fn with_dynamic_state(mut self, dynamic_state: vk::DynamicState) -> Self {
+        if !self.dynamic_states.contains(&dynamic_state) {
+            self.dynamic_states.push(dynamic_state);
+        }
+
+        self
+    }
+
+    /// #### Description
+    /// Descriptor set layouts in the order of `set = N` in shaders.
+    #[inline(always)]
+    pub fn with_set_layout(mut self, set_layout: vk::DescriptorSetLayout) -> Self {
+        self.layout.set_layouts.push(set_layout);
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_push_constants(
+        mut self,
+        stages: vk::ShaderStageFlags,
+        offset: u32,
+        size: u32,
+    ) -> Self {
+        self.layout.push_constant_ranges.push(PushConstantRange {
+            stages,
+            offset,
+            size,
+        });
+
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ComputePipelineDesc {
+    pub shader_stage: ShaderStage,
+    pub layout: PipelineLayoutDesc,
+}
+
+impl ComputePipelineDesc {
+    #[inline(always)]
+    pub fn new(shader: &Shader) -> Self {
+        Self {
+            shader_stage: shader.into(),
+            layout: Default::default(),
+        }
+    }
+
+    /// #### Description
+    /// Descriptor set layouts in the order of `set = N` in the shader.
+    #[inline(always)]
+    pub fn with_set_layout(mut self, set_layout: vk::DescriptorSetLayout) -> Self {
+        self.layout.set_layouts.push(set_layout);
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_push_constants(mut self, offset: u32, size: u32) -> Self {
+        self.layout.push_constant_ranges.push(PushConstantRange {
+            stages: vk::ShaderStageFlags::COMPUTE,
+            offset,
+            size,
+        });
+
+        self
+    }
+}
+
+/// #### Description
+/// Pipeline with its layout, both are owned by the `PipelineManager`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pipeline {
+    pub pipeline: vk::Pipeline,
+    pub layout: vk::PipelineLayout,
+    pub bind_point: vk::PipelineBindPoint,
+}
+
+/// #### Description
+/// Creates pipelines and pipeline layouts once per distinct description.
+/// Pipelines are compiled through the persistent pipeline cache.
+pub struct PipelineManager {
+    pipeline_cache_manager: PipelineCacheManager,
+    layouts: AHashMap<PipelineLayoutDesc, vk::PipelineLayout>,
+    graphics_pipelines: AHashMap<GraphicsPipelineDesc, Pipeline>,
+    compute_pipelines: AHashMap<ComputePipelineDesc, Pipeline>,
+}
+
+impl PipelineManager {
+    pub fn new(
+        device: &super::DeviceManager,
+        data_directory: &std::path::Path,
+    ) -> EngineResult<Self> {
+        debug!("Initializing pipeline cache");
+
+        Ok(Self {
+            pipeline_cache_manager: PipelineCacheManager::new(device, data_directory)?,
+            layouts: Default::default(),
+            graphics_pipelines: Default::default(),
+            compute_pipelines: Default::default(),
+        })
+    }
+
+    pub fn layout(
+        &mut self,
+        device: &super::DeviceManager,
+        desc: &PipelineLayoutDesc,
+    ) -> EngineResult<vk::PipelineLayout> {
+        if let Some(&layout) = self.layouts.get(desc) {
+            return Ok(layout);
+        }
+
+        let push_constant_ranges = desc
+            .push_constant_ranges
+            .iter()
+            .map(|range| {
+                vk::PushConstantRange::default()
+                    .stage_flags(range.stages)
+                    .offset(range.offset)
+                    .size(range.size)
+            })
+            .collect::<SmallVec<[_; 2]>>();
+        let layout_info = vk::PipelineLayoutCreateInfo::default()
+            .set_layouts(&desc.set_layouts)
+            .push_constant_ranges(&push_constant_ranges);
+        let layout = device.create_pipeline_layout(&layout_info)?;
+
+        self.layouts.insert(desc.clone(), layout);
+
+        Ok(layout)
+    }
+
+    pub fn graphics_pipeline(
+        &mut self,
+        device: &super::DeviceManager,
+        desc: &GraphicsPipelineDesc,
+    ) -> EngineResult<Pipeline> {
+        if let Some(&pipeline) = self.graphics_pipelines.get(desc) {
+            return Ok(pipeline);
+        }
+
+        if desc.blend_states.len() != desc.color_formats.len() {
+            return Err(EngineError::UnknownError(
+                "Every color attachment of the pipeline needs a blend state".to_owned(),
+            ));
+        }
+
+        let layout = self.layout(device, &desc.layout)?;
+
+        let entry_point =
+            std::ffi::CString::new(super::shader::ShaderManager::DEFAULT_ENTRY_POINT).unwrap();
+        let stages = desc
+            .shader_stages
+            .iter()
+            .map(|shader_stage| {
+                vk::PipelineShaderStageCreateInfo::default()
+                    .stage(shader_stage.stage)
+                    .module(shader_stage.module)
+                    .name(&entry_point)
+            })
+            .collect::<SmallVec<[_; 2]>>();
+
+        let vertex_bindings = desc
+            .vertex_bindings
+            .iter()
+            .map(|binding| {
+                vk::VertexInputBindingDescription::default()
+                    .binding(binding.binding)
+                    .stride(binding.stride)
+                    .input_rate(binding.input_rate)
+            })
+            .collect::<SmallVec<[_; 2]>>();
+        let vertex_attributes = desc
+            .vertex_attributes
+            .iter()
+            .map(|attribute| {
+                vk::VertexInputAttributeDescription::default()
+                    .location(attribute.location)
+                    .binding(attribute.binding)
+                    .format(attribute.format)
+                    .offset(attribute.offset)
+            })
+            .collect::<SmallVec<[_; 4]>>();
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::default()
+            .vertex_binding_descriptions(&vertex_bindings)
+            .vertex_attribute_descriptions(&vertex_attributes);
+
+        let input_assembly_state =
+            vk::PipelineInputAssemblyStateCreateInfo::default().topology(desc.topology);
+
+        // Counts must match the pipeline even though viewports and scissors are dynamic.
+        let viewport_state = vk::PipelineViewportStateCreateInfo::default()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::default()
+            .depth_clamp_enable(desc.rasterization.is_depth_clamp_enabled)
+            .polygon_mode(desc.rasterization.polygon_mode)
+            .cull_mode(desc.rasterization.cull_mode)
+            .front_face(desc.rasterization.front_face)
+            .line_width(1.0);
+
+        let multisample_state =
+            vk::PipelineMultisampleStateCreateInfo::default().rasterization_samples(desc.samples);
+
+        let depth_stencil = desc.depth_stencil.unwrap_or(DepthStencilState {
+            is_depth_test_enabled: false,
+            is_depth_write_enabled: false,
+            ..Default::default()
+        });
+        let (front_stencil, back_stencil) = depth_stencil.stencil.unwrap_or_default();
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::default()
+            .depth_test_enable(depth_stencil.is_depth_test_enabled)
+            .depth_write_enable(depth_stencil.is_depth_write_enabled)
+            .depth_compare_op(depth_stencil.depth_compare_op)
+            .stencil_test_enable(depth_stencil.stencil.is_some())
+            .front(front_stencil)
+            .back(back_stencil)
+            .max_depth_bounds(1.0);
+
+        let blend_attachments = desc
+            .blend_states
+            .iter()
+            .map(|blend_state| {
+                vk::PipelineColorBlendAttachmentState::default()
+                    .blend_enable(blend_state.is_enabled)
+                    .src_color_blend_factor(blend_state.src_color_factor)
+                    .dst_color_blend_factor(blend_state.dst_color_factor)
+                    .color_blend_op(blend_state.color_op)
+                    .src_alpha_blend_factor(blend_state.src_alpha_factor)
+                    .dst_alpha_blend_factor(blend_state.dst_alpha_factor)
+                    .alpha_blend_op(blend_state.alpha_op)
+                    .color_write_mask(blend_state.write_mask)
+            })
+            .collect::<SmallVec<[_; 4]>>();
+        let color_blend_state =
+            vk::PipelineColorBlendStateCreateInfo::default().attachments(&blend_attachments);
+
+        let dynamic_state =
+            vk::PipelineDynamicStateCreateInfo::default().dynamic_states(&desc.dynamic_states);
+
+        let mut rendering_info = vk::PipelineRenderingCreateInfo::default()
+            .color_attachment_formats(&desc.color_formats)
+            .depth_attachment_format(desc.depth_format)
+            .stencil_attachment_format(desc.stencil_format);
+
+        let pipeline_info = vk::GraphicsPipelineCreateInfo::default()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .multisample_state(&multisample_state)
+            .depth_stencil_state(&depth_stencil_state)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .push_next(&mut rendering_info);
+        let pipeline = Pipeline {
+            pipeline: device
+                .create_graphics_pipeline(self.pipeline_cache_manager.cache, &pipeline_info)?,
+            layout,
+            bind_point: vk::PipelineBindPoint::GRAPHICS,
+        };
+
+        self.graphics_pipelines.insert(desc.clone(), pipeline);
+
+        Ok(pipeline)
+    }
+
+    pub fn compute_pipeline(
+        &mut self,
+        device: &super::DeviceManager,
+        desc: &ComputePipelineDesc,
+    ) -> EngineResult<Pipeline> {
+        if let Some(&pipeline) = self.compute_pipelines.get(desc) {
+            return Ok(pipeline);
+        }
+
+        if desc.shader_stage.stage != vk::ShaderStageFlags::COMPUTE {
+            return Err(EngineError::ShaderError(
+                "Compute pipeline requires a compute shader".to_owned(),
+            ));
+        }
+
+        let layout = self.layout(device, &desc.layout)?;
+
+        let entry_point =
+            std::ffi::CString::new(super::shader::ShaderManager::DEFAULT_ENTRY_POINT).unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::default()
+            .stage(desc.shader_stage.stage)
+            .module(desc.shader_stage.module)
+            .name(&entry_point);
+
+        let pipeline_info = vk::ComputePipelineCreateInfo::default()
+            .stage(stage)
+            .layout(layout);
+        let pipeline = Pipeline {
+            pipeline: device
+                .create_compute_pipeline(self.pipeline_cache_manager.cache, &pipeline_info)?,
+            layout,
+            bind_point: vk::PipelineBindPoint::COMPUTE,
+        };
+
+        self.compute_pipelines.insert(desc.clone(), pipeline);
+
+        Ok(pipeline)
+    }
+
+    #[inline(always)]
+    pub fn pipeline_count(&self) -> usize {
+        self.graphics_pipelines.len() + self.compute_pipelines.len()
+    }
+
+    /// #### Description
+    /// The pipeline cache of a lost Device isn't saved, its data may be incomplete or corrupted.
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager, is_cache_saved: bool) {
+        for (_, pipeline) in self
+            .graphics_pipelines
+            .drain()
+            .chain(self.compute_pipelines.drain())
+        {
+            device.destroy_pipeline(pipeline.pipeline);
+        }
+
+        for (_, layout) in self.layouts.drain() {
+            device.destroy_pipeline_layout(layout);
+        }
+
+        self.pipeline_cache_manager
+            .destroy_resources(device, is_cache_saved);
+    }
+}
@@ -0,0 +1,115 @@
+use logging::*;
+
+use crate::error::EngineResult;
+
+use std::path::{Path, PathBuf};
+
+use ash::vk;
+
+/// #### Description
+/// `vk::PipelineCache` persisted between runs, so pipelines compiled once are not compiled again.
+/// Data written by another driver or GPU is discarded and the cache starts empty.
+pub struct PipelineCacheManager {
+    pub cache: vk::PipelineCache,
+    path: PathBuf,
+}
+
+impl PipelineCacheManager {
+    const FILE_NAME: &str = "pipeline_cache.bin";
+    /// Size of `VkPipelineCacheHeaderVersionOne`, its fields are always little-endian.
+    const HEADER_SIZE: usize = 32;
+
+    pub fn new(device: &super::DeviceManager, directory: &Path) -> EngineResult<Self> {
+        let path = directory.join(Self::FILE_NAME);
+
+        let initial_data = match std::fs::read(&path) {
+            Ok(data) if Self::is_compatible(device, &data) => {
+                debug!(std::format!(
+                    "Loaded pipeline cache of {} bytes from {}",
+                    data.len(),
+                    path.display()
+                ));
+
+                data
+            }
+            Ok(_) => {
+                debug!("Discarding pipeline cache made by another device or driver");
+
+                Vec::new()
+            }
+            Err(_) => Vec::new(),
+        };
+
+        let cache_info = vk::PipelineCacheCreateInfo::default().initial_data(&initial_data);
+        let cache = match device.create_pipeline_cache(&cache_info) {
+            Ok(cache) => cache,
+            // Data can still be corrupted past the header, then start from scratch.
+            Err(_) => device.create_pipeline_cache(&vk::PipelineCacheCreateInfo::default())?,
+        };
+
+        device.set_object_name(cache, "Pipeline Cache");
+
+        Ok(Self { cache, path })
+    }
+
+    /// #### Description
+    /// Writes the cache to disk. Failure isn't fatal, the cache is only an optimization.
+    pub fn save(&self, device: &super::DeviceManager) {
+        let result = device
+            .get_pipeline_cache_data(self.cache)
+            .map_err(|e| e.to_string())
+            .and_then(|data| {
+                if let Some(directory) = self.path.parent() {
+                    std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+                }
+
+                // Write to a temporary file first, so a crash mid-write can't leave a truncated cache.
+                let temporary_path = self.path.with_extension("tmp");
+                std::fs::write(&temporary_path, data)
+                    .and_then(|_| std::fs::rename(&temporary_path, &self.path))
+                    .map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = result {
+            warning!(std::format!(
+                "Failed to save pipeline cache to {}: {e}",
+                self.path.display()
+            ));
+        }
+    }
+
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager, is_cache_saved: bool) {
+        if self.cache == vk::PipelineCache::null() {
+            return;
+        }
+
+        if is_cache_saved {
+            self.save(device);
+        }
+        device.destroy_pipeline_cache(self.cache);
+        self.cache = vk::PipelineCache::null();
+    }
+
+    fn is_compatible(device: &super::DeviceManager, data: &[u8]) -> bool {
+        if data.len() < Self::HEADER_SIZE {
+            return false;
+        }
+
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        let header_size = read_u32(0) as usize;
+        let header_version = read_u32(4);
+        let vendor_id = read_u32(8);
+        let device_id = read_u32(12);
+        let pipeline_cache_uuid = &data[16..Self::HEADER_SIZE];
+
+        let properties = &device.device_properties;
+
+        header_size >= Self::HEADER_SIZE
+            && header_size <= data.len()
+            && header_version == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+            && vendor_id == properties.vendor_id
+            && device_id == properties.device_id
+            && pipeline_cache_uuid == properties.pipeline_cache_uuid
+    }
+}
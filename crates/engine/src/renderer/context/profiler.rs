@@ -0,0 +1,396 @@
+use logging::*;
+
+use crate::error::{EngineError, EngineResult};
+
+use super::CommandRecorder;
+
+use std::{panic::Location, sync::Mutex};
+
+use ash::vk;
+
+/// #### Description
+/// GPU time of one zone, relative to the start of the first zone of the frame.
+#[derive(Debug, Clone)]
+pub struct GpuZoneTiming {
+    pub name: String,
+    /// Nesting level, `0` for zones which aren't inside other zones.
+    pub depth: u32,
+    pub start_ms: f64,
+    pub duration_ms: f64,
+}
+
+/// #### Description
+/// Resolved GPU timings of a frame. Frames are resolved once the GPU finished them,
+/// so the report lags behind the frame being recorded by the number of frames in flight.
+#[derive(Debug, Clone, Default)]
+pub struct GpuFrameTimings {
+    pub frame_number: u64,
+    /// Time from the start of the first zone to the end of the last one.
+    pub total_ms: f64,
+    pub zones: Vec<GpuZoneTiming>,
+}
+
+struct Zone {
+    name: String,
+    depth: u32,
+    start_query: u32,
+    /// Reserved with the start query, so a zone which began can always be ended.
+    end_query: u32,
+    #[cfg(feature = "profiling")]
+    tracy_span: Option<tracy_client::GpuSpan>,
+}
+
+struct FrameQueries {
+    pool: vk::QueryPool,
+    zones: Vec<Zone>,
+    next_query: u32,
+    frame_number: u64,
+}
+
+/// #### Description
+/// Timestamp query pools per frame in flight. Zones are recorded with `CommandRecorder::gpu_zone`
+/// and resolved when the frame comes around again, after its fence was waited on.
+///
+/// #### NOTE
+/// Profiling is disabled on devices without timestamps on the queue family or without host query reset.
+/// With the `profiling` feature zones are also sent to Tracy as GPU zones.
+pub struct ProfilerManager {
+    frames: Vec<Mutex<FrameQueries>>,
+    current_frame_index: usize,
+    frame_number: u64,
+    /// Nanoseconds per timestamp tick.
+    timestamp_period: f64,
+    timestamp_mask: u64,
+    last_timings: Option<GpuFrameTimings>,
+    #[cfg(feature = "profiling")]
+    tracy_context: Option<tracy_client::GpuContext>,
+}
+
+impl ProfilerManager {
+    const MAX_ZONES_PER_FRAME: u32 = 256;
+    const QUERIES_PER_FRAME: u32 = Self::MAX_ZONES_PER_FRAME * 2;
+
+    pub fn new(device: &super::DeviceManager, frames_in_flight: usize) -> EngineResult<Self> {
+        let timestamp_period = device.device_properties.limits.timestamp_period as f64;
+        let valid_bits = device.timestamp_valid_bits;
+
+        let mut profiler_manager = Self {
+            frames: Default::default(),
+            current_frame_index: Default::default(),
+            frame_number: Default::default(),
+            timestamp_period,
+            timestamp_mask: match valid_bits {
+                64.. => u64::MAX,
+                _ => (1 << valid_bits) - 1,
+            },
+            last_timings: None,
+            #[cfg(feature = "profiling")]
+            tracy_context: None,
+        };
+
+        if valid_bits == 0
+            || timestamp_period == 0.0
+            || !device
+                .enabled_features
+                .is_enabled(super::DeviceFeature::HostQueryReset)
+        {
+            debug!("GPU timestamps aren't supported, GPU profiling is disabled.");
+
+            return Ok(profiler_manager);
+        }
+
+        debug!("Creating timestamp query pools.");
+
+        let query_pool_info = vk::QueryPoolCreateInfo::default()
+            .query_type(vk::QueryType::TIMESTAMP)
+            .query_count(Self::QUERIES_PER_FRAME);
+        for frame_index in 0..frames_in_flight {
+            let pool = match device.create_query_pool(&query_pool_info) {
+                Ok(pool) => pool,
+                Err(e) => {
+                    profiler_manager.destroy_resources(device);
+
+                    return Err(e);
+                }
+            };
+            device.reset_query_pool(pool, 0, Self::QUERIES_PER_FRAME);
+            device.set_object_name(
+                pool,
+                &std::format!("Frame {frame_index} Timestamp Query Pool"),
+            );
+
+            profiler_manager.frames.push(Mutex::new(FrameQueries {
+                pool,
+                zones: Default::default(),
+                next_query: Default::default(),
+                frame_number: Default::default(),
+            }));
+        }
+
+        Ok(profiler_manager)
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn last_timings(&self) -> Option<&GpuFrameTimings> {
+        self.last_timings.as_ref()
+    }
+
+    /// #### Description
+    /// Creates the Tracy GPU context, `gpu_timestamp` is a timestamp just written by the GPU
+    /// and is used to align GPU and CPU timelines.
+    #[cfg(feature = "profiling")]
+    pub fn connect_tracy(&mut self, gpu_timestamp: u64) {
+        let Some(client) = tracy_client::Client::running() else {
+            return;
+        };
+
+        match client.new_gpu_context(
+            Some("Vulkan"),
+            tracy_client::GpuContextType::Vulkan,
+            gpu_timestamp as i64,
+            self.timestamp_period as f32,
+        ) {
+            Ok(tracy_context) => self.tracy_context = Some(tracy_context),
+            Err(e) => warning!(std::format!("Failed to create Tracy GPU context: {e:?}")),
+        }
+    }
+
+    /// #### Description
+    /// Writes one timestamp into the pool of the current frame and returns its query,
+    /// used to calibrate the Tracy GPU context.
+    #[cfg(feature = "profiling")]
+    pub fn write_calibration_timestamp(&self, recorder: &CommandRecorder) -> Option<u32> {
+        let frame = self.frames.get(self.current_frame_index)?;
+        let mut frame = frame.lock().unwrap();
+
+        let query = frame.next_query;
+        frame.next_query += 1;
+        recorder.write_timestamp(vk::PipelineStageFlags2::ALL_COMMANDS, frame.pool, query);
+
+        Some(query)
+    }
+
+    /// #### Description
+    /// Reads a single timestamp written by `write_calibration_timestamp` and frees the query.
+    #[cfg(feature = "profiling")]
+    pub fn read_calibration_timestamp(
+        &self,
+        device: &super::DeviceManager,
+        query: u32,
+    ) -> EngineResult<u64> {
+        let mut frame = self.frames[self.current_frame_index].lock().unwrap();
+
+        let mut results = [[0_u64; 2]];
+        device.get_query_pool_results(frame.pool, query, &mut results)?;
+        device.reset_query_pool(frame.pool, query, 1);
+        frame.next_query = Default::default();
+
+        Ok(results[0][0])
+    }
+
+    /// #### Description
+    /// Resolves timings of the previous use of the frame and prepares its pool for recording.
+    /// Must be called only after the GPU finished the work of the frame.
+    pub fn begin_frame(
+        &mut self,
+        device: &super::DeviceManager,
+        frame_index: usize,
+    ) -> EngineResult<()> {
+        self.current_frame_index = frame_index;
+        self.frame_number += 1;
+
+        let Some(frame) = self.frames.get(frame_index) else {
+            return Ok(());
+        };
+        let mut frame = frame.lock().unwrap();
+
+        let mut timings = None;
+        if frame.next_query > 0 {
+            let mut results = vec![[0_u64; 2]; frame.next_query as usize];
+            match device.get_query_pool_results(frame.pool, 0, &mut results) {
+                // Zones which were never ended (e.g. the command buffer wasn't submitted) stay unavailable.
+                Ok(_) | Err(EngineError::VulkanApiError(vk::Result::NOT_READY)) => {}
+                Err(e) => return Err(e),
+            }
+
+            timings = Some(self.resolve(&mut frame, &results));
+
+            device.reset_query_pool(frame.pool, 0, frame.next_query);
+        }
+
+        frame.zones.clear();
+        frame.next_query = Default::default();
+        frame.frame_number = self.frame_number;
+        drop(frame);
+
+        if timings.is_some() {
+            self.last_timings = timings;
+        }
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Writes the start timestamp of a zone, returns `None` when profiling is disabled or the pool is full.
+    pub fn begin_zone(
+        &self,
+        recorder: &CommandRecorder,
+        name: &str,
+        depth: u32,
+        location: &Location,
+    ) -> Option<usize> {
+        let frame = self.frames.get(self.current_frame_index)?;
+        let mut frame = frame.lock().unwrap();
+
+        if frame.next_query + 2 > Self::QUERIES_PER_FRAME {
+            return None;
+        }
+
+        let start_query = frame.next_query;
+        let end_query = start_query + 1;
+        frame.next_query += 2;
+        recorder.write_timestamp(
+            vk::PipelineStageFlags2::TOP_OF_PIPE,
+            frame.pool,
+            start_query,
+        );
+
+        #[cfg(feature = "profiling")]
+        let tracy_span = self.tracy_context.as_ref().and_then(|tracy_context| {
+            tracy_context
+                .span_alloc(name, "", location.file(), location.line())
+                .ok()
+        });
+        #[cfg(not(feature = "profiling"))]
+        let _ = location;
+
+        frame.zones.push(Zone {
+            name: name.to_owned(),
+            depth,
+            start_query,
+            end_query,
+            #[cfg(feature = "profiling")]
+            tracy_span,
+        });
+
+        Some(frame.zones.len() - 1)
+    }
+
+    pub fn end_zone(&self, recorder: &CommandRecorder, zone_index: usize) {
+        let mut frame = self.frames[self.current_frame_index].lock().unwrap();
+        let pool = frame.pool;
+
+        let zone = &mut frame.zones[zone_index];
+        recorder.write_timestamp(
+            vk::PipelineStageFlags2::BOTTOM_OF_PIPE,
+            pool,
+            zone.end_query,
+        );
+        #[cfg(feature = "profiling")]
+        if let Some(tracy_span) = &mut zone.tracy_span {
+            tracy_span.end_zone();
+        }
+    }
+
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        for frame in self.frames.drain(..) {
+            device.destroy_query_pool(frame.into_inner().unwrap().pool);
+        }
+    }
+
+    fn resolve(&self, frame: &mut FrameQueries, results: &[[u64; 2]]) -> GpuFrameTimings {
+        let timestamp = |query: u32| {
+            let [value, availability] = results[query as usize];
+
+            (availability != 0).then_some(value & self.timestamp_mask)
+        };
+
+        let resolved_zones = frame
+            .zones
+            .drain(..)
+            .map(|zone| {
+                let start = timestamp(zone.start_query);
+                // The end query of a zone which was never ended stays unavailable.
+                let end = timestamp(zone.end_query);
+
+                #[cfg(feature = "profiling")]
+                if let Some(tracy_span) = zone.tracy_span {
+                    // Tracy waits for every allocated span, so unavailable ones are uploaded empty.
+                    let start = start.unwrap_or_default();
+                    tracy_span.upload_timestamp(start as i64, end.unwrap_or(start) as i64);
+                }
+
+                (zone.name, zone.depth, start.zip(end))
+            })
+            .collect::<Vec<_>>();
+
+        let Some(base) = resolved_zones
+            .iter()
+            .filter_map(|(_, _, timestamps)| timestamps.map(|(start, _)| start))
+            .min()
+        else {
+            return GpuFrameTimings {
+                frame_number: frame.frame_number,
+                ..Default::default()
+            };
+        };
+
+        let to_ms = |ticks: u64| {
+            (ticks.wrapping_sub(base) & self.timestamp_mask) as f64 * self.timestamp_period / 1e6
+        };
+        let zones = resolved_zones
+            .into_iter()
+            .filter_map(|(name, depth, timestamps)| {
+                let (start, end) = timestamps?;
+                let start_ms = to_ms(start);
+
+                Some(GpuZoneTiming {
+                    name,
+                    depth,
+                    start_ms,
+                    duration_ms: (to_ms(end) - start_ms).max(0.0),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        GpuFrameTimings {
+            frame_number: frame.frame_number,
+            total_ms: zones
+                .iter()
+                .map(|zone| zone.start_ms + zone.duration_ms)
+                .fold(0.0, f64::max),
+            zones,
+        }
+    }
+}
+
+/// #### Description
+/// Scope of a GPU zone, the end timestamp is written when it's dropped.
+/// Created by `CommandRecorder::gpu_zone`.
+pub struct GpuZone<'r, 'a> {
+    recorder: &'r CommandRecorder<'a>,
+    zone_index: Option<usize>,
+}
+
+impl<'r, 'a> GpuZone<'r, 'a> {
+    #[inline(always)]
+    pub(super) fn new(recorder: &'r CommandRecorder<'a>, zone_index: Option<usize>) -> Self {
+        Self {
+            recorder,
+            zone_index,
+        }
+    }
+}
+
+impl Drop for GpuZone<'_, '_> {
+    fn drop(&mut self) {
+        if let Some(zone_index) = self.zone_index {
+            self.recorder.end_gpu_zone(zone_index);
+        }
+    }
+}
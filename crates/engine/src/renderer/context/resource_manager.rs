@@ -1,72 +1,174 @@
+use ahash::AHashMap;
 use once_cell::sync::Lazy;
 
-use super::shader::Shader;
-use crate::common::Id;
+use super::{
+    allocator::GpuAllocator,
+    buffer::{Buffer, BufferDesc},
+    image::{Image, ImageDesc},
+    sampler::SamplerDesc,
+};
+use crate::{common::Id, error::EngineResult};
+
+use ash::vk;
 
 pub static mut RESOURCE_MANAGER: Lazy<ResourceManager> = Lazy::new(ResourceManager::new);
 
 pub struct ResourceManager {
-    device: *const super::DeviceManager,
-    shaders: Vec<*const Shader>,
-    // pub textures: HashMap<Id, Texture>,
+    buffers: AHashMap<Id, Buffer>,
+    images: AHashMap<Id, Image>,
+    samplers: AHashMap<SamplerDesc, vk::Sampler>,
+    next_resource_index: u64,
     // pub meshes: HashMap<Id, Mesh>,
     // pub materials: HashMap<Id, Material>,
     // pub pipelines: HashMap<Id, Pipeline>,
     // pub render_passes: HashMap<Id, RenderPass>,
     // pub framebuffers: HashMap<Id, Framebuffer>,
-    // pub samplers: HashMap<Id, Sampler>,
-    // pub descriptor_sets: HashMap<Id, DescriptorSet>,
-    // pub descriptor_set_layouts: HashMap<Id, DescriptorSetLayout>,
-    // pub descriptor_pools: HashMap<Id, DescriptorPool>,
     // pub command_buffers: HashMap<Id, CommandBuffer>,
     // pub command_pools: HashMap<Id, CommandPool>,
     // pub fences: HashMap<Id, Fence>,
     // pub semaphores: HashMap<Id, Semaphore>,
     // pub events: HashMap<Id, Event>,
     // pub query_pools: HashMap<Id, QueryPool>,
-    // pub buffers: HashMap<Id, Buffer>,
-    // pub images: HashMap<Id, Image>,
-    // pub image_views: HashMap<Id, ImageView>,
     // pub framebuffers: HashMap<Id, Framebuffer>,
     // pub render_passes: HashMap<Id, RenderPass>,
     // pub pipelines: HashMap<Id, Pipeline>,
     // pub pipeline_layouts: HashMap<Id, PipelineLayout>,
-    // pub pipeline_caches: HashMap<Id, PipelineCache>,
-    // pub pipeline_cache: HashMap<I
 }
 
 impl ResourceManager {
     #[inline(always)]
     pub fn new() -> Self {
         Self {
-            device: std::ptr::null(),
-            shaders: Default::default(),
+            buffers: Default::default(),
+            images: Default::default(),
+            samplers: Default::default(),
+            next_resource_index: Default::default(),
         }
     }
 
-    pub fn set_device(&mut self, device: &super::DeviceManager) {
-        self.device = device;
+    /// #### Description
+    /// Unique Id for a resource which isn't identified by a path, e.g. GPU buffers.
+    #[inline]
+    pub fn next_id(&mut self) -> Id {
+        self.next_resource_index += 1;
+
+        Id::new(self.next_resource_index)
+    }
+
+    #[inline]
+    pub fn register_buffer(&mut self, buffer: Buffer) {
+        self.buffers.insert(buffer.id, buffer);
     }
 
     #[inline]
-    pub fn register_shader(&mut self, shader: &Shader) {
-        self.shaders.push(shader);
-    }
-
-    pub unsafe fn delete_shader(&mut self, id: Id) {
-        let Some((index, shader)) = (unsafe {
-            self.shaders
-                .iter()
-                .enumerate()
-                .find(|(_, &shader)| (*shader).id == id)
-        }) else {
-            return;
-        };
-
-        unsafe {
-            (*self.device).destroy_shader_module(shader.as_ref().unwrap_unchecked().module);
+    pub fn buffer(&self, id: Id) -> Option<&Buffer> {
+        self.buffers.get(&id)
+    }
+
+    #[inline]
+    pub fn buffer_mut(&mut self, id: Id) -> Option<&mut Buffer> {
+        self.buffers.get_mut(&id)
+    }
+
+    pub fn delete_buffer(
+        &mut self,
+        id: Id,
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+    ) {
+        if let Some(buffer) = self.buffers.remove(&id) {
+            buffer.destroy(device, allocator);
         }
+    }
+
+    /// #### Description
+    /// Descriptions of every registered buffer, enough to create them again on a new Device.
+    pub fn buffer_descs(&self) -> Vec<(Id, BufferDesc)> {
+        self.buffers
+            .iter()
+            .map(|(&id, buffer)| (id, buffer.desc))
+            .collect()
+    }
+
+    /// #### Description
+    /// Destroys every registered buffer, must be called before the Device is destroyed.
+    pub fn delete_buffers(&mut self, device: &super::DeviceManager, allocator: &mut GpuAllocator) {
+        for (_, buffer) in self.buffers.drain() {
+            buffer.destroy(device, allocator);
+        }
+    }
 
-        self.shaders.remove(index);
+    #[inline]
+    pub fn register_image(&mut self, image: Image) {
+        self.images.insert(image.id, image);
+    }
+
+    #[inline]
+    pub fn image(&self, id: Id) -> Option<&Image> {
+        self.images.get(&id)
+    }
+
+    #[inline]
+    pub fn image_mut(&mut self, id: Id) -> Option<&mut Image> {
+        self.images.get_mut(&id)
+    }
+
+    pub fn delete_image(
+        &mut self,
+        id: Id,
+        device: &super::DeviceManager,
+        allocator: &mut GpuAllocator,
+    ) {
+        if let Some(image) = self.images.remove(&id) {
+            image.destroy(device, allocator);
+        }
+    }
+
+    /// #### Description
+    /// Descriptions of every registered image, enough to create them again on a new Device.
+    pub fn image_descs(&self) -> Vec<(Id, ImageDesc)> {
+        self.images
+            .iter()
+            .map(|(&id, image)| (id, image.desc))
+            .collect()
+    }
+
+    /// #### Description
+    /// Destroys every registered image, must be called before the Device is destroyed.
+    pub fn delete_images(&mut self, device: &super::DeviceManager, allocator: &mut GpuAllocator) {
+        for (_, image) in self.images.drain() {
+            image.destroy(device, allocator);
+        }
+    }
+
+    /// #### Description
+    /// Samplers are immutable and cheap to share, so equal descriptions get the same `vk::Sampler`.
+    pub fn sampler(
+        &mut self,
+        desc: SamplerDesc,
+        device: &super::DeviceManager,
+    ) -> EngineResult<vk::Sampler> {
+        let desc = desc.normalized(device);
+        if let Some(&sampler) = self.samplers.get(&desc) {
+            return Ok(sampler);
+        }
+
+        let sampler = desc.create(device)?;
+        self.samplers.insert(desc, sampler);
+
+        Ok(sampler)
+    }
+
+    #[inline(always)]
+    pub fn sampler_count(&self) -> usize {
+        self.samplers.len()
+    }
+
+    /// #### Description
+    /// Destroys every cached sampler, must be called before the Device is destroyed.
+    pub fn delete_samplers(&mut self, device: &super::DeviceManager) {
+        for (_, sampler) in self.samplers.drain() {
+            device.destroy_sampler(sampler);
+        }
     }
 }
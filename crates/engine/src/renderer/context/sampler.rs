@@ -0,0 +1,180 @@
+use crate::error::EngineResult;
+
+use std::hash::{Hash, Hasher};
+
+use ash::vk;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerDesc {
+    pub mag_filter: vk::Filter,
+    pub min_filter: vk::Filter,
+    pub mipmap_mode: vk::SamplerMipmapMode,
+    pub address_mode_u: vk::SamplerAddressMode,
+    pub address_mode_v: vk::SamplerAddressMode,
+    pub address_mode_w: vk::SamplerAddressMode,
+    /// `None` disables anisotropic filtering, the value is capped by the device limit.
+    pub max_anisotropy: Option<f32>,
+    /// `Some` makes a comparison sampler, e.g. for shadow maps.
+    pub compare_op: Option<vk::CompareOp>,
+    pub border_color: vk::BorderColor,
+    pub mip_lod_bias: f32,
+    pub min_lod: f32,
+    pub max_lod: f32,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            mag_filter: vk::Filter::LINEAR,
+            min_filter: vk::Filter::LINEAR,
+            mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+            address_mode_u: vk::SamplerAddressMode::REPEAT,
+            address_mode_v: vk::SamplerAddressMode::REPEAT,
+            address_mode_w: vk::SamplerAddressMode::REPEAT,
+            max_anisotropy: None,
+            compare_op: None,
+            border_color: vk::BorderColor::FLOAT_OPAQUE_BLACK,
+            mip_lod_bias: Default::default(),
+            min_lod: Default::default(),
+            max_lod: vk::LOD_CLAMP_NONE,
+        }
+    }
+}
+
+impl SamplerDesc {
+    #[inline(always)]
+    pub fn linear() -> Self {
+        Default::default()
+    }
+
+    #[inline(always)]
+    pub fn nearest() -> Self {
+        Self {
+            mag_filter: vk::Filter::NEAREST,
+            min_filter: vk::Filter::NEAREST,
+            mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+            ..Default::default()
+        }
+    }
+
+    #[inline(always)]
+    pub fn anisotropic(max_anisotropy: f32) -> Self {
+        Self {
+            max_anisotropy: Some(max_anisotropy),
+            ..Default::default()
+        }
+    }
+
+    /// #### Description
+    /// Comparison sampler for shadow maps, everything outside of the map is lit.
+    #[inline(always)]
+    pub fn shadow() -> Self {
+        Self {
+            address_mode_u: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+            address_mode_v: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+            address_mode_w: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+            compare_op: Some(vk::CompareOp::LESS_OR_EQUAL),
+            border_color: vk::BorderColor::FLOAT_OPAQUE_WHITE,
+            ..Default::default()
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_address_mode(self, address_mode: vk::SamplerAddressMode) -> Self {
+        Self {
+            address_mode_u: address_mode,
+            address_mode_v: address_mode,
+            address_mode_w: address_mode,
+            ..self
+        }
+    }
+
+    /// #### Description
+    /// Caps the description by what the device supports,
+    /// so descriptions which end up the same share one `vk::Sampler`.
+    pub fn normalized(self, device: &super::DeviceManager) -> Self {
+        let max_anisotropy = if device
+            .enabled_features
+            .is_enabled(super::DeviceFeature::SamplerAnisotropy)
+        {
+            let limit = device.device_properties.limits.max_sampler_anisotropy;
+
+            self.max_anisotropy
+                .map(|max_anisotropy| max_anisotropy.clamp(1.0, limit))
+                .filter(|&max_anisotropy| max_anisotropy > 1.0)
+        } else {
+            None
+        };
+
+        Self {
+            max_anisotropy,
+            ..self
+        }
+    }
+
+    pub fn create(&self, device: &super::DeviceManager) -> EngineResult<vk::Sampler> {
+        let sampler_info = vk::SamplerCreateInfo::default()
+            .mag_filter(self.mag_filter)
+            .min_filter(self.min_filter)
+            .mipmap_mode(self.mipmap_mode)
+            .address_mode_u(self.address_mode_u)
+            .address_mode_v(self.address_mode_v)
+            .address_mode_w(self.address_mode_w)
+            .anisotropy_enable(self.max_anisotropy.is_some())
+            .max_anisotropy(self.max_anisotropy.unwrap_or(1.0))
+            .compare_enable(self.compare_op.is_some())
+            .compare_op(self.compare_op.unwrap_or(vk::CompareOp::ALWAYS))
+            .border_color(self.border_color)
+            .mip_lod_bias(self.mip_lod_bias)
+            .min_lod(self.min_lod)
+            .max_lod(self.max_lod);
+
+        device.create_sampler(&sampler_info)
+    }
+
+    #[inline(always)]
+    fn key(
+        &self,
+    ) -> (
+        [i32; 6],
+        Option<u32>,
+        Option<vk::CompareOp>,
+        vk::BorderColor,
+        [u32; 3],
+    ) {
+        (
+            [
+                self.mag_filter.as_raw(),
+                self.min_filter.as_raw(),
+                self.mipmap_mode.as_raw(),
+                self.address_mode_u.as_raw(),
+                self.address_mode_v.as_raw(),
+                self.address_mode_w.as_raw(),
+            ],
+            self.max_anisotropy.map(f32::to_bits),
+            self.compare_op,
+            self.border_color,
+            [
+                self.mip_lod_bias.to_bits(),
+                self.min_lod.to_bits(),
+                self.max_lod.to_bits(),
+            ],
+        )
+    }
+}
+
+impl PartialEq for SamplerDesc {
+    #[inline(always)]
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SamplerDesc {}
+
+impl Hash for SamplerDesc {
+    #[inline(always)]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::{
     common::Id,
@@ -7,25 +7,30 @@ use crate::{
 
 use ash::vk;
 
-use super::resource_manager::RESOURCE_MANAGER;
-
 pub struct Shader {
     pub id: Id,
     pub module: vk::ShaderModule,
     pub stage: vk::ShaderStageFlags,
+    pub path: PathBuf,
+    /// Compiled code of the module, used to recreate it on a new Device without compiling again.
+    spirv: Vec<u32>,
 }
 
 impl Shader {
     #[inline(always)]
-    fn new(id: Id, module: vk::ShaderModule, stage: vk::ShaderStageFlags) -> Self {
-        Self { id, module, stage }
-    }
-}
-
-impl Drop for Shader {
-    fn drop(&mut self) {
-        unsafe {
-            RESOURCE_MANAGER.delete_shader(self.id);
+    fn new(
+        id: Id,
+        module: vk::ShaderModule,
+        stage: vk::ShaderStageFlags,
+        path: PathBuf,
+        spirv: Vec<u32>,
+    ) -> Self {
+        Self {
+            id,
+            module,
+            stage,
+            path,
+            spirv,
         }
     }
 }
@@ -39,13 +44,18 @@ pub struct ShaderManager<'a> {
 impl ShaderManager<'_> {
     pub const DEFAULT_ENTRY_POINT: &'static str = "main";
 
-    pub fn new() -> Self {
+    /// #### Description
+    /// With debug info the validation layer reports the source line of `debugPrintfEXT` calls.
+    pub fn new(is_debug_info: bool) -> Self {
         let mut compiler_options = shaderc::CompileOptions::new().unwrap();
         compiler_options.set_target_env(
             shaderc::TargetEnv::Vulkan,
             shaderc::EnvVersion::Vulkan1_3 as _,
         );
         compiler_options.set_optimization_level(shaderc::OptimizationLevel::Performance);
+        if is_debug_info {
+            compiler_options.set_generate_debug_info();
+        }
 
         let compiler = shaderc::Compiler::new().unwrap();
 
@@ -71,7 +81,7 @@ impl ShaderManager<'_> {
         &mut self,
         device: &super::DeviceManager,
         path: &T,
-    ) -> EngineResult<()> {
+    ) -> EngineResult<Id> {
         let path_as_str: &str = path.as_ref().to_str().unwrap();
         let id = Id::new(path_as_str);
 
@@ -81,9 +91,73 @@ impl ShaderManager<'_> {
             )));
         }
 
+        let (spirv, kind) = self.compile(path.as_ref())?;
+        let shader_module = Self::create_module(device, &spirv, path_as_str)?;
+
+        let shader = Shader::new(
+            id,
+            shader_module,
+            Self::map_shader_stage_from_kind(kind),
+            path.as_ref().to_owned(),
+            spirv,
+        );
+
+        self.shaders.push(shader);
+
+        Ok(id)
+    }
+
+    #[inline(always)]
+    pub fn shader(&self, id: Id) -> Option<&Shader> {
+        self.shaders.iter().find(|shader| shader.id == id)
+    }
+
+    #[inline(always)]
+    pub fn unload_shader(&mut self, id: Id, device: &super::DeviceManager) {
+        let Some(index) = self.shaders.iter().position(|shader| shader.id == id) else {
+            return;
+        };
+
+        let shader = self.shaders.remove(index);
+        device.destroy_shader_module(shader.module);
+    }
+
+    #[inline(always)]
+    pub fn unload_shaders(&mut self, device: &super::DeviceManager) {
+        for shader in self.shaders.drain(..) {
+            device.destroy_shader_module(shader.module);
+        }
+    }
+
+    /// #### Description
+    /// Destroys modules of the Device which is about to be destroyed, shaders stay loaded
+    /// and get new modules with `recreate_modules`.
+    pub fn destroy_modules(&mut self, device: &super::DeviceManager) {
+        for shader in &mut self.shaders {
+            device.destroy_shader_module(shader.module);
+            shader.module = vk::ShaderModule::null();
+        }
+    }
+
+    /// #### Description
+    /// Creates the module of every loaded shader on the given Device from its compiled code.
+    pub fn recreate_modules(&mut self, device: &super::DeviceManager) -> EngineResult<()> {
+        for shader in &mut self.shaders {
+            shader.module =
+                Self::create_module(device, &shader.spirv, shader.path.to_str().unwrap())?;
+        }
+
+        Ok(())
+    }
+
+    fn compile(&self, path: &Path) -> EngineResult<(Vec<u32>, shaderc::ShaderKind)> {
+        let path_as_str: &str = path.to_str().unwrap();
+
         let splitted_path = path_as_str.split('.').collect::<Vec<_>>();
         let Some(kind_name) = splitted_path.get(2) else {
-            return Err(EngineError::ShaderError("Invalid shader file extension.".to_owned()));
+            return Err(EngineError::ShaderError(
+                "Invalid shader file extension.".to_owned(),
+            ));
         };
 
         let kind = Self::map_shader_kind_from_str(kind_name).ok_or_else(|| {
@@ -94,7 +168,7 @@ impl ShaderManager<'_> {
             EngineError::ShaderError(format!("Failed to read shader file {path_as_str}: {e}"))
         })?;
 
-        let filename = path.as_ref().file_name().unwrap().to_str().unwrap();
+        let filename = path.file_name().unwrap().to_str().unwrap();
         let spirv = self
             .compiler
             .compile_into_spirv(
@@ -108,32 +182,18 @@ impl ShaderManager<'_> {
                 EngineError::ShaderError(format!("Failed to compile shader {path_as_str}: {e}"))
             })?;
 
-        let shader_module = device.create_shader_module(&spirv)?;
-
-        let shader = Shader::new(id, shader_module, Self::map_shader_stage_from_kind(kind));
-
-        unsafe {
-            RESOURCE_MANAGER.register_shader(&shader);
-        }
-        self.shaders.push(shader);
-
-        Ok(())
+        Ok((spirv.as_binary().to_vec(), kind))
     }
 
-    #[inline(always)]
-    pub fn unload_shader(&mut self, id: Id, _device: &super::DeviceManager) {
-        unsafe {
-            RESOURCE_MANAGER.delete_shader(id);
-        }
-    }
+    fn create_module(
+        device: &super::DeviceManager,
+        spirv: &[u32],
+        name: &str,
+    ) -> EngineResult<vk::ShaderModule> {
+        let shader_module = device.create_shader_module(spirv)?;
+        device.set_object_name(shader_module, name);
 
-    #[inline(always)]
-    pub fn unload_shaders(&mut self, _device: &super::DeviceManager) {
-        for shader in self.shaders.drain(..) {
-            unsafe {
-                RESOURCE_MANAGER.delete_shader(shader.id);
-            }
-        }
+        Ok(shader_module)
     }
 
     #[inline(always)]
@@ -0,0 +1,284 @@
+use ash::vk;
+
+/// #### Description
+/// Swapchain format and color space the application can ask for, in order of preference.
+///
+/// #### NOTE
+/// Color spaces other than sRGB nonlinear are reported by surfaces only with `VK_EXT_swapchain_colorspace`,
+/// which is enabled when available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SurfaceFormat {
+    /// 8-bit sRGB, the sRGB curve is applied by the hardware on write.
+    Srgb,
+    /// 8-bit UNORM in sRGB color space, shaders apply the sRGB curve themselves.
+    Unorm8,
+    /// 10-bit UNORM in sRGB color space, less banding than 8-bit, shaders apply the sRGB curve.
+    Unorm10,
+    /// HDR10: 10-bit UNORM, BT.2020 primaries, shaders encode with the ST 2084 (PQ) curve.
+    Hdr10,
+    /// 16-bit float scRGB: linear values, 1.0 is SDR white and values above it are HDR.
+    ExtendedSrgbLinear,
+}
+
+impl SurfaceFormat {
+    /// Preference used when the application doesn't supply one.
+    pub const DEFAULT: &[Self] = &[Self::Srgb];
+
+    /// #### Description
+    /// Surface formats matching the preference, tried in order.
+    fn candidates(self) -> &'static [vk::SurfaceFormatKHR] {
+        const fn surface_format(
+            format: vk::Format,
+            color_space: vk::ColorSpaceKHR,
+        ) -> vk::SurfaceFormatKHR {
+            vk::SurfaceFormatKHR {
+                format,
+                color_space,
+            }
+        }
+
+        const SRGB: &[vk::SurfaceFormatKHR] = &[
+            surface_format(vk::Format::B8G8R8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR),
+            surface_format(vk::Format::R8G8B8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR),
+        ];
+        const UNORM8: &[vk::SurfaceFormatKHR] = &[
+            surface_format(
+                vk::Format::B8G8R8A8_UNORM,
+                vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            ),
+            surface_format(
+                vk::Format::R8G8B8A8_UNORM,
+                vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            ),
+        ];
+        const UNORM10: &[vk::SurfaceFormatKHR] = &[
+            surface_format(
+                vk::Format::A2B10G10R10_UNORM_PACK32,
+                vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            ),
+            surface_format(
+                vk::Format::A2R10G10B10_UNORM_PACK32,
+                vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            ),
+        ];
+        const HDR10: &[vk::SurfaceFormatKHR] = &[
+            surface_format(
+                vk::Format::A2B10G10R10_UNORM_PACK32,
+                vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+            ),
+            surface_format(
+                vk::Format::A2R10G10B10_UNORM_PACK32,
+                vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+            ),
+        ];
+        const EXTENDED_SRGB_LINEAR: &[vk::SurfaceFormatKHR] = &[surface_format(
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        )];
+
+        match self {
+            Self::Srgb => SRGB,
+            Self::Unorm8 => UNORM8,
+            Self::Unorm10 => UNORM10,
+            Self::Hdr10 => HDR10,
+            Self::ExtendedSrgbLinear => EXTENDED_SRGB_LINEAR,
+        }
+    }
+
+    /// #### Description
+    /// The first supported format of the ranked preferences. Without a match the surface's
+    /// own sRGB nonlinear formats are used, 8-bit sRGB ones first.
+    ///
+    /// #### NOTE
+    /// A single `vk::Format::UNDEFINED` entry means the surface has no preferred format,
+    /// then the first candidate of the top preference is used.
+    /// Returns `None` only if the surface reports no sRGB nonlinear format, which the specification doesn't allow.
+    pub fn pick(
+        preferences: &[Self],
+        supported_formats: &[vk::SurfaceFormatKHR],
+    ) -> Option<(vk::SurfaceFormatKHR, OutputFormat)> {
+        if let [vk::SurfaceFormatKHR {
+            format: vk::Format::UNDEFINED,
+            ..
+        }] = supported_formats
+        {
+            let preference = preferences.first().copied().unwrap_or(Self::Srgb);
+            let surface_format = preference.candidates()[0];
+
+            return Some((
+                surface_format,
+                OutputFormat::new(surface_format, Some(preference)),
+            ));
+        }
+
+        let preferred = preferences.iter().find_map(|&preference| {
+            preference
+                .candidates()
+                .iter()
+                .find(|candidate| supported_formats.contains(candidate))
+                .map(|&surface_format| (surface_format, Some(preference)))
+        });
+        if let Some((surface_format, preference)) = preferred {
+            return Some((
+                surface_format,
+                OutputFormat::new(surface_format, preference),
+            ));
+        }
+
+        let fallback = Self::Srgb
+            .candidates()
+            .iter()
+            .find(|candidate| supported_formats.contains(candidate))
+            .or_else(|| {
+                supported_formats.iter().find(|surface_format| {
+                    surface_format.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR
+                })
+            })?;
+
+        Some((*fallback, OutputFormat::new(*fallback, None)))
+    }
+}
+
+/// #### Description
+/// Color space of the presented image, i.e. what tone mapping has to output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputColorSpace {
+    SrgbNonlinear,
+    ExtendedSrgbLinear,
+    Hdr10St2084,
+}
+
+/// #### Description
+/// Format the frames are presented in, so tone mapping can adapt to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputFormat {
+    /// Preference the format was picked by, `None` if the surface supports none of them.
+    pub preference: Option<SurfaceFormat>,
+    pub color_space: OutputColorSpace,
+    /// The sRGB curve is applied by the hardware on write, shaders output linear values.
+    pub is_srgb_encoded: bool,
+    pub bits_per_channel: u32,
+}
+
+impl OutputFormat {
+    pub fn new(surface_format: vk::SurfaceFormatKHR, preference: Option<SurfaceFormat>) -> Self {
+        let color_space = match surface_format.color_space {
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => OutputColorSpace::ExtendedSrgbLinear,
+            vk::ColorSpaceKHR::HDR10_ST2084_EXT => OutputColorSpace::Hdr10St2084,
+            _ => OutputColorSpace::SrgbNonlinear,
+        };
+        let is_srgb_encoded = matches!(
+            surface_format.format,
+            vk::Format::B8G8R8A8_SRGB
+                | vk::Format::R8G8B8A8_SRGB
+                | vk::Format::A8B8G8R8_SRGB_PACK32
+        );
+        let bits_per_channel = match surface_format.format {
+            vk::Format::A2B10G10R10_UNORM_PACK32 | vk::Format::A2R10G10B10_UNORM_PACK32 => 10,
+            vk::Format::R16G16B16A16_SFLOAT | vk::Format::R16G16B16A16_UNORM => 16,
+            vk::Format::R5G6B5_UNORM_PACK16 | vk::Format::B5G6R5_UNORM_PACK16 => 5,
+            _ => 8,
+        };
+
+        Self {
+            preference,
+            color_space,
+            is_srgb_encoded,
+            bits_per_channel,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_hdr(&self) -> bool {
+        self.color_space != OutputColorSpace::SrgbNonlinear
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn surface_format(format: vk::Format, color_space: vk::ColorSpaceKHR) -> vk::SurfaceFormatKHR {
+        vk::SurfaceFormatKHR {
+            format,
+            color_space,
+        }
+    }
+
+    #[test]
+    fn first_supported_preference_is_picked() {
+        let supported_formats = [
+            surface_format(vk::Format::B8G8R8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR),
+            surface_format(
+                vk::Format::A2R10G10B10_UNORM_PACK32,
+                vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+            ),
+        ];
+
+        let (picked, output_format) = SurfaceFormat::pick(
+            &[
+                SurfaceFormat::ExtendedSrgbLinear,
+                SurfaceFormat::Hdr10,
+                SurfaceFormat::Srgb,
+            ],
+            &supported_formats,
+        )
+        .unwrap();
+
+        assert_eq!(picked, supported_formats[1]);
+        assert_eq!(output_format.preference, Some(SurfaceFormat::Hdr10));
+        assert_eq!(output_format.color_space, OutputColorSpace::Hdr10St2084);
+        assert_eq!(output_format.bits_per_channel, 10);
+        assert!(output_format.is_hdr());
+    }
+
+    #[test]
+    fn surface_srgb_format_is_the_fallback() {
+        let supported_formats = [
+            surface_format(
+                vk::Format::R5G6B5_UNORM_PACK16,
+                vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            ),
+            surface_format(vk::Format::R8G8B8A8_SRGB, vk::ColorSpaceKHR::SRGB_NONLINEAR),
+        ];
+
+        let (picked, output_format) =
+            SurfaceFormat::pick(&[SurfaceFormat::Hdr10], &supported_formats).unwrap();
+
+        // 8-bit sRGB is preferred over the first reported format.
+        assert_eq!(picked, supported_formats[1]);
+        assert_eq!(output_format.preference, None);
+        assert!(output_format.is_srgb_encoded);
+    }
+
+    #[test]
+    fn single_undefined_format_allows_the_top_preference() {
+        let supported_formats = [surface_format(
+            vk::Format::UNDEFINED,
+            vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        )];
+
+        let (picked, output_format) =
+            SurfaceFormat::pick(&[SurfaceFormat::Unorm10], &supported_formats).unwrap();
+
+        assert_eq!(picked, SurfaceFormat::Unorm10.candidates()[0]);
+        assert_eq!(output_format.preference, Some(SurfaceFormat::Unorm10));
+        assert_eq!(
+            SurfaceFormat::pick(&[], &supported_formats).unwrap().0,
+            SurfaceFormat::Srgb.candidates()[0]
+        );
+    }
+
+    #[test]
+    fn nothing_is_picked_without_srgb_nonlinear_format() {
+        let supported_formats = [surface_format(
+            vk::Format::R16G16B16A16_SFLOAT,
+            vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+        )];
+
+        assert_eq!(
+            SurfaceFormat::pick(SurfaceFormat::DEFAULT, &supported_formats),
+            None
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use logging::*;
+
+use crate::error::{EngineError, EngineResult};
+
+use super::{OutputFormat, SurfaceFormat};
+
+use ash::{extensions::khr::Swapchain, vk};
+
+/// #### Description
+/// Synchronization of presentation with the refresh of the display.
+/// Modes unsupported by the surface fall back to `On`, which is always supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VsyncMode {
+    /// Frames are presented immediately, may tear (IMMEDIATE).
+    Off,
+    /// Waits for the refresh unless the frame is late, late frames may tear (FIFO_RELAXED).
+    Adaptive,
+    /// Waits for the refresh, never tears (FIFO).
+    On,
+    /// Never tears, the newest frame replaces queued ones without waiting (MAILBOX).
+    #[default]
+    LowLatency,
+}
+
+impl VsyncMode {
+    #[inline(always)]
+    fn present_mode(self) -> vk::PresentModeKHR {
+        match self {
+            Self::Off => vk::PresentModeKHR::IMMEDIATE,
+            Self::Adaptive => vk::PresentModeKHR::FIFO_RELAXED,
+            Self::On => vk::PresentModeKHR::FIFO,
+            Self::LowLatency => vk::PresentModeKHR::MAILBOX,
+        }
+    }
+
+    /// #### Description
+    /// The mode itself if the surface supports it, `On` otherwise.
+    fn supported_or_fifo(self, present_modes: &[vk::PresentModeKHR]) -> Self {
+        if present_modes.contains(&self.present_mode()) {
+            self
+        } else {
+            warning!(std::format!(
+                "Vsync mode {self:?} isn't supported by the Surface, falling back to {:?}.",
+                Self::On
+            ));
+
+            Self::On
+        }
+    }
+}
+
+pub struct SwapchainManager {
+    pub swapchain_loader: Swapchain,
+    pub swapchain: vk::SwapchainKHR,
+    pub images: Vec<vk::Image>,
+    pub image_views: Vec<vk::ImageView>,
+    /// Signaled when rendering into the image with the same index is finished, waited on by its present.
+    render_semaphores: Vec<vk::Semaphore>,
+    pub extent: vk::Extent2D,
+    pub surface_format: vk::SurfaceFormatKHR,
+    pub output_format: OutputFormat,
+    pub present_mode: vk::PresentModeKHR,
+    /// Mode the swapchain is created with, after the fallback.
+    pub vsync_mode: VsyncMode,
+    is_outdated: bool,
+}
+
+impl SwapchainManager {
+    /// Surfaces support at least one of them, opaque is preferred since the alpha of frames isn't meaningful.
+    const COMPOSITE_ALPHA_PREFERENCE: [vk::CompositeAlphaFlagsKHR; 4] = [
+        vk::CompositeAlphaFlagsKHR::OPAQUE,
+        vk::CompositeAlphaFlagsKHR::INHERIT,
+        vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+        vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+    ];
+
+    /// #### Description
+    /// The surface format is the first supported one of `surface_formats`, ranked by preference,
+    /// or a format of the surface itself if none is supported.
+    pub fn new(
+        instance: &ash::Instance,
+        device: &super::DeviceManager,
+        surface_handle: &super::SurfaceManager,
+        window_extent: vk::Extent2D,
+        surface_formats: &[SurfaceFormat],
+        vsync_mode: VsyncMode,
+    ) -> EngineResult<Self> {
+        debug!("Creating Swapchain.");
+
+        let Some(supported_formats) = &device.surface_formats else {
+            return Err(EngineError::UnknownError(
+                "Device was created without support of Surface".to_owned(),
+            ));
+        };
+        let Some((surface_format, output_format)) =
+            SurfaceFormat::pick(surface_formats, supported_formats)
+        else {
+            return Err(EngineError::UnknownError(
+                "Surface supports none of the preferred formats and no sRGB nonlinear format"
+                    .to_owned(),
+            ));
+        };
+        match output_format.preference {
+            Some(preference) => info!(std::format!(
+                "Picked Surface Format {preference:?}: {:?}, {:?}.",
+                surface_format.format,
+                surface_format.color_space
+            )),
+            None => warning!(std::format!(
+                "Surface supports none of preferred formats {surface_formats:?}, falling back to {:?}, {:?}.",
+                surface_format.format,
+                surface_format.color_space
+            )),
+        }
+
+        let swapchain_loader = device.create_swapchain_loader(instance);
+        let vsync_mode = Self::pick_vsync_mode(device, vsync_mode);
+
+        let mut swapchain_manager = Self {
+            swapchain_loader,
+            swapchain: vk::SwapchainKHR::null(),
+            images: Default::default(),
+            image_views: Default::default(),
+            render_semaphores: Default::default(),
+            extent: window_extent,
+            surface_format,
+            output_format,
+            present_mode: vsync_mode.present_mode(),
+            vsync_mode,
+            is_outdated: false,
+        };
+        if let Err(e) = swapchain_manager.create_swapchain(device, surface_handle, window_extent) {
+            swapchain_manager.destroy_resources(device);
+
+            return Err(e);
+        }
+
+        Ok(swapchain_manager)
+    }
+
+    /// #### Description
+    /// Rebuilds the swapchain and its image views for the new size of the window.
+    ///
+    /// #### NOTE
+    /// Must not be called while the window is minimized, the surface has zero extent then.
+    pub fn recreate(
+        &mut self,
+        device: &super::DeviceManager,
+        surface_handle: &super::SurfaceManager,
+        window_extent: vk::Extent2D,
+    ) -> EngineResult<()> {
+        debug!(std::format!(
+            "Recreating Swapchain with extent {}x{}.",
+            window_extent.width,
+            window_extent.height
+        ));
+
+        device.wait_for_idle()?;
+
+        self.destroy_image_objects(device);
+        self.create_swapchain(device, surface_handle, window_extent)?;
+        self.is_outdated = false;
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Returns `None` if the swapchain became outdated, in that case the frame must be skipped.
+    pub fn acquire_next_image(&mut self, semaphore: vk::Semaphore) -> EngineResult<Option<u32>> {
+        match unsafe {
+            self.swapchain_loader.acquire_next_image(
+                self.swapchain,
+                u64::MAX,
+                semaphore,
+                vk::Fence::null(),
+            )
+        } {
+            Ok((image_index, is_suboptimal)) => {
+                // Suboptimal image still can be presented, the swapchain is rebuilt next frame.
+                self.is_outdated |= is_suboptimal;
+
+                Ok(Some(image_index))
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                self.is_outdated = true;
+
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn present(
+        &mut self,
+        queue: vk::Queue,
+        wait_semaphore: vk::Semaphore,
+        image_index: u32,
+    ) -> EngineResult<()> {
+        let wait_semaphores = [wait_semaphore];
+        let swapchains = [self.swapchain];
+        let image_indices = [image_index];
+        let present_info = vk::PresentInfoKHR::default()
+            .wait_semaphores(&wait_semaphores)
+            .swapchains(&swapchains)
+            .image_indices(&image_indices);
+
+        match unsafe { self.swapchain_loader.queue_present(queue, &present_info) } {
+            Ok(is_suboptimal) => {
+                self.is_outdated |= is_suboptimal;
+
+                Ok(())
+            }
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                self.is_outdated = true;
+
+                Ok(())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// #### Description
+    /// Changes the present mode, the swapchain is recreated before the next frame.
+    pub fn set_vsync_mode(&mut self, device: &super::DeviceManager, vsync_mode: VsyncMode) {
+        let vsync_mode = Self::pick_vsync_mode(device, vsync_mode);
+        if vsync_mode == self.vsync_mode {
+            return;
+        }
+
+        self.vsync_mode = vsync_mode;
+        self.present_mode = vsync_mode.present_mode();
+        self.is_outdated = true;
+    }
+
+    #[inline(always)]
+    pub fn render_semaphore(&self, image_index: u32) -> vk::Semaphore {
+        self.render_semaphores[image_index as usize]
+    }
+
+    #[inline(always)]
+    pub fn mark_outdated(&mut self) {
+        self.is_outdated = true;
+    }
+
+    #[inline(always)]
+    pub fn is_outdated(&self) -> bool {
+        self.is_outdated
+    }
+
+    #[inline(always)]
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        self.destroy_image_objects(device);
+
+        // Nothing is left to destroy when called again, e.g. after a failed Device recreation.
+        if self.swapchain != vk::SwapchainKHR::null() {
+            unsafe {
+                self.swapchain_loader
+                    .destroy_swapchain(self.swapchain, None)
+            };
+            self.swapchain = vk::SwapchainKHR::null();
+        }
+    }
+
+    fn create_swapchain(
+        &mut self,
+        device: &super::DeviceManager,
+        surface_handle: &super::SurfaceManager,
+        window_extent: vk::Extent2D,
+    ) -> EngineResult<()> {
+        let surface_capabilities = unsafe {
+            surface_handle
+                .surface_loader
+                .get_physical_device_surface_capabilities(
+                    device.physical_device,
+                    surface_handle.surface,
+                )?
+        };
+
+        let extent = Self::pick_extent(&surface_capabilities, window_extent);
+
+        let mut min_image_count = surface_capabilities.min_image_count + 1;
+        // Zero means that there is no limit on the number of images.
+        if surface_capabilities.max_image_count > Default::default() {
+            min_image_count = min_image_count.min(surface_capabilities.max_image_count);
+        }
+
+        // Color attachment usage is guaranteed, transfer destination (e.g. for clears) isn't.
+        let image_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | (vk::ImageUsageFlags::TRANSFER_DST & surface_capabilities.supported_usage_flags);
+        let composite_alpha = Self::COMPOSITE_ALPHA_PREFERENCE
+            .into_iter()
+            .find(|&composite_alpha| {
+                surface_capabilities
+                    .supported_composite_alpha
+                    .contains(composite_alpha)
+            })
+            .unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE);
+
+        let old_swapchain = self.swapchain;
+        let swapchain_info = vk::SwapchainCreateInfoKHR::default()
+            .surface(surface_handle.surface)
+            .min_image_count(min_image_count)
+            .image_format(self.surface_format.format)
+            .image_color_space(self.surface_format.color_space)
+            .image_extent(extent)
+            .image_array_layers(1)
+            .image_usage(image_usage)
+            .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .pre_transform(surface_capabilities.current_transform)
+            .composite_alpha(composite_alpha)
+            .present_mode(self.present_mode)
+            .clipped(true)
+            .old_swapchain(old_swapchain);
+
+        let swapchain = unsafe {
+            self.swapchain_loader
+                .create_swapchain(&swapchain_info, None)?
+        };
+        // Stored right away, so `destroy_resources` releases the new swapchain if anything below fails.
+        self.swapchain = swapchain;
+        self.extent = extent;
+
+        if old_swapchain != vk::SwapchainKHR::null() {
+            unsafe { self.swapchain_loader.destroy_swapchain(old_swapchain, None) };
+        }
+
+        self.images = unsafe { self.swapchain_loader.get_swapchain_images(swapchain)? };
+        for (i, &image) in self.images.iter().enumerate() {
+            device.set_object_name(image, &std::format!("Swapchain Image {i}"));
+
+            let image_view_info = vk::ImageViewCreateInfo::default()
+                .image(image)
+                .view_type(vk::ImageViewType::TYPE_2D)
+                .format(self.surface_format.format)
+                .subresource_range(
+                    vk::ImageSubresourceRange::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .level_count(1)
+                        .layer_count(1),
+                );
+
+            let image_view = device.create_image_view(&image_view_info)?;
+            device.set_object_name(image_view, &std::format!("Swapchain Image View {i}"));
+
+            self.image_views.push(image_view);
+
+            let render_semaphore = device.create_semaphore(&vk::SemaphoreCreateInfo::default())?;
+            device.set_object_name(
+                render_semaphore,
+                &std::format!("Swapchain Image {i} Render Semaphore"),
+            );
+
+            self.render_semaphores.push(render_semaphore);
+        }
+
+        debug!(std::format!(
+            "Created Swapchain: {} images, extent {}x{}, present mode {:?}.",
+            self.images.len(),
+            extent.width,
+            extent.height,
+            self.present_mode
+        ));
+
+        Ok(())
+    }
+
+    /// #### Description
+    /// Image views and render semaphores, both are per swapchain image.
+    #[inline(always)]
+    fn destroy_image_objects(&mut self, device: &super::DeviceManager) {
+        for image_view in self.image_views.drain(..) {
+            device.destroy_image_view(image_view);
+        }
+        for render_semaphore in self.render_semaphores.drain(..) {
+            device.destroy_semaphore(render_semaphore);
+        }
+    }
+
+    #[inline(always)]
+    fn pick_vsync_mode(device: &super::DeviceManager, vsync_mode: VsyncMode) -> VsyncMode {
+        match &device.present_modes {
+            Some(present_modes) => vsync_mode.supported_or_fifo(present_modes),
+            None => VsyncMode::On,
+        }
+    }
+
+    #[inline(always)]
+    fn pick_extent(
+        surface_capabilities: &vk::SurfaceCapabilitiesKHR,
+        window_extent: vk::Extent2D,
+    ) -> vk::Extent2D {
+        // `u32::MAX` means that the size of the surface is determined by the extent of the swapchain.
+        if surface_capabilities.current_extent.width != u32::MAX {
+            return surface_capabilities.current_extent;
+        }
+
+        let min_extent = surface_capabilities.min_image_extent;
+        let max_extent = surface_capabilities.max_image_extent;
+
+        vk::Extent2D {
+            width: window_extent
+                .width
+                .clamp(min_extent.width, max_extent.width),
+            height: window_extent
+                .height
+                .clamp(min_extent.height, max_extent.height),
+        }
+    }
+}
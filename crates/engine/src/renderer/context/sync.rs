@@ -1,37 +1,151 @@
 use logging::*;
 
-use crate::error::EngineResult;
+use crate::error::{EngineError, EngineResult};
 use ash::vk;
 
-pub struct SynchronizationPrimitivesManager {
+pub struct FrameSynchronization {
     pub available_image_semaphore: vk::Semaphore,
-    pub render_semaphore: vk::Semaphore,
     pub render_fence: vk::Fence,
 }
 
-impl SynchronizationPrimitivesManager {
+impl FrameSynchronization {
     #[inline(always)]
-    pub fn new(device: &super::DeviceManager) -> EngineResult<Self> {
-        debug!("Initializing sync primitives");
-
+    fn new(device: &super::DeviceManager, frame_index: usize) -> EngineResult<Self> {
         let semaphore_info = vk::SemaphoreCreateInfo::default();
         let available_image_semaphore = device.create_semaphore(&semaphore_info)?;
-        let render_semaphore = device.create_semaphore(&semaphore_info)?;
 
         let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
-        let render_fence = device.create_fence(&fence_info)?;
+        let render_fence = match device.create_fence(&fence_info) {
+            Ok(render_fence) => render_fence,
+            Err(e) => {
+                device.destroy_semaphore(available_image_semaphore);
+
+                return Err(e);
+            }
+        };
+
+        device.set_object_name(
+            available_image_semaphore,
+            &std::format!("Frame {frame_index} Available Image Semaphore"),
+        );
+        device.set_object_name(
+            render_fence,
+            &std::format!("Frame {frame_index} Render Fence"),
+        );
 
         Ok(Self {
             available_image_semaphore,
-            render_semaphore,
             render_fence,
         })
     }
 
     #[inline(always)]
-    pub fn destroy_resources(&self, device: &super::DeviceManager) {
+    fn destroy_resources(&self, device: &super::DeviceManager) {
         device.destroy_semaphore(self.available_image_semaphore);
-        device.destroy_semaphore(self.render_semaphore);
         device.destroy_fence(self.render_fence);
     }
 }
+
+pub struct SynchronizationPrimitivesManager {
+    frames: Vec<FrameSynchronization>,
+    current_frame_index: usize,
+}
+
+impl SynchronizationPrimitivesManager {
+    pub const MAX_FRAMES_IN_FLIGHT: usize = 4;
+
+    pub fn new(device: &super::DeviceManager, frames_in_flight: usize) -> EngineResult<Self> {
+        debug!(std::format!(
+            "Initializing sync primitives for {frames_in_flight} frames in flight"
+        ));
+
+        if !(1..=Self::MAX_FRAMES_IN_FLIGHT).contains(&frames_in_flight) {
+            return Err(EngineError::UnknownError(std::format!(
+                "Frames in flight must be in range 1..={}, got {frames_in_flight}",
+                Self::MAX_FRAMES_IN_FLIGHT
+            )));
+        }
+
+        let mut sync_manager = Self {
+            frames: Vec::with_capacity(frames_in_flight),
+            current_frame_index: Default::default(),
+        };
+        for frame_index in 0..frames_in_flight {
+            match FrameSynchronization::new(device, frame_index) {
+                Ok(frame) => sync_manager.frames.push(frame),
+                Err(e) => {
+                    sync_manager.destroy_resources(device);
+
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(sync_manager)
+    }
+
+    #[inline(always)]
+    pub fn frames_in_flight(&self) -> usize {
+        self.frames.len()
+    }
+
+    #[inline(always)]
+    pub fn current_frame_index(&self) -> usize {
+        self.current_frame_index
+    }
+
+    #[inline(always)]
+    pub fn current_frame(&self) -> &FrameSynchronization {
+        &self.frames[self.current_frame_index]
+    }
+
+    #[inline(always)]
+    pub fn frame(&self, frame_index: usize) -> EngineResult<&FrameSynchronization> {
+        self.frames.get(frame_index).ok_or_else(|| {
+            EngineError::UnknownError(std::format!(
+                "Frame index {frame_index} is out of {} frames in flight",
+                self.frames.len()
+            ))
+        })
+    }
+
+    #[inline(always)]
+    pub fn advance_frame(&mut self) {
+        self.current_frame_index = (self.current_frame_index + 1) % self.frames.len();
+    }
+
+    /// #### Description
+    /// Blocks until the GPU finishes the work submitted for the frame with the given index.
+    #[inline(always)]
+    pub fn wait_for_frame(
+        &self,
+        device: &super::DeviceManager,
+        frame_index: usize,
+    ) -> EngineResult<()> {
+        device.wait_for_fences(&[self.frame(frame_index)?.render_fence], u64::MAX)
+    }
+
+    /// #### Description
+    /// Replaces the fence of the current frame with a signaled one, e.g. when it was reset
+    /// but the submission which should signal it failed.
+    pub fn replace_current_fence(&mut self, device: &super::DeviceManager) -> EngineResult<()> {
+        let fence_info = vk::FenceCreateInfo::default().flags(vk::FenceCreateFlags::SIGNALED);
+        let render_fence = device.create_fence(&fence_info)?;
+        device.set_object_name(
+            render_fence,
+            &std::format!("Frame {} Render Fence", self.current_frame_index),
+        );
+
+        let frame = &mut self.frames[self.current_frame_index];
+        device.destroy_fence(std::mem::replace(&mut frame.render_fence, render_fence));
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn destroy_resources(&mut self, device: &super::DeviceManager) {
+        for frame in self.frames.drain(..) {
+            frame.destroy_resources(device);
+        }
+    }
+}
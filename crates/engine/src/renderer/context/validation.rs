@@ -0,0 +1,241 @@
+#[cfg(feature = "dev")]
+use ash::vk;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+#[cfg(feature = "dev")]
+impl ValidationSeverity {
+    #[inline(always)]
+    pub fn from_flags(flags: vk::DebugUtilsMessageSeverityFlagsEXT) -> Self {
+        if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            Self::Error
+        } else if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            Self::Warning
+        } else if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+            Self::Info
+        } else {
+            Self::Verbose
+        }
+    }
+
+    /// #### Description
+    /// Severities the messenger is created with, lower ones aren't even reported by the layers.
+    #[inline(always)]
+    fn flags_from(lowest: Self) -> vk::DebugUtilsMessageSeverityFlagsEXT {
+        [
+            (
+                Self::Verbose,
+                vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE,
+            ),
+            (Self::Info, vk::DebugUtilsMessageSeverityFlagsEXT::INFO),
+            (
+                Self::Warning,
+                vk::DebugUtilsMessageSeverityFlagsEXT::WARNING,
+            ),
+            (Self::Error, vk::DebugUtilsMessageSeverityFlagsEXT::ERROR),
+        ]
+        .into_iter()
+        .filter(|&(severity, _)| severity >= lowest)
+        .fold(
+            vk::DebugUtilsMessageSeverityFlagsEXT::empty(),
+            |flags, (_, severity_flags)| flags | severity_flags,
+        )
+    }
+}
+
+/// #### Description
+/// What happens on the first validation error which isn't ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StrictMode {
+    /// Errors are only logged and counted.
+    #[default]
+    Off,
+    /// The first error is kept in `ValidationStats::first_error`, e.g. for tests to assert on.
+    RecordFailure,
+    /// Panics with the first error at the next `Context::check_validation`, which the engine runs after
+    /// every frame, blocking job and upload. The messenger callback can't unwind, so it only records the error.
+    Panic,
+}
+
+/// #### Description
+/// Optional checks of the validation layer enabled through `VK_EXT_validation_features`,
+/// all of them slow rendering down noticeably.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidationFeatures {
+    /// Reports missing or wrong barriers between commands, queues and submissions.
+    pub synchronization: bool,
+    /// Instruments shaders to catch out of bounds accesses to descriptors and buffers on the GPU.
+    pub gpu_assisted: bool,
+    /// Warns about usage which is valid, but slow or not portable.
+    pub best_practices: bool,
+    /// Logs output of `debugPrintfEXT` in shaders. Older layers can't combine it with `gpu_assisted`.
+    pub debug_printf: bool,
+}
+
+#[cfg(feature = "dev")]
+impl ValidationFeatures {
+    pub fn enables(&self) -> Vec<vk::ValidationFeatureEnableEXT> {
+        let mut enables = Vec::new();
+        if self.synchronization {
+            enables.push(vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION);
+        }
+        if self.gpu_assisted {
+            enables.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED);
+            enables.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT);
+        }
+        if self.best_practices {
+            enables.push(vk::ValidationFeatureEnableEXT::BEST_PRACTICES);
+        }
+        if self.debug_printf {
+            enables.push(vk::ValidationFeatureEnableEXT::DEBUG_PRINTF);
+        }
+
+        enables
+    }
+}
+
+/// #### Description
+/// How messages of the validation layers are filtered and reported, only used with the `dev` feature.
+#[derive(Debug, Clone)]
+pub struct ValidationPolicy {
+    /// Messages with these ID numbers are ignored, e.g. known false positives.
+    pub ignored_message_ids: Vec<i32>,
+    /// Messages with these ID names are ignored, e.g. `VUID-vkCmdDraw-None-02699`.
+    pub ignored_message_names: Vec<String>,
+    /// Lowest severity of general messages (e.g. from the loader) which is logged.
+    pub general_threshold: ValidationSeverity,
+    /// Lowest severity of specification violations which is logged.
+    pub validation_threshold: ValidationSeverity,
+    /// Lowest severity of performance warnings which is logged.
+    pub performance_threshold: ValidationSeverity,
+    /// How many times the same message is logged before it's only counted, `None` logs every repetition.
+    pub repeat_limit: Option<u32>,
+    pub strict: StrictMode,
+    pub features: ValidationFeatures,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self {
+            ignored_message_ids: Default::default(),
+            ignored_message_names: Default::default(),
+            general_threshold: ValidationSeverity::Info,
+            validation_threshold: ValidationSeverity::Info,
+            performance_threshold: ValidationSeverity::Info,
+            repeat_limit: Some(10),
+            strict: Default::default(),
+            features: Default::default(),
+        }
+    }
+}
+
+impl ValidationPolicy {
+    #[inline(always)]
+    pub fn with_ignored_message_id(mut self, message_id: i32) -> Self {
+        self.ignored_message_ids.push(message_id);
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_ignored_message_name(mut self, message_name: &str) -> Self {
+        self.ignored_message_names.push(message_name.to_owned());
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_threshold(mut self, threshold: ValidationSeverity) -> Self {
+        self.general_threshold = threshold;
+        self.validation_threshold = threshold;
+        self.performance_threshold = threshold;
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_repeat_limit(mut self, repeat_limit: Option<u32>) -> Self {
+        self.repeat_limit = repeat_limit;
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_strict(mut self, strict: StrictMode) -> Self {
+        self.strict = strict;
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn with_features(mut self, features: ValidationFeatures) -> Self {
+        self.features = features;
+
+        self
+    }
+}
+
+#[cfg(feature = "dev")]
+impl ValidationPolicy {
+    /// #### Description
+    /// Severities the messenger has to report to apply the policy.
+    pub fn severity_flags(&self) -> vk::DebugUtilsMessageSeverityFlagsEXT {
+        let lowest = self
+            .general_threshold
+            .min(self.validation_threshold)
+            .min(self.performance_threshold);
+        // Shader printf output is reported as information.
+        let lowest = if self.features.debug_printf {
+            lowest.min(ValidationSeverity::Info)
+        } else {
+            lowest
+        };
+
+        ValidationSeverity::flags_from(lowest)
+    }
+
+    #[inline(always)]
+    pub fn threshold(&self, message_type: vk::DebugUtilsMessageTypeFlagsEXT) -> ValidationSeverity {
+        if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION) {
+            self.validation_threshold
+        } else if message_type.contains(vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE) {
+            self.performance_threshold
+        } else {
+            self.general_threshold
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_ignored(&self, message_id: i32, message_name: Option<&str>) -> bool {
+        self.ignored_message_ids.contains(&message_id)
+            || message_name.is_some_and(|message_name| {
+                self.ignored_message_names
+                    .iter()
+                    .any(|ignored_name| ignored_name == message_name)
+            })
+    }
+}
+
+/// #### Description
+/// Counters of messages reported by the validation layers since the Context was created.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationStats {
+    pub verbose: u64,
+    pub info: u64,
+    pub warning: u64,
+    pub error: u64,
+    /// Lines printed by shaders with `debugPrintfEXT`, they aren't counted by severity.
+    pub debug_printf: u64,
+    /// Messages dropped by the ignore lists, they aren't counted by severity.
+    pub ignored: u64,
+    /// Repetitions over `ValidationPolicy::repeat_limit`, counted by severity but not logged.
+    pub suppressed: u64,
+    /// First validation error with `StrictMode::RecordFailure` or `StrictMode::Panic`.
+    pub first_error: Option<String>,
+}
@@ -0,0 +1,1075 @@
+use logging::*;
+
+use crate::error::{EngineError, EngineResult};
+
+use super::context::CommandRecorder;
+
+use std::fmt::Write;
+
+use ash::vk;
+use smallvec::SmallVec;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageHandle(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferHandle(usize);
+
+/// #### Description
+/// Image owned outside of the graph, e.g. the swapchain image or a texture.
+#[derive(Debug, Clone, Copy)]
+pub struct GraphImage {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub extent: vk::Extent2D,
+    pub subresource_range: vk::ImageSubresourceRange,
+    /// Layout the image is in when the graph starts.
+    pub initial_layout: vk::ImageLayout,
+    /// Stages which must finish before the first access, e.g. the stage the acquire semaphore waits at.
+    pub initial_stages: vk::PipelineStageFlags2,
+    /// Layout the image is transitioned to when the graph ends, `Some` also marks the image as an output.
+    pub final_layout: Option<vk::ImageLayout>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GraphBuffer {
+    pub buffer: vk::Buffer,
+    pub is_output: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttachmentLoad {
+    Load,
+    ClearColor([f32; 4]),
+    ClearDepth { depth: f32, stencil: u32 },
+    DontCare,
+}
+
+impl AttachmentLoad {
+    #[inline(always)]
+    fn op(self) -> vk::AttachmentLoadOp {
+        match self {
+            Self::Load => vk::AttachmentLoadOp::LOAD,
+            Self::ClearColor(_) | Self::ClearDepth { .. } => vk::AttachmentLoadOp::CLEAR,
+            Self::DontCare => vk::AttachmentLoadOp::DONT_CARE,
+        }
+    }
+
+    #[inline(always)]
+    fn clear_value(self) -> Option<vk::ClearValue> {
+        match self {
+            Self::ClearColor(float32) => Some(vk::ClearValue {
+                color: vk::ClearColorValue { float32 },
+            }),
+            Self::ClearDepth { depth, stencil } => Some(vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue { depth, stencil },
+            }),
+            Self::Load | Self::DontCare => None,
+        }
+    }
+}
+
+/// #### Description
+/// How an access synchronizes with others, `is_read` means the previous content is used.
+#[derive(Debug, Clone, Copy)]
+struct AccessState {
+    stages: vk::PipelineStageFlags2,
+    access: vk::AccessFlags2,
+    layout: vk::ImageLayout,
+    is_read: bool,
+    is_write: bool,
+}
+
+impl AccessState {
+    #[inline(always)]
+    fn new(
+        stages: vk::PipelineStageFlags2,
+        access: vk::AccessFlags2,
+        layout: vk::ImageLayout,
+        is_read: bool,
+        is_write: bool,
+    ) -> Self {
+        Self {
+            stages,
+            access,
+            layout,
+            is_read,
+            is_write,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ImageAccess {
+    ColorAttachment(AttachmentLoad),
+    DepthAttachment(AttachmentLoad),
+    DepthRead,
+    Sampled(vk::PipelineStageFlags2),
+    StorageRead(vk::PipelineStageFlags2),
+    StorageWrite(vk::PipelineStageFlags2),
+    TransferSrc,
+    TransferDst,
+}
+
+impl ImageAccess {
+    fn state(self) -> AccessState {
+        match self {
+            Self::ColorAttachment(load) => AccessState::new(
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                load == AttachmentLoad::Load,
+                true,
+            ),
+            Self::DepthAttachment(load) => AccessState::new(
+                vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+                vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ
+                    | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                vk::ImageLayout::DEPTH_ATTACHMENT_OPTIMAL,
+                load == AttachmentLoad::Load,
+                true,
+            ),
+            Self::DepthRead => AccessState::new(
+                vk::PipelineStageFlags2::EARLY_FRAGMENT_TESTS
+                    | vk::PipelineStageFlags2::LATE_FRAGMENT_TESTS,
+                vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_READ,
+                vk::ImageLayout::DEPTH_READ_ONLY_OPTIMAL,
+                true,
+                false,
+            ),
+            Self::Sampled(stages) => AccessState::new(
+                stages,
+                vk::AccessFlags2::SHADER_SAMPLED_READ,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                true,
+                false,
+            ),
+            Self::StorageRead(stages) => AccessState::new(
+                stages,
+                vk::AccessFlags2::SHADER_STORAGE_READ,
+                vk::ImageLayout::GENERAL,
+                true,
+                false,
+            ),
+            Self::StorageWrite(stages) => AccessState::new(
+                stages,
+                vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                vk::ImageLayout::GENERAL,
+                true,
+                true,
+            ),
+            Self::TransferSrc => AccessState::new(
+                vk::PipelineStageFlags2::ALL_TRANSFER,
+                vk::AccessFlags2::TRANSFER_READ,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                true,
+                false,
+            ),
+            Self::TransferDst => AccessState::new(
+                vk::PipelineStageFlags2::ALL_TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                false,
+                true,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BufferAccess {
+    Vertex,
+    Index,
+    Indirect,
+    Uniform(vk::PipelineStageFlags2),
+    StorageRead(vk::PipelineStageFlags2),
+    StorageWrite(vk::PipelineStageFlags2),
+    TransferSrc,
+    TransferDst,
+}
+
+impl BufferAccess {
+    fn state(self) -> AccessState {
+        // Buffers have no layout, so every access uses the same one and never transitions.
+        let (stages, access, is_read, is_write) = match self {
+            Self::Vertex => (
+                vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT,
+                vk::AccessFlags2::VERTEX_ATTRIBUTE_READ,
+                true,
+                false,
+            ),
+            Self::Index => (
+                vk::PipelineStageFlags2::INDEX_INPUT,
+                vk::AccessFlags2::INDEX_READ,
+                true,
+                false,
+            ),
+            Self::Indirect => (
+                vk::PipelineStageFlags2::DRAW_INDIRECT,
+                vk::AccessFlags2::INDIRECT_COMMAND_READ,
+                true,
+                false,
+            ),
+            Self::Uniform(stages) => (stages, vk::AccessFlags2::UNIFORM_READ, true, false),
+            Self::StorageRead(stages) => {
+                (stages, vk::AccessFlags2::SHADER_STORAGE_READ, true, false)
+            }
+            Self::StorageWrite(stages) => (
+                stages,
+                vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                true,
+                true,
+            ),
+            Self::TransferSrc => (
+                vk::PipelineStageFlags2::ALL_TRANSFER,
+                vk::AccessFlags2::TRANSFER_READ,
+                true,
+                false,
+            ),
+            Self::TransferDst => (
+                vk::PipelineStageFlags2::ALL_TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                false,
+                true,
+            ),
+        };
+
+        AccessState::new(
+            stages,
+            access,
+            vk::ImageLayout::UNDEFINED,
+            is_read,
+            is_write,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ResourceKey {
+    Image(usize),
+    Buffer(usize),
+}
+
+type RecordPass<'g> = Box<dyn FnOnce(&CommandRecorder) + 'g>;
+
+struct Pass<'g> {
+    name: String,
+    image_accesses: SmallVec<[(ImageHandle, ImageAccess); 4]>,
+    buffer_accesses: SmallVec<[(BufferHandle, BufferAccess); 4]>,
+    has_side_effects: bool,
+    record: Option<RecordPass<'g>>,
+}
+
+impl Pass<'_> {
+    #[inline(always)]
+    fn accesses(&self) -> impl Iterator<Item = (ResourceKey, AccessState)> + '_ {
+        self.image_accesses
+            .iter()
+            .map(|&(image, access)| (ResourceKey::Image(image.0), access.state()))
+            .chain(
+                self.buffer_accesses
+                    .iter()
+                    .map(|&(buffer, access)| (ResourceKey::Buffer(buffer.0), access.state())),
+            )
+    }
+}
+
+/// #### Description
+/// Declares how a pass uses resources, created by `RenderGraph::add_pass`.
+pub struct PassBuilder<'a, 'g> {
+    pass: &'a mut Pass<'g>,
+}
+
+impl<'g> PassBuilder<'_, 'g> {
+    /// #### Description
+    /// Whether the image is read, written or both follows from the access.
+    #[inline(always)]
+    pub fn image(self, image: ImageHandle, access: ImageAccess) -> Self {
+        self.pass.image_accesses.push((image, access));
+
+        self
+    }
+
+    #[inline(always)]
+    pub fn color_attachment(self, image: ImageHandle, load: AttachmentLoad) -> Self {
+        self.image(image, ImageAccess::ColorAttachment(load))
+    }
+
+    #[inline(always)]
+    pub fn depth_attachment(self, image: ImageHandle, load: AttachmentLoad) -> Self {
+        self.image(image, ImageAccess::DepthAttachment(load))
+    }
+
+    #[inline(always)]
+    pub fn buffer(self, buffer: BufferHandle, access: BufferAccess) -> Self {
+        self.pass.buffer_accesses.push((buffer, access));
+
+        self
+    }
+
+    /// #### Description
+    /// The pass is never culled, e.g. it writes something read back by the CPU.
+    #[inline(always)]
+    pub fn with_side_effects(self) -> Self {
+        self.pass.has_side_effects = true;
+
+        self
+    }
+
+    /// #### Description
+    /// Commands of the pass. Passes with attachments are recorded inside dynamic rendering
+    /// with viewport and scissor covering the attachments, barriers are recorded by the graph.
+    #[inline(always)]
+    pub fn record(self, record: impl FnOnce(&CommandRecorder) + 'g) {
+        self.pass.record = Some(Box::new(record));
+    }
+}
+
+/// #### Description
+/// Frame described as passes which declare their resource usage. The graph orders the passes,
+/// culls the ones which don't contribute to outputs and inserts barriers and layout transitions.
+#[derive(Default)]
+pub struct RenderGraph<'g> {
+    images: Vec<(String, GraphImage)>,
+    buffers: Vec<(String, GraphBuffer)>,
+    passes: Vec<Pass<'g>>,
+}
+
+impl<'g> RenderGraph<'g> {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline(always)]
+    pub fn import_image(&mut self, name: &str, image: GraphImage) -> ImageHandle {
+        self.images.push((name.to_owned(), image));
+
+        ImageHandle(self.images.len() - 1)
+    }
+
+    #[inline(always)]
+    pub fn import_buffer(&mut self, name: &str, buffer: GraphBuffer) -> BufferHandle {
+        self.buffers.push((name.to_owned(), buffer));
+
+        BufferHandle(self.buffers.len() - 1)
+    }
+
+    pub fn add_pass(&mut self, name: &str) -> PassBuilder<'_, 'g> {
+        self.passes.push(Pass {
+            name: name.to_owned(),
+            image_accesses: Default::default(),
+            buffer_accesses: Default::default(),
+            has_side_effects: false,
+            record: None,
+        });
+
+        PassBuilder {
+            pass: self.passes.last_mut().unwrap(),
+        }
+    }
+
+    /// #### Description
+    /// Resolves the execution order, culls passes and computes barriers of every pass.
+    pub fn compile(self) -> EngineResult<CompiledRenderGraph<'g>> {
+        for pass in &self.passes {
+            let mut attachment_extents = pass
+                .image_accesses
+                .iter()
+                .filter(|(_, access)| {
+                    matches!(
+                        access,
+                        ImageAccess::ColorAttachment(_) | ImageAccess::DepthAttachment(_)
+                    )
+                })
+                .map(|(image, _)| self.images[image.0].1.extent);
+
+            if let Some(extent) = attachment_extents.next() {
+                if attachment_extents.any(|other_extent| other_extent != extent) {
+                    return Err(EngineError::UnknownError(std::format!(
+                        "Attachments of the pass \"{}\" have different extents",
+                        pass.name
+                    )));
+                }
+            }
+        }
+
+        let order = self.execution_order();
+        let is_alive = self.alive_passes(&order);
+        let (pass_barriers, final_barriers) = self.barriers(&order, &is_alive);
+
+        Ok(CompiledRenderGraph {
+            graph: self,
+            order,
+            is_alive,
+            pass_barriers,
+            final_barriers,
+        })
+    }
+
+    #[inline(always)]
+    fn resource_index(&self, key: ResourceKey) -> usize {
+        match key {
+            ResourceKey::Image(index) => index,
+            ResourceKey::Buffer(index) => self.images.len() + index,
+        }
+    }
+
+    /// #### Description
+    /// Topological order of passes by their dependencies, independent passes keep the declaration order.
+    fn execution_order(&self) -> Vec<usize> {
+        let pass_count = self.passes.len();
+        let mut dependents = vec![Vec::new(); pass_count];
+        let mut dependency_counts = vec![0_usize; pass_count];
+
+        for later in 0..pass_count {
+            for earlier in 0..later {
+                if self.depends_on(later, earlier) {
+                    dependents[earlier].push(later);
+                    dependency_counts[later] += 1;
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(pass_count);
+        let mut ready = (0..pass_count)
+            .filter(|&pass| dependency_counts[pass] == 0)
+            .collect::<std::collections::BTreeSet<_>>();
+        while let Some(pass) = ready.pop_first() {
+            order.push(pass);
+
+            for &dependent in &dependents[pass] {
+                dependency_counts[dependent] -= 1;
+                if dependency_counts[dependent] == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        order
+    }
+
+    /// #### Description
+    /// Passes are dependent when both touch a resource and at least one of them writes it.
+    fn depends_on(&self, later: usize, earlier: usize) -> bool {
+        self.passes[later].accesses().any(|(key, state)| {
+            self.passes[earlier]
+                .accesses()
+                .any(|(earlier_key, earlier_state)| {
+                    earlier_key == key && (state.is_write || earlier_state.is_write)
+                })
+        })
+    }
+
+    /// #### Description
+    /// Walks passes backwards from outputs, a pass is alive if it has side effects
+    /// or writes something which is still needed. Fully overwritten resources stop being needed.
+    fn alive_passes(&self, order: &[usize]) -> Vec<bool> {
+        let mut is_needed = self
+            .images
+            .iter()
+            .map(|(_, image)| image.final_layout.is_some())
+            .chain(self.buffers.iter().map(|(_, buffer)| buffer.is_output))
+            .collect::<Vec<_>>();
+        let mut is_alive = vec![false; self.passes.len()];
+
+        for &pass_index in order.iter().rev() {
+            let pass = &self.passes[pass_index];
+
+            let does_write_needed = pass
+                .accesses()
+                .any(|(key, state)| state.is_write && is_needed[self.resource_index(key)]);
+            if !pass.has_side_effects && !does_write_needed {
+                continue;
+            }
+            is_alive[pass_index] = true;
+
+            for (key, state) in pass.accesses() {
+                if state.is_write && !state.is_read {
+                    is_needed[self.resource_index(key)] = false;
+                }
+            }
+            for (key, state) in pass.accesses() {
+                if state.is_read {
+                    is_needed[self.resource_index(key)] = true;
+                }
+            }
+        }
+
+        is_alive
+    }
+
+    fn barriers(&self, order: &[usize], is_alive: &[bool]) -> (Vec<PassBarriers>, PassBarriers) {
+        let mut states = self
+            .images
+            .iter()
+            .map(|(_, image)| ResourceState {
+                layout: image.initial_layout,
+                last_write: Some((image.initial_stages, vk::AccessFlags2::NONE)),
+                ..Default::default()
+            })
+            .chain(self.buffers.iter().map(|_| Default::default()))
+            .collect::<Vec<_>>();
+
+        let mut pass_barriers = (0..self.passes.len())
+            .map(|_| PassBarriers::default())
+            .collect::<Vec<_>>();
+        for &pass_index in order.iter().filter(|&&pass_index| is_alive[pass_index]) {
+            for (key, state) in self.passes[pass_index].accesses() {
+                let Some((src_stages, src_access, old_layout)) =
+                    states[self.resource_index(key)].access(state)
+                else {
+                    continue;
+                };
+
+                let barriers = &mut pass_barriers[pass_index];
+                match key {
+                    ResourceKey::Image(index) => barriers.image_barriers.push(
+                        vk::ImageMemoryBarrier2::default()
+                            .src_stage_mask(src_stages)
+                            .src_access_mask(src_access)
+                            .dst_stage_mask(state.stages)
+                            .dst_access_mask(state.access)
+                            .old_layout(old_layout)
+                            .new_layout(state.layout)
+                            .image(self.images[index].1.image)
+                            .subresource_range(self.images[index].1.subresource_range),
+                    ),
+                    ResourceKey::Buffer(index) => barriers.buffer_barriers.push(
+                        vk::BufferMemoryBarrier2::default()
+                            .src_stage_mask(src_stages)
+                            .src_access_mask(src_access)
+                            .dst_stage_mask(state.stages)
+                            .dst_access_mask(state.access)
+                            .buffer(self.buffers[index].1.buffer)
+                            .size(vk::WHOLE_SIZE),
+                    ),
+                }
+            }
+        }
+
+        let mut final_barriers = PassBarriers::default();
+        for (state, (_, image)) in states.iter_mut().zip(&self.images) {
+            let Some(final_layout) = image.final_layout else {
+                continue;
+            };
+
+            // Whatever consumes the image next (present, copy) is synchronized by the submission.
+            let final_state = AccessState::new(
+                vk::PipelineStageFlags2::ALL_COMMANDS,
+                vk::AccessFlags2::NONE,
+                final_layout,
+                true,
+                false,
+            );
+            if let Some((src_stages, src_access, old_layout)) = state.access(final_state) {
+                final_barriers.image_barriers.push(
+                    vk::ImageMemoryBarrier2::default()
+                        .src_stage_mask(src_stages)
+                        .src_access_mask(src_access)
+                        .dst_stage_mask(final_state.stages)
+                        .old_layout(old_layout)
+                        .new_layout(final_layout)
+                        .image(image.image)
+                        .subresource_range(image.subresource_range),
+                );
+            }
+        }
+
+        (pass_barriers, final_barriers)
+    }
+}
+
+/// #### Description
+/// Synchronization state of a resource between passes.
+#[derive(Debug, Default, Clone, Copy)]
+struct ResourceState {
+    layout: vk::ImageLayout,
+    last_write: Option<(vk::PipelineStageFlags2, vk::AccessFlags2)>,
+    /// Stages which read the resource since the last write.
+    read_stages: vk::PipelineStageFlags2,
+    /// Stages and accesses to which the last write is already visible.
+    visible_stages: vk::PipelineStageFlags2,
+    visible_access: vk::AccessFlags2,
+}
+
+impl ResourceState {
+    /// #### Description
+    /// Updates the state with the access, returns source stages, source access and old layout
+    /// of the barrier which has to precede the access, if any.
+    fn access(
+        &mut self,
+        state: AccessState,
+    ) -> Option<(vk::PipelineStageFlags2, vk::AccessFlags2, vk::ImageLayout)> {
+        let old_layout = self.layout;
+        let is_layout_transition = state.layout != old_layout;
+        self.layout = state.layout;
+
+        // Layout transitions are writes too, so they are ordered after reads and the last write.
+        if state.is_write || is_layout_transition {
+            let (write_stages, write_access) = self.last_write.unwrap_or_default();
+            let src_stages = write_stages | self.read_stages;
+            let needs_barrier = is_layout_transition || !src_stages.is_empty();
+
+            let access = if state.is_write {
+                state.access
+            } else {
+                vk::AccessFlags2::NONE
+            };
+            self.last_write = Some((state.stages, access));
+            self.read_stages = if state.is_write {
+                vk::PipelineStageFlags2::NONE
+            } else {
+                state.stages
+            };
+            self.visible_stages = state.stages;
+            self.visible_access = state.access;
+
+            return needs_barrier.then_some((src_stages, write_access, old_layout));
+        }
+
+        self.read_stages |= state.stages;
+
+        let (write_stages, write_access) = self.last_write?;
+        if self.visible_stages.contains(state.stages) && self.visible_access.contains(state.access)
+        {
+            return None;
+        }
+
+        self.visible_stages |= state.stages;
+        self.visible_access |= state.access;
+
+        Some((write_stages, write_access, old_layout))
+    }
+}
+
+#[derive(Default)]
+struct PassBarriers {
+    buffer_barriers: Vec<vk::BufferMemoryBarrier2<'static>>,
+    image_barriers: Vec<vk::ImageMemoryBarrier2<'static>>,
+}
+
+impl PassBarriers {
+    #[inline(always)]
+    fn record(&self, recorder: &CommandRecorder) {
+        if !self.buffer_barriers.is_empty() || !self.image_barriers.is_empty() {
+            recorder.pipeline_barrier(&self.buffer_barriers, &self.image_barriers);
+        }
+    }
+}
+
+pub struct CompiledRenderGraph<'g> {
+    graph: RenderGraph<'g>,
+    order: Vec<usize>,
+    is_alive: Vec<bool>,
+    pass_barriers: Vec<PassBarriers>,
+    final_barriers: PassBarriers,
+}
+
+impl CompiledRenderGraph<'_> {
+    /// #### Description
+    /// Records alive passes with their barriers into the command buffer.
+    pub fn execute(mut self, recorder: &CommandRecorder) {
+        for &pass_index in &self.order {
+            if !self.is_alive[pass_index] {
+                continue;
+            }
+
+            let _label = recorder.debug_label(&self.graph.passes[pass_index].name);
+            self.pass_barriers[pass_index].record(recorder);
+            let _zone = recorder.gpu_zone(&self.graph.passes[pass_index].name);
+
+            let pass = &mut self.graph.passes[pass_index];
+            let record = pass.record.take();
+
+            let mut color_attachments = SmallVec::<[_; 4]>::new();
+            let mut depth_attachment = None;
+            let mut render_extent = None;
+            for &(image, access) in &pass.image_accesses {
+                let load = match access {
+                    ImageAccess::ColorAttachment(load) | ImageAccess::DepthAttachment(load) => load,
+                    _ => continue,
+                };
+                let graph_image = &self.graph.images[image.0].1;
+                render_extent = Some(graph_image.extent);
+
+                let mut attachment = vk::RenderingAttachmentInfo::default()
+                    .image_view(graph_image.view)
+                    .image_layout(access.state().layout)
+                    .load_op(load.op())
+                    .store_op(vk::AttachmentStoreOp::STORE);
+                if let Some(clear_value) = load.clear_value() {
+                    attachment = attachment.clear_value(clear_value);
+                }
+
+                match access {
+                    ImageAccess::DepthAttachment(_) => depth_attachment = Some(attachment),
+                    _ => color_attachments.push(attachment),
+                }
+            }
+
+            let Some(extent) = render_extent else {
+                if let Some(record) = record {
+                    record(recorder);
+                }
+
+                continue;
+            };
+
+            let mut rendering_info = vk::RenderingInfo::default()
+                .render_area(vk::Rect2D::default().extent(extent))
+                .layer_count(1)
+                .color_attachments(&color_attachments);
+            if let Some(depth_attachment) = &depth_attachment {
+                rendering_info = rendering_info.depth_attachment(depth_attachment);
+            }
+
+            recorder.begin_rendering(&rendering_info);
+            if let Some(record) = record {
+                recorder.set_viewport_and_scissor(extent);
+                record(recorder);
+            }
+            recorder.end_rendering();
+        }
+
+        self.final_barriers.record(recorder);
+    }
+
+    /// #### Description
+    /// Names of passes which were culled because nothing depends on them.
+    pub fn culled_passes(&self) -> Vec<&str> {
+        self.graph
+            .passes
+            .iter()
+            .zip(&self.is_alive)
+            .filter(|(_, &is_alive)| !is_alive)
+            .map(|(pass, _)| pass.name.as_str())
+            .collect()
+    }
+
+    /// #### Description
+    /// Graph in Graphviz DOT format. Passes are boxes numbered in execution order, culled passes are dashed,
+    /// outputs are double octagons.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph RenderGraph {\n    rankdir=LR;\n");
+
+        let resources = self
+            .graph
+            .images
+            .iter()
+            .enumerate()
+            .map(|(i, (name, image))| (ResourceKey::Image(i), name, image.final_layout.is_some()))
+            .chain(
+                self.graph
+                    .buffers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (name, buffer))| (ResourceKey::Buffer(i), name, buffer.is_output)),
+            );
+        for (key, name, is_output) in resources {
+            let shape = if is_output {
+                "doubleoctagon"
+            } else {
+                "ellipse"
+            };
+            writeln!(
+                dot,
+                "    {} [label=\"{}\", shape={shape}];",
+                Self::dot_id(key),
+                Self::dot_label(name)
+            )
+            .unwrap();
+        }
+
+        for (position, &pass_index) in self.order.iter().enumerate() {
+            let pass = &self.graph.passes[pass_index];
+            let style = if self.is_alive[pass_index] {
+                "filled"
+            } else {
+                "dashed"
+            };
+            writeln!(
+                dot,
+                "    pass_{pass_index} [label=\"{position}: {}\", shape=box, style={style}, fillcolor=lightblue];",
+                Self::dot_label(&pass.name)
+            )
+            .unwrap();
+
+            for (key, state) in pass.accesses() {
+                if state.is_read {
+                    writeln!(dot, "    {} -> pass_{pass_index};", Self::dot_id(key)).unwrap();
+                }
+                if state.is_write {
+                    writeln!(dot, "    pass_{pass_index} -> {};", Self::dot_id(key)).unwrap();
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// #### Description
+    /// Logs the graph in DOT format.
+    #[inline(always)]
+    pub fn dump(&self) {
+        debug!(self.to_dot());
+    }
+
+    #[inline(always)]
+    fn dot_id(key: ResourceKey) -> String {
+        match key {
+            ResourceKey::Image(index) => std::format!("image_{index}"),
+            ResourceKey::Buffer(index) => std::format!("buffer_{index}"),
+        }
+    }
+
+    /// #### Description
+    /// Escapes the name so it can be placed inside a quoted DOT string.
+    #[inline(always)]
+    fn dot_label(name: &str) -> String {
+        name.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph_image(final_layout: Option<vk::ImageLayout>) -> GraphImage {
+        GraphImage {
+            image: vk::Image::null(),
+            view: vk::ImageView::null(),
+            extent: vk::Extent2D {
+                width: 64,
+                height: 64,
+            },
+            subresource_range: vk::ImageSubresourceRange::default()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .level_count(1)
+                .layer_count(1),
+            initial_layout: vk::ImageLayout::UNDEFINED,
+            initial_stages: vk::PipelineStageFlags2::NONE,
+            final_layout,
+        }
+    }
+
+    fn graph_buffer(is_output: bool) -> GraphBuffer {
+        GraphBuffer {
+            buffer: vk::Buffer::null(),
+            is_output,
+        }
+    }
+
+    const CLEAR: AttachmentLoad = AttachmentLoad::ClearColor([0.0; 4]);
+
+    #[test]
+    fn execution_order_follows_dependencies() {
+        let mut graph = RenderGraph::new();
+        let gbuffer = graph.import_image("G-Buffer", graph_image(None));
+        let output = graph.import_image(
+            "Output",
+            graph_image(Some(vk::ImageLayout::PRESENT_SRC_KHR)),
+        );
+        let particles = graph.import_buffer("Particles", graph_buffer(true));
+
+        graph.add_pass("Geometry").color_attachment(gbuffer, CLEAR);
+        graph.add_pass("Simulation").buffer(
+            particles,
+            BufferAccess::StorageWrite(vk::PipelineStageFlags2::COMPUTE_SHADER),
+        );
+        graph
+            .add_pass("Lighting")
+            .image(
+                gbuffer,
+                ImageAccess::Sampled(vk::PipelineStageFlags2::FRAGMENT_SHADER),
+            )
+            .color_attachment(output, CLEAR);
+
+        let order = graph.execution_order();
+
+        assert_eq!(order, vec![0, 1, 2]);
+        assert!(graph.depends_on(2, 0));
+        assert!(!graph.depends_on(1, 0));
+        assert!(!graph.depends_on(2, 1));
+    }
+
+    #[test]
+    fn readers_of_the_same_resource_are_independent() {
+        let mut graph = RenderGraph::new();
+        let texture = graph.import_image("Texture", graph_image(None));
+
+        graph.add_pass("First Reader").image(
+            texture,
+            ImageAccess::Sampled(vk::PipelineStageFlags2::FRAGMENT_SHADER),
+        );
+        graph.add_pass("Second Reader").image(
+            texture,
+            ImageAccess::Sampled(vk::PipelineStageFlags2::FRAGMENT_SHADER),
+        );
+
+        assert!(!graph.depends_on(1, 0));
+    }
+
+    #[test]
+    fn passes_not_contributing_to_outputs_are_culled() {
+        let mut graph = RenderGraph::new();
+        let unused = graph.import_image("Unused", graph_image(None));
+        let output = graph.import_image(
+            "Output",
+            graph_image(Some(vk::ImageLayout::PRESENT_SRC_KHR)),
+        );
+        let readback = graph.import_buffer("Readback", graph_buffer(false));
+
+        graph.add_pass("Unused").color_attachment(unused, CLEAR);
+        graph.add_pass("Main").color_attachment(output, CLEAR);
+        graph
+            .add_pass("Readback")
+            .buffer(readback, BufferAccess::TransferDst)
+            .with_side_effects();
+
+        let order = graph.execution_order();
+        let is_alive = graph.alive_passes(&order);
+
+        assert_eq!(is_alive, vec![false, true, true]);
+    }
+
+    #[test]
+    fn overwritten_results_are_culled() {
+        let mut graph = RenderGraph::new();
+        let output = graph.import_image(
+            "Output",
+            graph_image(Some(vk::ImageLayout::PRESENT_SRC_KHR)),
+        );
+
+        graph
+            .add_pass("Overwritten")
+            .color_attachment(output, CLEAR);
+        graph.add_pass("Final").color_attachment(output, CLEAR);
+
+        let order = graph.execution_order();
+        let is_alive = graph.alive_passes(&order);
+
+        assert_eq!(is_alive, vec![false, true]);
+    }
+
+    #[test]
+    fn read_results_keep_their_producers_alive() {
+        let mut graph = RenderGraph::new();
+        let scene = graph.import_image("Scene", graph_image(None));
+        let output = graph.import_image(
+            "Output",
+            graph_image(Some(vk::ImageLayout::PRESENT_SRC_KHR)),
+        );
+
+        graph.add_pass("Scene").color_attachment(scene, CLEAR);
+        graph
+            .add_pass("Blend")
+            .color_attachment(output, CLEAR)
+            .color_attachment(scene, AttachmentLoad::Load);
+        graph
+            .add_pass("Tone Mapping")
+            .image(
+                scene,
+                ImageAccess::Sampled(vk::PipelineStageFlags2::FRAGMENT_SHADER),
+            )
+            .color_attachment(output, AttachmentLoad::Load);
+
+        let order = graph.execution_order();
+        let is_alive = graph.alive_passes(&order);
+
+        assert_eq!(is_alive, vec![true, true, true]);
+    }
+
+    #[test]
+    fn read_after_write_waits_for_the_write() {
+        let mut state = ResourceState::default();
+
+        let write = BufferAccess::StorageWrite(vk::PipelineStageFlags2::COMPUTE_SHADER).state();
+        assert_eq!(state.access(write), None);
+
+        let read = BufferAccess::StorageRead(vk::PipelineStageFlags2::VERTEX_SHADER).state();
+        assert_eq!(
+            state.access(read),
+            Some((
+                vk::PipelineStageFlags2::COMPUTE_SHADER,
+                vk::AccessFlags2::SHADER_STORAGE_READ | vk::AccessFlags2::SHADER_STORAGE_WRITE,
+                vk::ImageLayout::UNDEFINED,
+            ))
+        );
+        // The write is already visible to the stage.
+        assert_eq!(state.access(read), None);
+    }
+
+    #[test]
+    fn write_after_read_waits_for_the_read() {
+        let mut state = ResourceState::default();
+
+        let read = BufferAccess::Vertex.state();
+        assert_eq!(state.access(read), None);
+
+        let write = BufferAccess::TransferDst.state();
+        assert_eq!(
+            state.access(write),
+            Some((
+                vk::PipelineStageFlags2::VERTEX_ATTRIBUTE_INPUT,
+                vk::AccessFlags2::NONE,
+                vk::ImageLayout::UNDEFINED,
+            ))
+        );
+    }
+
+    #[test]
+    fn layout_changes_transition_the_image() {
+        let mut state = ResourceState::default();
+
+        let attachment = ImageAccess::ColorAttachment(CLEAR).state();
+        assert_eq!(
+            state.access(attachment),
+            Some((
+                vk::PipelineStageFlags2::NONE,
+                vk::AccessFlags2::NONE,
+                vk::ImageLayout::UNDEFINED,
+            ))
+        );
+
+        let sampled = ImageAccess::Sampled(vk::PipelineStageFlags2::FRAGMENT_SHADER).state();
+        assert_eq!(
+            state.access(sampled),
+            Some((
+                vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags2::COLOR_ATTACHMENT_READ | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+                vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            ))
+        );
+        assert_eq!(state.layout, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL);
+
+        // Reading in the same layout from another stage only makes the transition visible.
+        let compute = ImageAccess::Sampled(vk::PipelineStageFlags2::COMPUTE_SHADER).state();
+        assert_eq!(
+            state.access(compute),
+            Some((
+                vk::PipelineStageFlags2::FRAGMENT_SHADER,
+                vk::AccessFlags2::NONE,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ))
+        );
+    }
+
+    #[test]
+    fn dot_labels_are_escaped() {
+        let mut graph = RenderGraph::new();
+        let output = graph.import_image(
+            "Output \"HDR\"",
+            graph_image(Some(vk::ImageLayout::PRESENT_SRC_KHR)),
+        );
+        graph.add_pass("C:\\Pass").color_attachment(output, CLEAR);
+
+        let dot = graph.compile().unwrap().to_dot();
+
+        assert!(dot.contains("label=\"Output \\\"HDR\\\"\""));
+        assert!(dot.contains("label=\"0: C:\\\\Pass\""));
+    }
+}
@@ -0,0 +1,41 @@
+//! Frames on a real device, the tests are skipped on machines without Vulkan.
+
+use engine::{vk, Engine, QueueKind, RendererConfig};
+
+#[test]
+fn frame_failing_after_begin_frame_does_not_block_the_next_one() {
+    let Ok(mut engine) = Engine::new_headless(64, 64, RendererConfig::default()) else {
+        eprintln!("No Vulkan device is available, skipping.");
+
+        return;
+    };
+    let context = engine.context_mut();
+
+    // Abandoned between `begin_frame` and `end_frame`, e.g. by an error while recording.
+    let image_index = context.begin_frame().unwrap().unwrap();
+    assert_eq!(context.begin_frame().unwrap(), Some(image_index));
+
+    // Command buffers of different queues fail the submission, after the fence would have been reset.
+    let graphics = context
+        .record_commands(QueueKind::Graphics, vk::CommandBufferLevel::PRIMARY, None)
+        .unwrap()
+        .finish()
+        .unwrap();
+    let compute = context
+        .record_commands(QueueKind::Compute, vk::CommandBufferLevel::PRIMARY, None)
+        .unwrap()
+        .finish()
+        .unwrap();
+    assert!(context
+        .end_frame(image_index, &[graphics, compute])
+        .is_err());
+
+    // Would wait forever on a fence nothing signals.
+    for _ in 0..=context.frames_in_flight() {
+        engine.draw().unwrap();
+    }
+    let context = engine.context();
+    for frame_index in 0..context.frames_in_flight() {
+        context.wait_for_frame(frame_index).unwrap();
+    }
+}